@@ -42,15 +42,142 @@ testname="Running executable without arguments returns an error."
 $exe 2>/dev/null
 expect_failure "$testname"
 
-testname="Running executable with generation parameters but no output fails."
-$exe --params ./integration_tests/example_params.json 2>/dev/null
+params="./integration_tests/example_params.json"
+output="./integration_tests/example_output.json"
+binary_output="./integration_tests/example_output.bin"
+extended_output="./integration_tests/example_extended.bin"
+csv_output="./integration_tests/example_output.csv"
+resumed_output="./integration_tests/example_resumed.bin"
+
+testname="Generating with generation parameters but no output fails."
+$exe generate --params $params 2>/dev/null
+expect_failure "$testname"
+
+testname="Generating with the old flat arguments fails."
+$exe --params $params --out $output 2>/dev/null
 expect_failure "$testname"
 
-testname="Running executable with generation parameters and output creates the output."
-rm -rf ./integration_tests/example_output.json
-$exe --params ./integration_tests/example_params.json --out ./integration_tests/example_output.json >/dev/null
+testname="Generating with generation parameters and output creates the output."
+rm -rf $output
+$exe generate --params $params --out $output -q >/dev/null
+expect_success "$testname"
+expect_file $output "$testname"
+
+testname="Generating in the binary format creates the output."
+rm -rf $binary_output
+$exe generate --params $params --out $binary_output --format binary -q >/dev/null
+expect_success "$testname"
+expect_file $binary_output "$testname"
+
+testname="Generating with JSON progress reports the end of the run on stderr."
+rm -rf $output
+$exe generate --params $params --out $output --progress json 2>&1 >/dev/null | grep '"event":"finished"' >/dev/null
+expect_success "$testname"
+
+testname="Generating quietly reports nothing on stderr."
+rm -rf $output
+if [ -n "$($exe generate --params $params --out $output -q 2>&1 >/dev/null)" ]; then
+    echo "Test:"
+    echo $testname
+    echo "failed."
+    exit 1
+fi
+expect_file $output "$testname"
+
+testname="Generating into a missing directory with JSON progress reports the failure."
+$exe generate --params $params --out ./integration_tests/missing/output.json --progress json 2>&1 >/dev/null | grep '"event":"failed"' >/dev/null
+expect_success "$testname"
+$exe generate --params $params --out ./integration_tests/missing/output.json --progress json 2>/dev/null
+expect_failure "$testname"
+
+testname="A dry run prints an estimate without creating any output."
+rm -rf ./integration_tests/dry_run_output.json
+$exe generate --params $params --dry-run --out ./integration_tests/dry_run_output.json | grep '"chunks"' >/dev/null
+expect_success "$testname"
+if [ -f ./integration_tests/dry_run_output.json ]; then
+    echo "Test:"
+    echo $testname
+    echo "failed."
+    exit 1
+fi
+
+testname="A dry run does not need an output file."
+$exe generate --params $params --dry-run >/dev/null
+expect_success "$testname"
+
+testname="Validating the example parameters succeeds."
+$exe validate --params $params >/dev/null
+expect_success "$testname"
+
+testname="Validating parameters without a chunk size fails."
+echo '{"observer_position_in_pc": [0, 0, 0], "apparent_magnitude_limit": 4.0, "max_distance_in_pc": 50.0, "chunksize_in_pc": 0.0}' >./integration_tests/invalid_params.json
+$exe validate --params ./integration_tests/invalid_params.json 2>/dev/null
+expect_failure "$testname"
+
+testname="Inspecting a catalogue prints its generation parameters."
+$exe inspect --catalogue $output | grep '"max_distance_in_pc": 50.0' >/dev/null
+expect_success "$testname"
+
+testname="Inspecting a binary catalogue prints its generation parameters."
+$exe inspect --catalogue $binary_output | grep '"chunksize_in_pc": 15.0' >/dev/null
 expect_success "$testname"
-expect_file "./integration_tests/example_output.json" "$testname"
+
+testname="Inspecting a file that is not a catalogue fails."
+$exe inspect --catalogue $params 2>/dev/null
+expect_failure "$testname"
+
+testname="Summarising a catalogue reports that it is complete."
+$exe stats --catalogue $output | grep '"complete": true' >/dev/null
+expect_success "$testname"
+
+testname="Extending a catalogue creates a catalogue with the larger distance."
+rm -rf $extended_output
+$exe extend --catalogue $binary_output --max-distance 80 --out $extended_output -q >/dev/null
+expect_success "$testname"
+expect_file $extended_output "$testname"
+$exe inspect --catalogue $extended_output | grep '"max_distance_in_pc": 80.0' >/dev/null
+expect_success "$testname"
+
+testname="Resuming an interrupted run gives the same catalogue as an uninterrupted run."
+rm -rf $resumed_output $resumed_output.checkpoint $resumed_output.index
+$exe generate --params ./integration_tests/resume_params.json --out $resumed_output --format binary --checkpoint-interval 0 --stop-after-chunks 100 -q 2>/dev/null
+expect_failure "$testname"
+expect_file $resumed_output.checkpoint "$testname"
+expect_file $resumed_output.index "$testname"
+test $(stat -c %s $resumed_output) -lt $(stat -c %s $extended_output)
+expect_success "$testname"
+$exe generate --params ./integration_tests/resume_params.json --out $resumed_output --format binary --resume -q
+expect_success "$testname"
+cmp -s $resumed_output $extended_output
+expect_success "$testname"
+if [ -f $resumed_output.checkpoint ] || [ -f $resumed_output.index ]; then
+    echo "Test:"
+    echo $testname
+    echo "failed."
+    exit 1
+fi
+
+testname="Resuming without a checkpoint fails."
+$exe generate --params $params --out $resumed_output --resume -q 2>/dev/null
+expect_failure "$testname"
+
+testname="Extending a catalogue to a smaller distance fails."
+$exe extend --catalogue $output --max-distance 20 --out ./integration_tests/shrunk.json 2>/dev/null
+expect_failure "$testname"
+
+testname="Converting a catalogue creates the output in the new format."
+rm -rf $csv_output
+$exe convert --catalogue $binary_output --out $csv_output --format csv --columns x_pc,y_pc,z_pc >/dev/null
+expect_success "$testname"
+expect_file $csv_output "$testname"
+head -n 1 $csv_output | grep '^x_pc,y_pc,z_pc$' >/dev/null
+expect_success "$testname"
+
+testname="Querying a binary catalogue succeeds."
+$exe query --catalogue $binary_output --cone 0,0,180 >/dev/null
+expect_success "$testname"
+
+rm -f ./integration_tests/invalid_params.json ./integration_tests/shrunk.json
 
 echo "All integration tests passed."
 exit 0
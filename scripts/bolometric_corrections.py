@@ -0,0 +1,190 @@
+#!/usr/bin/env python3
+"""Writes data/bolometric_corrections.csv, a synthetic placeholder for a published grid of bolometric corrections.
+
+The spectrum of a star is approximated by a blackbody whose temperature differs from the
+effective temperature where metal lines and surface gravity change the colours of real stars.
+See the header written by this script and the section "Photometric Bands" of
+Documentation/Generation_Algorithm.md for the approximation and its limitations.
+"""
+
+import bisect
+import math
+import os
+
+H = 6.62607015e-34
+C = 2.99792458e8
+K_B = 1.380649e-23
+
+SOLAR_EFFECTIVE_TEMPERATURE = 5772.0
+SOLAR_BOLOMETRIC_MAGNITUDE = 4.8
+SOLAR_LOG_G = 4.438
+
+# Name, center and FWHM in nm, absolute magnitude of the sun.
+BANDS = [
+    ("johnson_b", 445.0, 94.0, 5.44),
+    ("johnson_v", 551.0, 88.0, 4.81),
+    ("gaia_g", 640.0, 440.0, 4.67),
+    ("gaia_bp", 511.0, 234.0, 5.03),
+    ("gaia_rp", 777.0, 296.0, 4.20),
+]
+
+# Wavelength range and step of the integration over the passbands, in nm.
+SHORTEST_WAVELENGTH = 200
+LONGEST_WAVELENGTH = 2000
+WAVELENGTH_STEP = 2
+
+TEMPERATURES = [round(2000.0 * 50.0 ** (i / 60)) for i in range(61)]
+LOG_GS = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 9.0]
+METALLICITIES = [-3.0, -2.0, -1.0, -0.5, 0.0, 0.5, 1.0]
+
+# Range of the colour calibration of Alonso et al. (1996).
+COOLEST_CALIBRATED = 4000.0
+HOTTEST_CALIBRATED = 8000.0
+# Above this temperature, metal lines no longer shift the colours.
+HOTTEST_SHIFTED = 12000.0
+LOWEST_METALLICITY = -3.0
+HIGHEST_METALLICITY = 0.5
+# B-V reddening per dex of decreasing surface gravity, from K giants (log g = 2.5) being about
+# 0.06 mag redder than K dwarfs of the same effective temperature.
+REDDENING_PER_DEX_OF_GRAVITY = 0.06 / (SOLAR_LOG_G - 2.5)
+# Range of the blackbody temperatures that reproduce the colours of the stars.
+COOLEST_BLACKBODY = 1000.0
+HOTTEST_BLACKBODY = 200000.0
+LOWEST_SHIFTED_LOG_G = 0.0
+HIGHEST_SHIFTED_LOG_G = SOLAR_LOG_G
+
+
+def planck(wavelength_nm, temperature):
+    wavelength = wavelength_nm * 1e-9
+    exponent = H * C / (wavelength * K_B * temperature)
+    if exponent > 700.0:
+        return 0.0
+    return 1.0 / (wavelength**5 * math.expm1(exponent))
+
+
+def band_signal(temperature, center, fwhm):
+    """Photons counted in a Gaussian passband per unit of bolometric flux, up to a constant."""
+    sigma = fwhm / (2.0 * math.sqrt(2.0 * math.log(2.0)))
+    signal = 0.0
+    for i in range(SHORTEST_WAVELENGTH, LONGEST_WAVELENGTH, WAVELENGTH_STEP):
+        wavelength = i + 0.5 * WAVELENGTH_STEP
+        weight = math.exp(-0.5 * ((wavelength - center) / sigma) ** 2)
+        signal += weight * planck(wavelength, temperature) * wavelength * WAVELENGTH_STEP
+    return signal / temperature**4
+
+
+def dwarf_b_minus_v(theta, metallicity):
+    """B-V of a dwarf with theta = 5040 K / T_eff, inverted from Alonso et al. (1996), A&A 313, 873:
+    theta = 0.541 + 0.533 (B-V) + 0.007 (B-V)^2 - 0.019 (B-V) [Fe/H] - 0.047 [Fe/H] - 0.011 [Fe/H]^2.
+    """
+    a = 0.007
+    b = 0.533 - 0.019 * metallicity
+    c = 0.541 - 0.047 * metallicity - 0.011 * metallicity**2 - theta
+    return (-b + math.sqrt(b * b - 4.0 * a * c)) / (2.0 * a)
+
+
+def solar_dwarf_theta(b_minus_v):
+    return 0.541 + 0.533 * b_minus_v + 0.007 * b_minus_v**2
+
+
+def colour_shift(temperature, log_g, metallicity):
+    """The change of B-V of a star with respect to a solar metallicity dwarf of the same effective temperature."""
+    clamped = min(max(temperature, COOLEST_CALIBRATED), HOTTEST_CALIBRATED)
+    metallicity = min(max(metallicity, LOWEST_METALLICITY), HIGHEST_METALLICITY)
+    log_g = min(max(log_g, LOWEST_SHIFTED_LOG_G), HIGHEST_SHIFTED_LOG_G)
+    theta = 5040.0 / clamped
+    shift = dwarf_b_minus_v(theta, metallicity) - dwarf_b_minus_v(theta, 0.0)
+    shift += REDDENING_PER_DEX_OF_GRAVITY * (SOLAR_LOG_G - log_g)
+    if temperature > HOTTEST_CALIBRATED:
+        fade = math.log(HOTTEST_SHIFTED / temperature) / math.log(HOTTEST_SHIFTED / HOTTEST_CALIBRATED)
+        shift *= max(fade, 0.0)
+    return shift
+
+
+def blackbody_b_minus_v(temperature):
+    b = BANDS[0]
+    v = BANDS[1]
+    return 2.5 * math.log10(band_signal(temperature, v[1], v[2]) / band_signal(temperature, b[1], b[2]))
+
+
+class ColourTemperatures:
+    """The temperature of the blackbody with the colours of a star.
+
+    The blackbody temperature is chosen such that the B-V of the blackbody changes by the same
+    amount as the B-V of the star with respect to a solar metallicity dwarf.
+    """
+
+    def __init__(self):
+        steps = 4000
+        self.log_temperatures = [
+            math.log(COOLEST_BLACKBODY) + i / steps * math.log(HOTTEST_BLACKBODY / COOLEST_BLACKBODY)
+            for i in range(steps + 1)
+        ]
+        # B-V decreases with temperature, so the colours are stored with reversed sign to be ascending.
+        self.negative_colours = [-blackbody_b_minus_v(math.exp(t)) for t in self.log_temperatures]
+
+    def __call__(self, temperature, log_g, metallicity):
+        shift = colour_shift(temperature, log_g, metallicity)
+        if shift == 0.0:
+            return temperature
+        target = -(blackbody_b_minus_v(temperature) + shift)
+        index = bisect.bisect_left(self.negative_colours, target)
+        index = min(max(index, 1), len(self.negative_colours) - 1)
+        lower = self.negative_colours[index - 1]
+        upper = self.negative_colours[index]
+        weight = (target - lower) / (upper - lower)
+        log_temperature = self.log_temperatures[index - 1] + weight * (
+            self.log_temperatures[index] - self.log_temperatures[index - 1]
+        )
+        return math.exp(log_temperature)
+
+
+def bolometric_corrections(temperature, log_g, metallicity, colour_temperature, zero_points):
+    shape = colour_temperature(temperature, log_g, metallicity)
+    return [
+        2.5 * math.log10(band_signal(shape, center, fwhm)) + zero_point
+        for (_, center, fwhm, _), zero_point in zip(BANDS, zero_points)
+    ]
+
+
+def main():
+    colour_temperature = ColourTemperatures()
+    zero_points = [
+        SOLAR_BOLOMETRIC_MAGNITUDE - magnitude - 2.5 * math.log10(band_signal(SOLAR_EFFECTIVE_TEMPERATURE, center, fwhm))
+        for _, center, fwhm, magnitude in BANDS
+    ]
+    path = os.path.join(os.path.dirname(__file__), "..", "data", "bolometric_corrections.csv")
+    with open(path, "w") as file:
+        file.write(
+            """# Bolometric corrections BC = M_bol - M_band, written by scripts/bolometric_corrections.py.
+#
+# SYNTHETIC PLACEHOLDER: this is an approximation, not a published grid of model atmospheres.
+# The photons of a blackbody are counted from 200 nm to 2000 nm with Gaussian approximations of the passbands
+# (center / FWHM in nm: Johnson B 445/94, Johnson V 551/88, Gaia G 640/440, Gaia BP 511/234, Gaia RP 777/296),
+# with zero points chosen such that the sun (T_eff = 5772 K, log g = 4.438, [Fe/H] = 0, M_bol = 4.8) has
+# M_B = 5.44, M_V = 4.81, M_G = 4.67, M_BP = 5.03 and M_RP = 4.20.
+# Metallicity and surface gravity change the temperature of the blackbody, such that its B-V changes like that of a star:
+# - [Fe/H] shifts the B-V colour as in the dwarf calibration of Alonso et al. (1996), A&A 313, 873,
+#   which holds from 4000 K to 8000 K and [Fe/H] from -3 to 0.5. The shift is clamped outside of these ranges
+#   and fades out between 8000 K and 12000 K.
+# - Surface gravities below the solar one redden B-V by 0.031 mag per dex, such that K giants are 0.06 mag redder
+#   than K dwarfs of the same effective temperature.
+# Molecular bands of cool stars and the Balmer jump of hot stars are not modelled.
+# Replace this file with tabulated bolometric corrections of the same layout, e.g. from MIST, for more realistic magnitudes.
+#
+# Every combination of effective temperature, log g and metallicity must be present.
+effective_temperature_in_k,log_g,metallicity_in_dex,bc_johnson_b,bc_johnson_v,bc_gaia_g,bc_gaia_bp,bc_gaia_rp
+"""
+        )
+        for temperature in TEMPERATURES:
+            for log_g in LOG_GS:
+                for metallicity in METALLICITIES:
+                    corrections = bolometric_corrections(
+                        temperature, log_g, metallicity, colour_temperature, zero_points
+                    )
+                    values = ",".join(f"{value:.4f}" for value in corrections)
+                    file.write(f"{temperature},{log_g:.1f},{metallicity:.1f},{values}\n")
+
+
+if __name__ == "__main__":
+    main()
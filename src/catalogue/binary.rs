@@ -0,0 +1,881 @@
+use std::{
+    error::Error,
+    ffi::OsString,
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    colour::Colour,
+    coordinates::{
+        EclipticCoordinates, EquatorialCoordinates, GalacticCoordinates, SkyCoordinates,
+    },
+    generation_statistics::GenerationStatistics,
+    generator::GeneratedChunk,
+    kinematics::Astrometry,
+    multiplicity::Orbit,
+    photometry::Magnitudes,
+    population::{Population, Subpopulation},
+    star::Star,
+    variability::{Eclipse, Variability, VariabilityClass},
+};
+
+use super::{index::IndexEntry, CatalogueHeader, CatalogueWriter, Record, WriterState};
+
+/// The first bytes of every binary catalogue.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#binary-format>
+pub(crate) const MAGIC: &[u8; 8] = b"URSALUMI";
+/// Incremented whenever the layout of the binary format changes.
+pub(crate) const SCHEMA_VERSION: u32 = 2;
+/// Size of a star record, excluding its kind and its companions, in bytes.
+pub(crate) const STAR_RECORD_SIZE: usize = 494;
+
+const STAR: u8 = 1;
+const UNRESOLVED_CLUSTER: u8 = 2;
+const CHUNK_COMPLETED: u8 = 3;
+const STATISTICS: u8 = 4;
+const INDEX: u8 = 5;
+
+/// The last bytes of every complete binary catalogue, preceded by the offset of the index.
+const INDEX_MAGIC: &[u8; 8] = b"URSAINDX";
+/// Size of an entry of the index, in bytes.
+pub(crate) const INDEX_ENTRY_SIZE: usize = 72;
+
+const HAS_COOLING_AGE: u16 = 1 << 0;
+const HAS_SYSTEM_MAGNITUDES: u16 = 1 << 1;
+const HAS_GALACTIC: u16 = 1 << 2;
+const HAS_EQUATORIAL: u16 = 1 << 3;
+const HAS_ECLIPTIC: u16 = 1 << 4;
+const HAS_COLOUR: u16 = 1 << 5;
+const HAS_ORBIT: u16 = 1 << 6;
+const ORBIT_RESOLVED: u16 = 1 << 7;
+const HAS_VARIABILITY: u16 = 1 << 8;
+const HAS_ECLIPSE: u16 = 1 << 9;
+const HAS_CLUSTER: u16 = 1 << 10;
+
+/// Writes the catalogue in the compact little-endian binary format, flushing after every chunk.
+///
+/// The entries of the spatial index are collected in a separate file while the catalogue is written, and are only appended to it by `finish`, so that they are neither held in memory nor copied into every checkpoint.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#binary-format>
+pub(crate) struct BinaryWriter<W: Write, I: Read + Write + Seek> {
+    out: W,
+    /// Number of bytes written so far.
+    position: u64,
+    /// The encoded entries of the spatial index written so far.
+    index: I,
+    index_entries: u64,
+}
+
+/// The file next to a binary catalogue in which the entries of its spatial index are collected until the catalogue is complete.
+pub(crate) fn index_path_for(output: &Path) -> PathBuf {
+    let mut path = OsString::from(output.as_os_str());
+    path.push(".index");
+    PathBuf::from(path)
+}
+
+impl<W: Write, I: Read + Write + Seek> BinaryWriter<W, I> {
+    pub(crate) fn new(out: W, index: I) -> Self {
+        BinaryWriter {
+            out,
+            position: 0,
+            index,
+            index_entries: 0,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.out.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn write_json(
+        &mut self,
+        kind: u8,
+        value: &impl serde::Serialize,
+    ) -> Result<(), Box<dyn Error>> {
+        let json = serde_json::to_vec(value)?;
+        self.write(&[kind])?;
+        self.write(&(json.len() as u64).to_le_bytes())?;
+        self.write(&json)?;
+        Ok(())
+    }
+}
+
+impl<W: Write, I: Read + Write + Seek> CatalogueWriter for BinaryWriter<W, I> {
+    fn write_header(&mut self, header: &CatalogueHeader) -> Result<(), Box<dyn Error>> {
+        let json = serde_json::to_vec(header)?;
+        self.write(MAGIC)?;
+        self.write(&SCHEMA_VERSION.to_le_bytes())?;
+        self.write(&(json.len() as u64).to_le_bytes())?;
+        self.write(&json)?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, index: usize, chunk: GeneratedChunk) -> Result<(), Box<dyn Error>> {
+        if let Some(entry) = IndexEntry::new(index as u64, self.position, &chunk.stars) {
+            let mut bytes = Vec::with_capacity(INDEX_ENTRY_SIZE);
+            encode_index_entry(&entry, &mut bytes);
+            self.index.write_all(&bytes)?;
+            self.index.flush()?;
+            self.index_entries += 1;
+        }
+        let mut bytes = Vec::new();
+        for star in chunk.stars.iter() {
+            bytes.clear();
+            bytes.push(STAR);
+            encode_star(star, &mut bytes)?;
+            self.write(&bytes)?;
+        }
+        for cluster in chunk.unresolved_clusters.iter() {
+            self.write_json(UNRESOLVED_CLUSTER, cluster)?;
+        }
+        self.write(&[CHUNK_COMPLETED])?;
+        self.write(&(index as u64).to_le_bytes())?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self, statistics: &GenerationStatistics) -> Result<(), Box<dyn Error>> {
+        self.write_json(STATISTICS, statistics)?;
+        let index_offset = self.position;
+        self.write(&[INDEX])?;
+        self.write(&self.index_entries.to_le_bytes())?;
+        // Entries behind the recorded ones may be left over from an interrupted run, and are ignored.
+        self.index.seek(SeekFrom::Start(0))?;
+        let mut entry = [0; INDEX_ENTRY_SIZE];
+        for _ in 0..self.index_entries {
+            self.index.read_exact(&mut entry)?;
+            self.write(&entry)?;
+        }
+        self.write(&index_offset.to_le_bytes())?;
+        self.write(INDEX_MAGIC)?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn state(&self) -> WriterState {
+        WriterState {
+            offset: self.position,
+            index_entries: self.index_entries,
+            ..Default::default()
+        }
+    }
+
+    fn resume(&mut self, state: &WriterState) -> Result<(), Box<dyn Error>> {
+        self.position = state.offset;
+        self.index_entries = state.index_entries;
+        self.index.seek(SeekFrom::Start(
+            state.index_entries * INDEX_ENTRY_SIZE as u64,
+        ))?;
+        Ok(())
+    }
+}
+
+/// Reads a catalogue in the binary format, record by record.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#binary-format>
+pub(crate) struct BinaryReader<R: Read> {
+    input: R,
+    header: CatalogueHeader,
+}
+
+impl<R: Read> BinaryReader<R> {
+    /// Reads the header, and fails if the input is not a binary catalogue of the supported schema version.
+    pub(crate) fn new(mut input: R) -> Result<Self, Box<dyn Error>> {
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err("The input is not a binary Ursa Lumi catalogue".into());
+        }
+        let mut version = [0; 4];
+        input.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != SCHEMA_VERSION {
+            return Err(format!(
+                "Unsupported binary catalogue version {}, expected {}",
+                version, SCHEMA_VERSION
+            )
+            .into());
+        }
+        let header = serde_json::from_slice(&read_blob(&mut input)?)?;
+        Ok(BinaryReader { input, header })
+    }
+
+    pub(crate) fn header(&self) -> &CatalogueHeader {
+        &self.header
+    }
+
+    /// The next record after the header, or `None` at the end of the input.
+    pub(crate) fn read_record(&mut self) -> Result<Option<Record>, Box<dyn Error>> {
+        let mut kind = [0; 1];
+        match self.input.read_exact(&mut kind) {
+            Ok(()) => {}
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(error) => return Err(error.into()),
+        }
+        let record = match kind[0] {
+            STAR => Record::Star(Box::new(read_star(&mut self.input)?)),
+            UNRESOLVED_CLUSTER => Record::UnresolvedCluster(Box::new(serde_json::from_slice(
+                &read_blob(&mut self.input)?,
+            )?)),
+            CHUNK_COMPLETED => {
+                let mut index = [0; 8];
+                self.input.read_exact(&mut index)?;
+                Record::ChunkCompleted(u64::from_le_bytes(index) as usize)
+            }
+            STATISTICS => Record::Statistics(serde_json::from_slice(&read_blob(&mut self.input)?)?),
+            INDEX => return Ok(None),
+            other => return Err(format!("Unknown record kind {}", other).into()),
+        };
+        Ok(Some(record))
+    }
+}
+
+impl<R: Read + Seek> BinaryReader<R> {
+    /// Reads the spatial index at the end of the catalogue, which only complete catalogues have.
+    pub(crate) fn read_index(&mut self) -> Result<Vec<IndexEntry>, Box<dyn Error>> {
+        self.input.seek(SeekFrom::End(-16))?;
+        let mut footer = [0; 16];
+        self.input.read_exact(&mut footer)?;
+        let mut decoder = Decoder { bytes: &footer };
+        let index_offset = decoder.u64();
+        if decoder.take::<8>() != *INDEX_MAGIC {
+            return Err("The catalogue has no index, it is probably incomplete".into());
+        }
+        self.input.seek(SeekFrom::Start(index_offset))?;
+        let mut head = [0; 9];
+        self.input.read_exact(&mut head)?;
+        let mut decoder = Decoder { bytes: &head };
+        if decoder.u8() != INDEX {
+            return Err("The index offset does not point to the index".into());
+        }
+        (0..decoder.u64())
+            .map(|_| {
+                let mut entry = [0; INDEX_ENTRY_SIZE];
+                self.input.read_exact(&mut entry)?;
+                Ok(decode_index_entry(&entry))
+            })
+            .collect()
+    }
+
+    /// Continues reading at the given offset from the start of the file, which must be the start of a record.
+    pub(crate) fn seek(&mut self, offset: u64) -> Result<(), Box<dyn Error>> {
+        self.input.seek(SeekFrom::Start(offset))?;
+        Ok(())
+    }
+}
+
+fn encode_index_entry(entry: &IndexEntry, bytes: &mut Vec<u8>) {
+    bytes.extend(entry.chunk_index.to_le_bytes());
+    bytes.extend(entry.offset.to_le_bytes());
+    let (min, max) = (entry.min_in_pc, entry.max_in_pc);
+    put_f64s(bytes, &[min.0, min.1, min.2, max.0, max.1, max.2]);
+    bytes.extend(entry.number_of_stars.to_le_bytes());
+}
+
+fn decode_index_entry(bytes: &[u8; INDEX_ENTRY_SIZE]) -> IndexEntry {
+    let mut decoder = Decoder { bytes };
+    IndexEntry {
+        chunk_index: decoder.u64(),
+        offset: decoder.u64(),
+        min_in_pc: decoder.triple(),
+        max_in_pc: decoder.triple(),
+        number_of_stars: decoder.u64(),
+    }
+}
+
+/// Reads a length-prefixed blob.
+///
+/// The blob is read only as far as the input reaches, so a corrupt length cannot allocate more memory than the file holds.
+fn read_blob(input: &mut impl Read) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut length = [0; 8];
+    input.read_exact(&mut length)?;
+    let length = u64::from_le_bytes(length);
+    let mut blob = Vec::new();
+    input.take(length).read_to_end(&mut blob)?;
+    if blob.len() as u64 != length {
+        return Err(format!(
+            "The catalogue ends after {} of the {} bytes of a JSON record",
+            blob.len(),
+            length
+        )
+        .into());
+    }
+    Ok(blob)
+}
+
+fn population_code(population: Population) -> u8 {
+    match population {
+        Population::ThinDisc(Subpopulation::Alive) => 0,
+        Population::ThinDisc(Subpopulation::WhiteDwarf) => 1,
+        Population::ThickDisc(Subpopulation::Alive) => 2,
+        Population::ThickDisc(Subpopulation::WhiteDwarf) => 3,
+        Population::Spheroid => 4,
+        Population::Bulge => 5,
+    }
+}
+
+fn population_from_code(code: u8) -> Result<Population, Box<dyn Error>> {
+    match code {
+        0 => Ok(Population::ThinDisc(Subpopulation::Alive)),
+        1 => Ok(Population::ThinDisc(Subpopulation::WhiteDwarf)),
+        2 => Ok(Population::ThickDisc(Subpopulation::Alive)),
+        3 => Ok(Population::ThickDisc(Subpopulation::WhiteDwarf)),
+        4 => Ok(Population::Spheroid),
+        5 => Ok(Population::Bulge),
+        other => Err(format!("Unknown population code {}", other).into()),
+    }
+}
+
+fn variability_class_code(class: VariabilityClass) -> u8 {
+    match class {
+        VariabilityClass::ClassicalCepheid => 0,
+        VariabilityClass::RrLyrae => 1,
+        VariabilityClass::DeltaScuti => 2,
+        VariabilityClass::Mira => 3,
+        VariabilityClass::EclipsingBinary => 4,
+    }
+}
+
+fn variability_class_from_code(code: u8) -> Result<VariabilityClass, Box<dyn Error>> {
+    match code {
+        0 => Ok(VariabilityClass::ClassicalCepheid),
+        1 => Ok(VariabilityClass::RrLyrae),
+        2 => Ok(VariabilityClass::DeltaScuti),
+        3 => Ok(VariabilityClass::Mira),
+        4 => Ok(VariabilityClass::EclipsingBinary),
+        other => Err(format!("Unknown variability class code {}", other).into()),
+    }
+}
+
+/// Appends the fixed-width record of the star, followed by the records of its companions.
+fn encode_star(star: &Star, bytes: &mut Vec<u8>) -> Result<(), Box<dyn Error>> {
+    let start = bytes.len();
+    let sky = &star.sky_coordinates;
+    let flags = [
+        (star.cooling_age_in_gyr.is_some(), HAS_COOLING_AGE),
+        (
+            star.system_apparent_magnitudes.is_some(),
+            HAS_SYSTEM_MAGNITUDES,
+        ),
+        (sky.galactic.is_some(), HAS_GALACTIC),
+        (sky.equatorial.is_some(), HAS_EQUATORIAL),
+        (sky.ecliptic.is_some(), HAS_ECLIPTIC),
+        (star.colour.is_some(), HAS_COLOUR),
+        (star.orbit.is_some(), HAS_ORBIT),
+        (
+            star.orbit.is_some_and(|orbit| orbit.resolved),
+            ORBIT_RESOLVED,
+        ),
+        (star.variability.is_some(), HAS_VARIABILITY),
+        (
+            star.variability.is_some_and(|v| v.eclipse.is_some()),
+            HAS_ECLIPSE,
+        ),
+        (star.cluster_id.is_some(), HAS_CLUSTER),
+    ]
+    .iter()
+    .filter(|(present, _)| *present)
+    .fold(0, |flags, (_, flag)| flags | flag);
+    bytes.extend(flags.to_le_bytes());
+
+    let (x, y, z) = star.position_in_pc;
+    let (vx, vy, vz) = star.velocity_in_km_per_s;
+    put_f64s(
+        bytes,
+        &[
+            x,
+            y,
+            z,
+            vx,
+            vy,
+            vz,
+            star.initial_mass_in_solar_masses,
+            star.mass_in_solar_masses,
+            star.age_in_gyr,
+            star.metallicity_in_dex,
+            star.luminosity_in_solar_units,
+            star.effective_temperature_in_k,
+        ],
+    );
+    bytes.push(population_code(star.population));
+    put_f64s(bytes, &[star.cooling_age_in_gyr.unwrap_or_default()]);
+    put_f64s(bytes, &magnitudes_to_array(&star.absolute_magnitudes));
+    put_f64s(bytes, &magnitudes_to_array(&star.apparent_magnitudes));
+    put_f64s(
+        bytes,
+        &magnitudes_to_array(&star.system_apparent_magnitudes.unwrap_or_default()),
+    );
+    put_f64s(bytes, &[star.visual_extinction_in_mag]);
+    let galactic = sky.galactic.map_or([0.; 3], |g| {
+        [g.longitude_in_deg, g.latitude_in_deg, g.distance_in_pc]
+    });
+    let equatorial = sky.equatorial.map_or([0.; 2], |e| {
+        [e.right_ascension_in_deg, e.declination_in_deg]
+    });
+    let ecliptic = sky
+        .ecliptic
+        .map_or([0.; 2], |e| [e.longitude_in_deg, e.latitude_in_deg]);
+    put_f64s(bytes, &galactic);
+    put_f64s(bytes, &equatorial);
+    put_f64s(bytes, &ecliptic);
+    let astrometry = &star.astrometry;
+    put_f64s(
+        bytes,
+        &[
+            astrometry.proper_motion_ra_in_mas_per_yr,
+            astrometry.proper_motion_dec_in_mas_per_yr,
+            astrometry.radial_velocity_in_km_per_s,
+            astrometry.tangential_velocity_in_km_per_s,
+        ],
+    );
+    let colour = star.colour.map_or([0.; 7], |c| {
+        let (lr, lg, lb) = c.linear_srgb;
+        let (r, g, b) = c.srgb;
+        [lr, lg, lb, r, g, b, c.relative_brightness]
+    });
+    put_f64s(bytes, &colour);
+    let orbit = star.orbit.map_or([0.; 5], |o| {
+        [
+            o.mass_ratio,
+            o.period_in_days,
+            o.eccentricity,
+            o.semi_major_axis_in_au,
+            o.angular_separation_in_arcsec,
+        ]
+    });
+    put_f64s(bytes, &orbit);
+    bytes.push(
+        star.variability
+            .map_or(0, |v| variability_class_code(v.class)),
+    );
+    let variability = star.variability.map_or([0.; 5], |v| {
+        let eclipse = v
+            .eclipse
+            .map_or([0.; 2], |e| [e.secondary_depth_in_mag, e.duration_in_phase]);
+        [
+            v.period_in_days,
+            v.amplitude_in_mag,
+            v.phase_at_epoch,
+            eclipse[0],
+            eclipse[1],
+        ]
+    });
+    put_f64s(bytes, &variability);
+    bytes.extend(star.cluster_id.unwrap_or_default().to_le_bytes());
+    bytes.extend(u16::try_from(star.companions.len())?.to_le_bytes());
+    debug_assert_eq!(bytes.len() - start, STAR_RECORD_SIZE);
+
+    for companion in star.companions.iter() {
+        encode_star(companion, bytes)?;
+    }
+    Ok(())
+}
+
+fn put_f64s(bytes: &mut Vec<u8>, values: &[f64]) {
+    for value in values {
+        bytes.extend(value.to_le_bytes());
+    }
+}
+
+fn magnitudes_to_array(magnitudes: &Magnitudes) -> [f64; 6] {
+    [
+        magnitudes.bolometric,
+        magnitudes.johnson_b,
+        magnitudes.johnson_v,
+        magnitudes.gaia_g,
+        magnitudes.gaia_bp,
+        magnitudes.gaia_rp,
+    ]
+}
+
+/// Consumes a fixed-width record from the front.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let (head, tail) = self.bytes.split_at(N);
+        self.bytes = tail;
+        let mut array = [0; N];
+        array.copy_from_slice(head);
+        array
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.take::<1>()[0]
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.take())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take())
+    }
+
+    fn f64(&mut self) -> f64 {
+        f64::from_le_bytes(self.take())
+    }
+
+    fn triple(&mut self) -> (f64, f64, f64) {
+        (self.f64(), self.f64(), self.f64())
+    }
+
+    fn magnitudes(&mut self) -> Magnitudes {
+        Magnitudes {
+            bolometric: self.f64(),
+            johnson_b: self.f64(),
+            johnson_v: self.f64(),
+            gaia_g: self.f64(),
+            gaia_bp: self.f64(),
+            gaia_rp: self.f64(),
+        }
+    }
+}
+
+fn read_star(input: &mut impl Read) -> Result<Star, Box<dyn Error>> {
+    let mut record = [0; STAR_RECORD_SIZE];
+    input.read_exact(&mut record)?;
+    let mut decoder = Decoder { bytes: &record };
+    let flags = decoder.u16();
+    let has = |flag: u16| flags & flag != 0;
+
+    let position_in_pc = decoder.triple();
+    let velocity_in_km_per_s = decoder.triple();
+    let initial_mass_in_solar_masses = decoder.f64();
+    let mass_in_solar_masses = decoder.f64();
+    let age_in_gyr = decoder.f64();
+    let metallicity_in_dex = decoder.f64();
+    let luminosity_in_solar_units = decoder.f64();
+    let effective_temperature_in_k = decoder.f64();
+    let population = population_from_code(decoder.u8())?;
+    let cooling_age_in_gyr = Some(decoder.f64()).filter(|_| has(HAS_COOLING_AGE));
+    let absolute_magnitudes = decoder.magnitudes();
+    let apparent_magnitudes = decoder.magnitudes();
+    let system_apparent_magnitudes =
+        Some(decoder.magnitudes()).filter(|_| has(HAS_SYSTEM_MAGNITUDES));
+    let visual_extinction_in_mag = decoder.f64();
+    let galactic = GalacticCoordinates {
+        longitude_in_deg: decoder.f64(),
+        latitude_in_deg: decoder.f64(),
+        distance_in_pc: decoder.f64(),
+    };
+    let equatorial = EquatorialCoordinates {
+        right_ascension_in_deg: decoder.f64(),
+        declination_in_deg: decoder.f64(),
+    };
+    let ecliptic = EclipticCoordinates {
+        longitude_in_deg: decoder.f64(),
+        latitude_in_deg: decoder.f64(),
+    };
+    let sky_coordinates = SkyCoordinates {
+        galactic: Some(galactic).filter(|_| has(HAS_GALACTIC)),
+        equatorial: Some(equatorial).filter(|_| has(HAS_EQUATORIAL)),
+        ecliptic: Some(ecliptic).filter(|_| has(HAS_ECLIPTIC)),
+    };
+    let astrometry = Astrometry {
+        proper_motion_ra_in_mas_per_yr: decoder.f64(),
+        proper_motion_dec_in_mas_per_yr: decoder.f64(),
+        radial_velocity_in_km_per_s: decoder.f64(),
+        tangential_velocity_in_km_per_s: decoder.f64(),
+    };
+    let colour = Colour {
+        linear_srgb: decoder.triple(),
+        srgb: decoder.triple(),
+        relative_brightness: decoder.f64(),
+    };
+    let orbit = Orbit {
+        mass_ratio: decoder.f64(),
+        period_in_days: decoder.f64(),
+        eccentricity: decoder.f64(),
+        semi_major_axis_in_au: decoder.f64(),
+        angular_separation_in_arcsec: decoder.f64(),
+        resolved: has(ORBIT_RESOLVED),
+    };
+    let class = decoder.u8();
+    let period_in_days = decoder.f64();
+    let amplitude_in_mag = decoder.f64();
+    let phase_at_epoch = decoder.f64();
+    let eclipse = Eclipse {
+        secondary_depth_in_mag: decoder.f64(),
+        duration_in_phase: decoder.f64(),
+    };
+    let variability = if has(HAS_VARIABILITY) {
+        Some(Variability {
+            class: variability_class_from_code(class)?,
+            period_in_days,
+            amplitude_in_mag,
+            phase_at_epoch,
+            eclipse: Some(eclipse).filter(|_| has(HAS_ECLIPSE)),
+        })
+    } else {
+        None
+    };
+    let cluster_id = Some(decoder.u64()).filter(|_| has(HAS_CLUSTER));
+    let number_of_companions = decoder.u16();
+
+    let companions = (0..number_of_companions)
+        .map(|_| read_star(input))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Star {
+        position_in_pc,
+        velocity_in_km_per_s,
+        initial_mass_in_solar_masses,
+        mass_in_solar_masses,
+        age_in_gyr,
+        metallicity_in_dex,
+        luminosity_in_solar_units,
+        effective_temperature_in_k,
+        population,
+        cooling_age_in_gyr,
+        absolute_magnitudes,
+        apparent_magnitudes,
+        system_apparent_magnitudes,
+        visual_extinction_in_mag,
+        sky_coordinates,
+        astrometry,
+        colour: Some(colour).filter(|_| has(HAS_COLOUR)),
+        orbit: Some(orbit).filter(|_| has(HAS_ORBIT)),
+        companions,
+        variability,
+        cluster_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        catalogue::write_catalogue, coordinates::SkyFrame,
+        generation_parameters::GenerationParameters,
+    };
+
+    use super::*;
+
+    fn star(population: Population, seed: f64) -> Star {
+        let magnitudes = Magnitudes {
+            bolometric: seed,
+            johnson_b: seed + 0.1,
+            johnson_v: seed + 0.2,
+            gaia_g: seed + 0.3,
+            gaia_bp: seed + 0.4,
+            gaia_rp: seed + 0.5,
+        };
+        Star {
+            position_in_pc: (8200. + seed, -seed, 0.5 * seed),
+            velocity_in_km_per_s: (-10., 230. + seed, 7.),
+            initial_mass_in_solar_masses: 1. + seed,
+            mass_in_solar_masses: 0.9 + seed,
+            age_in_gyr: 4.6,
+            metallicity_in_dex: -0.3,
+            luminosity_in_solar_units: 2. * seed,
+            effective_temperature_in_k: 5772.,
+            population,
+            cooling_age_in_gyr: None,
+            absolute_magnitudes: magnitudes,
+            apparent_magnitudes: Magnitudes {
+                johnson_v: seed + 5.,
+                ..magnitudes
+            },
+            system_apparent_magnitudes: None,
+            visual_extinction_in_mag: 0.01 * seed,
+            sky_coordinates: SkyCoordinates::default(),
+            astrometry: Astrometry {
+                proper_motion_ra_in_mas_per_yr: 12.,
+                proper_motion_dec_in_mas_per_yr: -3.,
+                radial_velocity_in_km_per_s: 20.,
+                tangential_velocity_in_km_per_s: 15.,
+            },
+            colour: None,
+            orbit: None,
+            companions: Vec::new(),
+            variability: None,
+            cluster_id: None,
+        }
+    }
+
+    fn solar_neighbourhood() -> GenerationParameters {
+        GenerationParameters {
+            observer_position_in_pc: Some((8200., 0., 0.)),
+            apparent_magnitude_limit: 6.,
+            max_distance_in_pc: 50.,
+            chunksize_in_pc: 15.,
+            ..Default::default()
+        }
+    }
+
+    fn round_trip(stars: Vec<Star>) -> (CatalogueHeader, Vec<Record>) {
+        let header = CatalogueHeader {
+            generation_parameters: GenerationParameters::default(),
+            epoch_in_kyr: 12.,
+            observer_position_in_pc: (8200., 0., 20.8),
+        };
+        let mut writer = BinaryWriter::new(Vec::new(), Cursor::new(Vec::new()));
+        writer
+            .write_header(&header)
+            .expect("Writing should succeed");
+        let chunk = GeneratedChunk {
+            stars,
+            unresolved_clusters: Vec::new(),
+        };
+        writer
+            .write_chunk(7, chunk)
+            .expect("Writing should succeed");
+        writer
+            .finish(&GenerationStatistics::default())
+            .expect("Writing should succeed");
+
+        let mut reader =
+            BinaryReader::new(writer.out.as_slice()).expect("The header should be valid");
+        let mut records = Vec::new();
+        while let Some(record) = reader.read_record().expect("The record should be valid") {
+            records.push(record);
+        }
+        (reader.header().clone(), records)
+    }
+
+    #[test]
+    fn stars_of_every_population_round_trip() {
+        let populations = [
+            Population::ThinDisc(Subpopulation::Alive),
+            Population::ThinDisc(Subpopulation::WhiteDwarf),
+            Population::ThickDisc(Subpopulation::Alive),
+            Population::ThickDisc(Subpopulation::WhiteDwarf),
+            Population::Spheroid,
+            Population::Bulge,
+        ];
+        let mut stars: Vec<Star> = populations
+            .iter()
+            .enumerate()
+            .map(|(i, population)| star(*population, i as f64))
+            .collect();
+        stars[1].cooling_age_in_gyr = Some(1.5);
+        stars[2].sky_coordinates = SkyCoordinates {
+            galactic: Some(GalacticCoordinates {
+                longitude_in_deg: 10.,
+                latitude_in_deg: -5.,
+                distance_in_pc: 100.,
+            }),
+            equatorial: None,
+            ecliptic: Some(EclipticCoordinates {
+                longitude_in_deg: 200.,
+                latitude_in_deg: 30.,
+            }),
+        };
+        stars[2].colour = Some(Colour {
+            linear_srgb: (1., 0.8, 0.6),
+            srgb: (1., 0.9, 0.8),
+            relative_brightness: 3.,
+        });
+        stars[2].cluster_id = Some((3 << 32) | 5);
+        stars[4].variability = Some(Variability {
+            class: VariabilityClass::RrLyrae,
+            period_in_days: 0.55,
+            amplitude_in_mag: 0.8,
+            phase_at_epoch: 0.25,
+            eclipse: None,
+        });
+        let mut companion = star(Population::ThinDisc(Subpopulation::Alive), 10.);
+        companion.orbit = Some(Orbit {
+            mass_ratio: 0.5,
+            period_in_days: 3.,
+            eccentricity: 0.1,
+            semi_major_axis_in_au: 0.05,
+            angular_separation_in_arcsec: 0.001,
+            resolved: false,
+        });
+        companion.variability = Some(Variability {
+            class: VariabilityClass::EclipsingBinary,
+            period_in_days: 3.,
+            amplitude_in_mag: 0.4,
+            phase_at_epoch: 0.7,
+            eclipse: Some(Eclipse {
+                secondary_depth_in_mag: 0.1,
+                duration_in_phase: 0.05,
+            }),
+        });
+        stars[0].companions.push(companion);
+        stars[0].system_apparent_magnitudes = Some(Magnitudes::default());
+
+        let (header, records) = round_trip(stars.clone());
+
+        assert_eq!(header.epoch_in_kyr, 12.);
+        assert_eq!(header.observer_position_in_pc, (8200., 0., 20.8));
+        assert_eq!(records.len(), stars.len() + 2);
+        for (record, star) in records.iter().zip(stars.iter()) {
+            match record {
+                Record::Star(read) => assert_eq!(read.as_ref(), star),
+                _ => panic!("Expected a star record"),
+            }
+        }
+        assert!(matches!(records[stars.len()], Record::ChunkCompleted(7)));
+        assert!(matches!(records[stars.len() + 1], Record::Statistics(_)));
+    }
+
+    #[test]
+    fn files_with_the_wrong_version_are_rejected() {
+        let mut writer = BinaryWriter::new(Vec::new(), Cursor::new(Vec::new()));
+        write_catalogue(&solar_neighbourhood(), &mut writer).expect("Generation should succeed");
+        let mut bytes = writer.out;
+        assert!(BinaryReader::new(bytes.as_slice()).is_ok());
+
+        bytes[8..12].copy_from_slice(&(SCHEMA_VERSION + 1).to_le_bytes());
+        assert!(BinaryReader::new(bytes.as_slice()).is_err());
+
+        bytes[0] = b'X';
+        assert!(BinaryReader::new(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn corrupt_record_lengths_are_rejected() {
+        let mut writer = BinaryWriter::new(Vec::new(), Cursor::new(Vec::new()));
+        write_catalogue(&solar_neighbourhood(), &mut writer).expect("Generation should succeed");
+        let mut bytes = writer.out;
+
+        bytes[12..20].copy_from_slice(&u64::MAX.to_le_bytes());
+        let error = BinaryReader::new(bytes.as_slice())
+            .err()
+            .expect("The header should be rejected");
+        assert!(error.to_string().contains("The catalogue ends after"));
+    }
+
+    #[test]
+    fn generated_catalogue_round_trips() {
+        let params = GenerationParameters {
+            include_colour: true,
+            sky_frames: vec![SkyFrame::Galactic, SkyFrame::Equatorial],
+            include_companions: true,
+            include_variability: true,
+            ..solar_neighbourhood()
+        };
+        let mut writer = BinaryWriter::new(Vec::new(), Cursor::new(Vec::new()));
+        let statistics = write_catalogue(&params, &mut writer).expect("Generation should succeed");
+
+        let mut reader = BinaryReader::new(writer.out.as_slice()).expect("The header is valid");
+        let (mut stars, mut companions) = (0, 0);
+        let mut last = None;
+        while let Some(record) = reader.read_record().expect("The record should be valid") {
+            if let Record::Star(star) = &record {
+                stars += 1;
+                companions += star.companions.len() as u64;
+                assert!(star.colour.is_some());
+                assert!(star.sky_coordinates.galactic.is_some());
+                assert!(star.sky_coordinates.ecliptic.is_none());
+            }
+            last = Some(record);
+        }
+        assert_eq!(stars, statistics.stars_kept);
+        assert_eq!(companions, statistics.companions_kept);
+        match last {
+            Some(Record::Statistics(read)) => assert_eq!(read, statistics),
+            _ => panic!("The last record should contain the statistics"),
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    error::Error,
+    ffi::OsString,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    generation_parameters::GenerationParameters, generation_statistics::GenerationStatistics,
+};
+
+use super::{CatalogueWriter, OutputFormat, WriterState};
+
+/// Everything needed to continue an interrupted run after its last completed chunk.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#checkpoints>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Checkpoint {
+    /// Number of completed chunks. Because chunks are written in order, these are the chunks with the indices below it.
+    pub(crate) chunks_completed: usize,
+    pub(crate) generation_parameters: GenerationParameters,
+    pub(crate) format: OutputFormat,
+    /// Names of the columns of the tabular formats.
+    pub(crate) columns: Vec<String>,
+    /// The statistics of the completed chunks.
+    pub(crate) statistics: GenerationStatistics,
+    /// The state of the writer after the last completed chunk, including the offset up to which the output is valid.
+    pub(crate) writer: WriterState,
+}
+
+impl Checkpoint {
+    /// The file next to the output in which its checkpoints are saved.
+    pub(crate) fn path_for(output: &Path) -> PathBuf {
+        let mut path = OsString::from(output.as_os_str());
+        path.push(".checkpoint");
+        PathBuf::from(path)
+    }
+
+    pub(crate) fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(path)
+            .map_err(|error| format!("There is no checkpoint at {}: {}", path.display(), error))?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    /// Fails unless the checkpoint was saved by a run with the same parameters, format and columns, which would otherwise produce a different output.
+    pub(crate) fn check_matches(
+        &self,
+        generation_parameters: &GenerationParameters,
+        format: OutputFormat,
+        columns: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        if serde_json::to_value(&self.generation_parameters)?
+            != serde_json::to_value(generation_parameters)?
+        {
+            return Err("The checkpoint was saved for different generation parameters".into());
+        }
+        if self.format != format || self.columns != columns {
+            return Err("The checkpoint was saved for a different format or columns".into());
+        }
+        Ok(())
+    }
+
+    /// Replaces the file at the given path, such that it holds either the previous or the new checkpoint if the run is interrupted while saving.
+    fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut temporary = OsString::from(path.as_os_str());
+        temporary.push(".tmp");
+        let mut out = BufWriter::new(File::create(&temporary)?);
+        serde_json::to_writer(&mut out, self)?;
+        out.flush()?;
+        drop(out);
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+/// Saves a checkpoint after a chunk at most once per interval, and removes it once the run is complete.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#checkpoints>
+pub(crate) struct Checkpointer {
+    path: PathBuf,
+    interval: Duration,
+    last_save: Option<Instant>,
+    checkpoint: Checkpoint,
+}
+
+impl Checkpointer {
+    pub(crate) fn new(
+        path: PathBuf,
+        interval: Duration,
+        generation_parameters: &GenerationParameters,
+        format: OutputFormat,
+        columns: Vec<String>,
+    ) -> Self {
+        Checkpointer {
+            path,
+            interval,
+            last_save: None,
+            checkpoint: Checkpoint {
+                chunks_completed: 0,
+                generation_parameters: generation_parameters.clone(),
+                format,
+                columns,
+                statistics: GenerationStatistics::default(),
+                writer: WriterState::default(),
+            },
+        }
+    }
+
+    /// Saves the state after the given number of chunks, if the interval has passed since the last save.
+    pub(crate) fn save_if_due(
+        &mut self,
+        chunks_completed: usize,
+        statistics: &GenerationStatistics,
+        writer: &(impl CatalogueWriter + ?Sized),
+    ) -> Result<(), Box<dyn Error>> {
+        let now = Instant::now();
+        if self
+            .last_save
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return Ok(());
+        }
+        self.last_save = Some(now);
+        self.checkpoint.chunks_completed = chunks_completed;
+        self.checkpoint.statistics = statistics.clone();
+        self.checkpoint.writer = writer.state();
+        self.checkpoint.save(&self.path)
+    }
+
+    /// Removes the checkpoint, because the output is complete.
+    pub(crate) fn remove(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::catalogue::{
+        binary::BinaryWriter, column::Column, csv::CsvWriter, fits::FitsWriter,
+        ndjson::NdjsonWriter, resume_catalogue, write_catalogue, write_catalogue_with_progress,
+    };
+
+    use super::*;
+
+    /// A writer of the given format, which collects the index entries of a binary catalogue in the given buffer.
+    fn writer<'a>(
+        format: OutputFormat,
+        out: Cursor<&'a mut Vec<u8>>,
+        index: &'a mut Vec<u8>,
+    ) -> Box<dyn CatalogueWriter + 'a> {
+        let columns = vec![Column::System, Column::X, Column::ApparentMagnitude(None)];
+        match format {
+            OutputFormat::Ndjson => Box::new(NdjsonWriter::new(out)),
+            OutputFormat::Binary => Box::new(BinaryWriter::new(out, Cursor::new(index))),
+            OutputFormat::Csv => Box::new(CsvWriter::new(out, ',', columns)),
+            OutputFormat::Tsv => Box::new(CsvWriter::new(out, '\t', columns)),
+            OutputFormat::Fits => Box::new(FitsWriter::new(out, columns)),
+        }
+    }
+
+    /// A path in the temporary directory that no other test or concurrent test run uses.
+    fn unique_path(test: &str, format: OutputFormat) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "ursa_lumi_{}_{}_{:?}.checkpoint",
+            std::process::id(),
+            test,
+            format
+        ))
+    }
+
+    #[test]
+    fn resumed_catalogue_equals_an_uninterrupted_one() {
+        let params = GenerationParameters {
+            observer_position_in_pc: Some((8200., 0., 0.)),
+            apparent_magnitude_limit: 6.,
+            max_distance_in_pc: 40.,
+            chunksize_in_pc: 15.,
+            include_companions: true,
+            ..Default::default()
+        };
+        for format in [
+            OutputFormat::Ndjson,
+            OutputFormat::Binary,
+            OutputFormat::Csv,
+            OutputFormat::Fits,
+        ] {
+            let mut uninterrupted = Vec::new();
+            write_catalogue(
+                &params,
+                writer(format, Cursor::new(&mut uninterrupted), &mut Vec::new()).as_mut(),
+            )
+            .expect("Generation should succeed");
+
+            let path = unique_path("resumed_catalogue_equals_an_uninterrupted_one", format);
+            // Only the first chunk is checkpointed, and the run is aborted a few chunks later.
+            let mut checkpointer = Checkpointer::new(
+                path.clone(),
+                Duration::from_secs(3600),
+                &params,
+                format,
+                Vec::new(),
+            );
+            let mut output = Vec::new();
+            let mut index = Vec::new();
+            let aborted = write_catalogue_with_progress(
+                &params,
+                writer(format, Cursor::new(&mut output), &mut index).as_mut(),
+                &mut |progress| match progress.chunks_completed {
+                    5 => Err("interrupted".into()),
+                    _ => Ok(()),
+                },
+                Some(&mut checkpointer),
+            );
+            assert!(aborted.is_err());
+
+            let checkpoint = Checkpoint::load(&path).expect("A checkpoint should have been saved");
+            assert_eq!(checkpoint.chunks_completed, 1);
+            assert!((checkpoint.writer.offset as usize) < output.len());
+            checkpoint
+                .check_matches(&params, format, &[])
+                .expect("The checkpoint should match its parameters");
+            output.truncate(checkpoint.writer.offset as usize);
+            let mut out = Cursor::new(&mut output);
+            out.set_position(checkpoint.writer.offset);
+            resume_catalogue(
+                &checkpoint,
+                writer(format, out, &mut index).as_mut(),
+                &mut |_| Ok(()),
+                &mut checkpointer,
+            )
+            .expect("Resuming should succeed");
+
+            assert!(output == uninterrupted, "{:?}", format);
+            assert!(!path.exists());
+        }
+    }
+
+    #[test]
+    fn checkpoints_only_match_the_run_that_saved_them() {
+        let params = GenerationParameters {
+            seed: 3,
+            ..Default::default()
+        };
+        let checkpointer = Checkpointer::new(
+            PathBuf::new(),
+            Duration::ZERO,
+            &params,
+            OutputFormat::Binary,
+            Vec::new(),
+        );
+        let checkpoint = checkpointer.checkpoint;
+        assert!(checkpoint
+            .check_matches(&params, OutputFormat::Binary, &[])
+            .is_ok());
+        let other_seed = GenerationParameters {
+            seed: 4,
+            ..params.clone()
+        };
+        assert!(checkpoint
+            .check_matches(&other_seed, OutputFormat::Binary, &[])
+            .is_err());
+        let other_distance = GenerationParameters {
+            max_distance_in_pc: params.max_distance_in_pc + 1.,
+            ..params.clone()
+        };
+        assert!(checkpoint
+            .check_matches(&other_distance, OutputFormat::Binary, &[])
+            .is_err());
+        assert!(checkpoint
+            .check_matches(&params, OutputFormat::Ndjson, &[])
+            .is_err());
+    }
+}
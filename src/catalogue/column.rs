@@ -0,0 +1,454 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    colour::Colour,
+    coordinates::SkyCoordinates,
+    kinematics::Astrometry,
+    photometry::{Band, Magnitudes},
+    population::{Population, Subpopulation},
+    star::Star,
+    star_cluster::{ClusterKind, UnresolvedCluster},
+    variability::VariabilityClass,
+};
+
+/// A column of the tabular outputs, named after the quantity and its unit.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#csv-and-tsv>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Column {
+    /// Running number of the system, shared by a primary and its companions.
+    System,
+    /// 0 for the primary, and counting up for its companions.
+    Component,
+    X,
+    Y,
+    Z,
+    VelocityX,
+    VelocityY,
+    VelocityZ,
+    InitialMass,
+    Mass,
+    Age,
+    Metallicity,
+    Luminosity,
+    EffectiveTemperature,
+    Population,
+    Subpopulation,
+    CoolingAge,
+    AbsoluteMagnitude(Option<Band>),
+    ApparentMagnitude(Option<Band>),
+    SystemMagnitude(Option<Band>),
+    VisualExtinction,
+    GalacticLongitude,
+    GalacticLatitude,
+    Distance,
+    RightAscension,
+    Declination,
+    EclipticLongitude,
+    EclipticLatitude,
+    ProperMotionRa,
+    ProperMotionDec,
+    RadialVelocity,
+    TangentialVelocity,
+    SrgbRed,
+    SrgbGreen,
+    SrgbBlue,
+    RelativeBrightness,
+    MassRatio,
+    OrbitalPeriod,
+    Eccentricity,
+    SemiMajorAxis,
+    Resolved,
+    VariabilityClass,
+    VariabilityPeriod,
+    Amplitude,
+    ClusterId,
+    /// Whether the row is a star or an unresolved cluster.
+    Object,
+    /// Only present for unresolved clusters.
+    AngularRadius,
+}
+
+const BANDS: [Option<Band>; 6] = [
+    None,
+    Some(Band::JohnsonB),
+    Some(Band::JohnsonV),
+    Some(Band::GaiaG),
+    Some(Band::GaiaBp),
+    Some(Band::GaiaRp),
+];
+
+impl Column {
+    /// All columns, in the order in which they are written if none are selected.
+    pub(crate) fn all() -> Vec<Column> {
+        let mut columns = vec![
+            Column::System,
+            Column::Component,
+            Column::X,
+            Column::Y,
+            Column::Z,
+            Column::VelocityX,
+            Column::VelocityY,
+            Column::VelocityZ,
+            Column::InitialMass,
+            Column::Mass,
+            Column::Age,
+            Column::Metallicity,
+            Column::Luminosity,
+            Column::EffectiveTemperature,
+            Column::Population,
+            Column::Subpopulation,
+            Column::CoolingAge,
+        ];
+        columns.extend(BANDS.map(Column::AbsoluteMagnitude));
+        columns.extend(BANDS.map(Column::ApparentMagnitude));
+        columns.extend(BANDS.map(Column::SystemMagnitude));
+        columns.extend([
+            Column::VisualExtinction,
+            Column::GalacticLongitude,
+            Column::GalacticLatitude,
+            Column::Distance,
+            Column::RightAscension,
+            Column::Declination,
+            Column::EclipticLongitude,
+            Column::EclipticLatitude,
+            Column::ProperMotionRa,
+            Column::ProperMotionDec,
+            Column::RadialVelocity,
+            Column::TangentialVelocity,
+            Column::SrgbRed,
+            Column::SrgbGreen,
+            Column::SrgbBlue,
+            Column::RelativeBrightness,
+            Column::MassRatio,
+            Column::OrbitalPeriod,
+            Column::Eccentricity,
+            Column::SemiMajorAxis,
+            Column::Resolved,
+            Column::VariabilityClass,
+            Column::VariabilityPeriod,
+            Column::Amplitude,
+            Column::ClusterId,
+            Column::Object,
+            Column::AngularRadius,
+        ]);
+        columns
+    }
+
+    pub(crate) fn name(&self) -> String {
+        let name = match self {
+            Column::System => "system",
+            Column::Component => "component",
+            Column::X => "x_pc",
+            Column::Y => "y_pc",
+            Column::Z => "z_pc",
+            Column::VelocityX => "vx_km_s",
+            Column::VelocityY => "vy_km_s",
+            Column::VelocityZ => "vz_km_s",
+            Column::InitialMass => "initial_mass_msun",
+            Column::Mass => "mass_msun",
+            Column::Age => "age_gyr",
+            Column::Metallicity => "metallicity_dex",
+            Column::Luminosity => "luminosity_lsun",
+            Column::EffectiveTemperature => "teff_k",
+            Column::Population => "population",
+            Column::Subpopulation => "subpopulation",
+            Column::CoolingAge => "cooling_age_gyr",
+            Column::AbsoluteMagnitude(band) => return format!("abs_mag_{}", band_suffix(*band)),
+            Column::ApparentMagnitude(band) => return format!("app_mag_{}", band_suffix(*band)),
+            Column::SystemMagnitude(band) => return format!("system_mag_{}", band_suffix(*band)),
+            Column::VisualExtinction => "extinction_v_mag",
+            Column::GalacticLongitude => "l_deg",
+            Column::GalacticLatitude => "b_deg",
+            Column::Distance => "distance_pc",
+            Column::RightAscension => "ra_deg",
+            Column::Declination => "dec_deg",
+            Column::EclipticLongitude => "ecliptic_lon_deg",
+            Column::EclipticLatitude => "ecliptic_lat_deg",
+            Column::ProperMotionRa => "pmra_mas_yr",
+            Column::ProperMotionDec => "pmdec_mas_yr",
+            Column::RadialVelocity => "rv_km_s",
+            Column::TangentialVelocity => "vt_km_s",
+            Column::SrgbRed => "srgb_r",
+            Column::SrgbGreen => "srgb_g",
+            Column::SrgbBlue => "srgb_b",
+            Column::RelativeBrightness => "relative_brightness",
+            Column::MassRatio => "mass_ratio",
+            Column::OrbitalPeriod => "orbital_period_days",
+            Column::Eccentricity => "eccentricity",
+            Column::SemiMajorAxis => "semi_major_axis_au",
+            Column::Resolved => "resolved",
+            Column::VariabilityClass => "variability_class",
+            Column::VariabilityPeriod => "variability_period_days",
+            Column::Amplitude => "amplitude_mag",
+            Column::ClusterId => "cluster_id",
+            Column::Object => "object",
+            Column::AngularRadius => "angular_radius_arcsec",
+        };
+        name.to_string()
+    }
+
+    /// The entry of the column for the given star, `None` if the star lacks the quantity.
+    pub(crate) fn value(&self, star: &Star, system: u64, component: usize) -> Value {
+        match self {
+            Column::System => Value::Integer(Some(system)),
+            Column::Component => Value::Integer(Some(component as u64)),
+            Column::X => Value::Float(Some(star.position_in_pc.0)),
+            Column::Y => Value::Float(Some(star.position_in_pc.1)),
+            Column::Z => Value::Float(Some(star.position_in_pc.2)),
+            Column::VelocityX => Value::Float(Some(star.velocity_in_km_per_s.0)),
+            Column::VelocityY => Value::Float(Some(star.velocity_in_km_per_s.1)),
+            Column::VelocityZ => Value::Float(Some(star.velocity_in_km_per_s.2)),
+            Column::InitialMass => Value::Float(Some(star.initial_mass_in_solar_masses)),
+            Column::Mass => Value::Float(Some(star.mass_in_solar_masses)),
+            Column::Age => Value::Float(Some(star.age_in_gyr)),
+            Column::Metallicity => Value::Float(Some(star.metallicity_in_dex)),
+            Column::Luminosity => Value::Float(Some(star.luminosity_in_solar_units)),
+            Column::EffectiveTemperature => Value::Float(Some(star.effective_temperature_in_k)),
+            Column::Population => Value::Text(population_name(star.population)),
+            Column::Subpopulation => Value::Text(subpopulation_name(star.population)),
+            Column::CoolingAge => Value::Float(star.cooling_age_in_gyr),
+            Column::AbsoluteMagnitude(band) => {
+                Value::Float(Some(star.absolute_magnitudes.get(*band)))
+            }
+            Column::ApparentMagnitude(band) => {
+                Value::Float(Some(star.apparent_magnitudes.get(*band)))
+            }
+            Column::SystemMagnitude(band) => Value::Float(
+                star.system_apparent_magnitudes
+                    .as_ref()
+                    .map(|magnitudes: &Magnitudes| magnitudes.get(*band)),
+            ),
+            Column::VisualExtinction => Value::Float(Some(star.visual_extinction_in_mag)),
+            Column::GalacticLongitude
+            | Column::GalacticLatitude
+            | Column::Distance
+            | Column::RightAscension
+            | Column::Declination
+            | Column::EclipticLongitude
+            | Column::EclipticLatitude
+            | Column::ProperMotionRa
+            | Column::ProperMotionDec
+            | Column::RadialVelocity
+            | Column::TangentialVelocity
+            | Column::SrgbRed
+            | Column::SrgbGreen
+            | Column::SrgbBlue
+            | Column::RelativeBrightness => {
+                self.observed_value(&star.sky_coordinates, &star.astrometry, star.colour)
+            }
+            Column::MassRatio => Value::Float(star.orbit.map(|o| o.mass_ratio)),
+            Column::OrbitalPeriod => Value::Float(star.orbit.map(|o| o.period_in_days)),
+            Column::Eccentricity => Value::Float(star.orbit.map(|o| o.eccentricity)),
+            Column::SemiMajorAxis => Value::Float(star.orbit.map(|o| o.semi_major_axis_in_au)),
+            Column::Resolved => Value::Flag(star.orbit.map(|o| o.resolved)),
+            Column::VariabilityClass => Value::Text(
+                star.variability
+                    .map_or("", |v| variability_class_name(v.class)),
+            ),
+            Column::VariabilityPeriod => Value::Float(star.variability.map(|v| v.period_in_days)),
+            Column::Amplitude => Value::Float(star.variability.map(|v| v.amplitude_in_mag)),
+            Column::ClusterId => Value::Integer(star.cluster_id),
+            Column::Object => Value::Text("Star"),
+            Column::AngularRadius => Value::Float(None),
+        }
+    }
+
+    /// The entry of the column for the given unresolved cluster, `None` for the quantities of single stars.
+    ///
+    /// The initial mass is the total initial mass of the members, and the magnitudes are integrated over them.
+    pub(crate) fn cluster_value(&self, unresolved: &UnresolvedCluster) -> Value {
+        let cluster = &unresolved.cluster;
+        match self {
+            Column::X => Value::Float(Some(cluster.center_in_pc.0)),
+            Column::Y => Value::Float(Some(cluster.center_in_pc.1)),
+            Column::Z => Value::Float(Some(cluster.center_in_pc.2)),
+            Column::VelocityX => Value::Float(Some(cluster.velocity_in_km_per_s.0)),
+            Column::VelocityY => Value::Float(Some(cluster.velocity_in_km_per_s.1)),
+            Column::VelocityZ => Value::Float(Some(cluster.velocity_in_km_per_s.2)),
+            Column::InitialMass => Value::Float(Some(cluster.mass_in_solar_masses)),
+            Column::Age => Value::Float(Some(cluster.age_in_gyr)),
+            Column::Metallicity => Value::Float(Some(cluster.metallicity_in_dex)),
+            Column::Population => Value::Text(cluster_population_name(cluster.kind)),
+            Column::AbsoluteMagnitude(band) => {
+                Value::Float(Some(unresolved.absolute_magnitudes.get(*band)))
+            }
+            Column::ApparentMagnitude(band) => {
+                Value::Float(Some(unresolved.apparent_magnitudes.get(*band)))
+            }
+            Column::VisualExtinction => Value::Float(Some(unresolved.visual_extinction_in_mag)),
+            Column::GalacticLongitude
+            | Column::GalacticLatitude
+            | Column::Distance
+            | Column::RightAscension
+            | Column::Declination
+            | Column::EclipticLongitude
+            | Column::EclipticLatitude
+            | Column::ProperMotionRa
+            | Column::ProperMotionDec
+            | Column::RadialVelocity
+            | Column::TangentialVelocity
+            | Column::SrgbRed
+            | Column::SrgbGreen
+            | Column::SrgbBlue
+            | Column::RelativeBrightness => self.observed_value(
+                &unresolved.sky_coordinates,
+                &unresolved.astrometry,
+                unresolved.colour,
+            ),
+            Column::ClusterId => Value::Integer(Some(cluster.id)),
+            Column::Object => Value::Text("UnresolvedCluster"),
+            Column::AngularRadius => Value::Float(Some(unresolved.angular_radius_in_arcsec)),
+            Column::System | Column::Component => Value::Integer(None),
+            Column::Resolved => Value::Flag(None),
+            Column::Subpopulation | Column::VariabilityClass => Value::Text(""),
+            Column::Mass
+            | Column::Luminosity
+            | Column::EffectiveTemperature
+            | Column::CoolingAge
+            | Column::SystemMagnitude(_)
+            | Column::MassRatio
+            | Column::OrbitalPeriod
+            | Column::Eccentricity
+            | Column::SemiMajorAxis
+            | Column::VariabilityPeriod
+            | Column::Amplitude => Value::Float(None),
+        }
+    }
+
+    /// The entries that stars and unresolved clusters share, as seen by the observer.
+    fn observed_value(
+        &self,
+        sky: &SkyCoordinates,
+        astrometry: &Astrometry,
+        colour: Option<Colour>,
+    ) -> Value {
+        match self {
+            Column::GalacticLongitude => Value::Float(sky.galactic.map(|g| g.longitude_in_deg)),
+            Column::GalacticLatitude => Value::Float(sky.galactic.map(|g| g.latitude_in_deg)),
+            Column::Distance => Value::Float(sky.galactic.map(|g| g.distance_in_pc)),
+            Column::RightAscension => {
+                Value::Float(sky.equatorial.map(|e| e.right_ascension_in_deg))
+            }
+            Column::Declination => Value::Float(sky.equatorial.map(|e| e.declination_in_deg)),
+            Column::EclipticLongitude => Value::Float(sky.ecliptic.map(|e| e.longitude_in_deg)),
+            Column::EclipticLatitude => Value::Float(sky.ecliptic.map(|e| e.latitude_in_deg)),
+            Column::ProperMotionRa => Value::Float(Some(astrometry.proper_motion_ra_in_mas_per_yr)),
+            Column::ProperMotionDec => {
+                Value::Float(Some(astrometry.proper_motion_dec_in_mas_per_yr))
+            }
+            Column::RadialVelocity => Value::Float(Some(astrometry.radial_velocity_in_km_per_s)),
+            Column::TangentialVelocity => {
+                Value::Float(Some(astrometry.tangential_velocity_in_km_per_s))
+            }
+            Column::SrgbRed => Value::Float(colour.map(|c| c.srgb.0)),
+            Column::SrgbGreen => Value::Float(colour.map(|c| c.srgb.1)),
+            Column::SrgbBlue => Value::Float(colour.map(|c| c.srgb.2)),
+            Column::RelativeBrightness => Value::Float(colour.map(|c| c.relative_brightness)),
+            _ => Value::Float(None),
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let all = Column::all();
+        all.iter()
+            .find(|column| column.name() == name)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<String> = all.iter().map(Column::name).collect();
+                format!(
+                    "Unknown column '{}', expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+fn band_suffix(band: Option<Band>) -> &'static str {
+    match band {
+        None => "bol",
+        Some(Band::JohnsonB) => "b",
+        Some(Band::JohnsonV) => "v",
+        Some(Band::GaiaG) => "g",
+        Some(Band::GaiaBp) => "bp",
+        Some(Band::GaiaRp) => "rp",
+    }
+}
+
+pub(crate) fn population_name(population: Population) -> &'static str {
+    match population {
+        Population::ThinDisc(_) => "ThinDisc",
+        Population::ThickDisc(_) => "ThickDisc",
+        Population::Spheroid => "Spheroid",
+        Population::Bulge => "Bulge",
+    }
+}
+
+/// The population in which clusters of the given kind form.
+fn cluster_population_name(kind: ClusterKind) -> &'static str {
+    match kind {
+        ClusterKind::OpenCluster | ClusterKind::ObAssociation => "ThinDisc",
+        ClusterKind::GlobularCluster => "Spheroid",
+    }
+}
+
+fn subpopulation_name(population: Population) -> &'static str {
+    match population {
+        Population::ThinDisc(Subpopulation::Alive)
+        | Population::ThickDisc(Subpopulation::Alive) => "Alive",
+        Population::ThinDisc(Subpopulation::WhiteDwarf)
+        | Population::ThickDisc(Subpopulation::WhiteDwarf) => "WhiteDwarf",
+        Population::Spheroid | Population::Bulge => "",
+    }
+}
+
+pub(crate) fn variability_class_name(class: VariabilityClass) -> &'static str {
+    match class {
+        VariabilityClass::ClassicalCepheid => "ClassicalCepheid",
+        VariabilityClass::RrLyrae => "RrLyrae",
+        VariabilityClass::DeltaScuti => "DeltaScuti",
+        VariabilityClass::Mira => "Mira",
+        VariabilityClass::EclipsingBinary => "EclipsingBinary",
+    }
+}
+
+/// The entry of a column for a single star.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Value {
+    Float(Option<f64>),
+    Integer(Option<u64>),
+    Text(&'static str),
+    Flag(Option<bool>),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Float(Some(value)) => write!(f, "{}", value),
+            Value::Integer(Some(value)) => write!(f, "{}", value),
+            Value::Text(value) => write!(f, "{}", value),
+            Value::Flag(Some(value)) => write!(f, "{}", value),
+            Value::Float(None) | Value::Integer(None) | Value::Flag(None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_column_name_is_unique_and_parses_back() {
+        let all = Column::all();
+        for column in all.iter() {
+            assert_eq!(column.name().parse::<Column>(), Ok(*column));
+            assert_eq!(all.iter().filter(|c| c.name() == column.name()).count(), 1);
+        }
+        assert!("mass_in_kg".parse::<Column>().is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use std::{error::Error, io::Write};
+
+use crate::{
+    generation_statistics::GenerationStatistics, generator::GeneratedChunk, star::Star,
+    star_cluster::UnresolvedCluster,
+};
+
+use super::{column::Column, CatalogueHeader, CatalogueWriter, WriterState};
+
+/// Writes one row per star, companion and unresolved cluster, with the selected columns separated by the given character.
+///
+/// The `object` column tells unresolved clusters apart from stars, so it has to be selected if globular clusters are included.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#csv-and-tsv>
+pub(crate) struct CsvWriter<W: Write> {
+    out: W,
+    separator: char,
+    columns: Vec<Column>,
+    next_system: u64,
+    /// Number of bytes written so far.
+    position: u64,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub(crate) fn new(out: W, separator: char, columns: Vec<Column>) -> Self {
+        CsvWriter {
+            out,
+            separator,
+            columns,
+            next_system: 0,
+            position: 0,
+        }
+    }
+
+    fn write_row(&mut self, entries: Vec<String>) -> Result<(), Box<dyn Error>> {
+        let row = entries.join(&self.separator.to_string());
+        writeln!(self.out, "{}", row)?;
+        self.position += row.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn write_star(
+        &mut self,
+        star: &Star,
+        system: u64,
+        component: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        let entries = self
+            .columns
+            .iter()
+            .map(|column| column.value(star, system, component).to_string())
+            .collect();
+        self.write_row(entries)
+    }
+
+    fn write_cluster(&mut self, cluster: &UnresolvedCluster) -> Result<(), Box<dyn Error>> {
+        let entries = self
+            .columns
+            .iter()
+            .map(|column| column.cluster_value(cluster).to_string())
+            .collect();
+        self.write_row(entries)
+    }
+}
+
+impl<W: Write> CatalogueWriter for CsvWriter<W> {
+    fn write_header(&mut self, header: &CatalogueHeader) -> Result<(), Box<dyn Error>> {
+        if header.generation_parameters.include_globular_clusters
+            && !self.columns.contains(&Column::Object)
+        {
+            return Err(format!(
+                "Unresolved globular clusters cannot be told apart from stars without the '{}' column",
+                Column::Object.name()
+            )
+            .into());
+        }
+        let names = self.columns.iter().map(Column::name).collect();
+        self.write_row(names)?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, _index: usize, chunk: GeneratedChunk) -> Result<(), Box<dyn Error>> {
+        for star in chunk.stars.iter() {
+            let system = self.next_system;
+            self.next_system += 1;
+            self.write_star(star, system, 0)?;
+            for (i, companion) in star.companions.iter().enumerate() {
+                self.write_star(companion, system, i + 1)?;
+            }
+        }
+        for cluster in chunk.unresolved_clusters.iter() {
+            self.write_cluster(cluster)?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self, _statistics: &GenerationStatistics) -> Result<(), Box<dyn Error>> {
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn state(&self) -> WriterState {
+        WriterState {
+            offset: self.position,
+            systems: self.next_system,
+            ..Default::default()
+        }
+    }
+
+    fn resume(&mut self, state: &WriterState) -> Result<(), Box<dyn Error>> {
+        self.position = state.offset;
+        self.next_system = state.systems;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        catalogue::write_catalogue,
+        generation_parameters::GenerationParameters,
+        photometry::Magnitudes,
+        star_cluster::{ClusterKind, StarCluster},
+    };
+
+    use super::*;
+
+    fn generate(separator: char, columns: Vec<Column>) -> (String, GenerationStatistics) {
+        let params = GenerationParameters {
+            observer_position_in_pc: Some((8200., 0., 0.)),
+            apparent_magnitude_limit: 6.,
+            max_distance_in_pc: 50.,
+            chunksize_in_pc: 15.,
+            include_companions: true,
+            ..Default::default()
+        };
+        let mut writer = CsvWriter::new(Vec::new(), separator, columns);
+        let statistics = write_catalogue(&params, &mut writer).expect("Generation should succeed");
+        let output = String::from_utf8(writer.out).expect("The output should be UTF-8");
+        (output, statistics)
+    }
+
+    #[test]
+    fn csv_has_a_header_row_and_one_row_per_star_and_companion() {
+        let (output, statistics) = generate(',', Column::all());
+        let mut lines = output.lines();
+        let header = lines.next().expect("There should be a header row");
+        assert!(header.starts_with("system,component,x_pc,y_pc,z_pc,"));
+        assert!(header.contains(",mass_msun,"));
+
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert_eq!(
+            rows.len() as u64,
+            statistics.stars_kept + statistics.companions_kept
+        );
+        for row in rows.iter() {
+            assert_eq!(row.len(), Column::all().len());
+        }
+        let primaries = rows.iter().filter(|row| row[1] == "0").count();
+        assert_eq!(primaries as u64, statistics.stars_kept);
+    }
+
+    #[test]
+    fn tsv_contains_only_the_selected_columns() {
+        let columns = vec![Column::Population, Column::Subpopulation, Column::Mass];
+        let (output, _) = generate('\t', columns);
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some("population\tsubpopulation\tmass_msun"));
+        for line in lines {
+            let entries: Vec<&str> = line.split('\t').collect();
+            assert_eq!(entries.len(), 3);
+            assert!(["ThinDisc", "ThickDisc", "Spheroid", "Bulge"].contains(&entries[0]));
+            assert!(["Alive", "WhiteDwarf", ""].contains(&entries[1]));
+            assert!(entries[2].parse::<f64>().is_ok());
+        }
+    }
+
+    fn header_with_globular_clusters() -> CatalogueHeader {
+        CatalogueHeader {
+            generation_parameters: GenerationParameters {
+                observer_position_in_pc: Some((0., 0., 30_000.)),
+                apparent_magnitude_limit: 10.,
+                max_distance_in_pc: 30_000.,
+                chunksize_in_pc: 2_000.,
+                include_globular_clusters: true,
+                ..Default::default()
+            },
+            epoch_in_kyr: 0.,
+            observer_position_in_pc: (0., 0., 30_000.),
+        }
+    }
+
+    fn unresolved_cluster() -> UnresolvedCluster {
+        UnresolvedCluster {
+            cluster: StarCluster {
+                id: 7,
+                kind: ClusterKind::GlobularCluster,
+                age_in_gyr: 12.,
+                metallicity_in_dex: -1.5,
+                center_in_pc: (100., 200., 300.),
+                velocity_in_km_per_s: (10., 20., 30.),
+                mass_in_solar_masses: 2e5,
+                scale_radius_in_pc: 3.,
+            },
+            absolute_magnitudes: Magnitudes::default(),
+            apparent_magnitudes: Magnitudes::default(),
+            visual_extinction_in_mag: 0.1,
+            angular_radius_in_arcsec: 12.5,
+            sky_coordinates: Default::default(),
+            astrometry: Default::default(),
+            colour: None,
+        }
+    }
+
+    #[test]
+    fn unresolved_clusters_are_written_as_rows_of_their_own_object_type() {
+        let columns = vec![
+            Column::Object,
+            Column::X,
+            Column::InitialMass,
+            Column::Mass,
+            Column::ClusterId,
+            Column::AngularRadius,
+        ];
+        let mut writer = CsvWriter::new(Vec::new(), ',', columns);
+        writer
+            .write_header(&header_with_globular_clusters())
+            .expect("Writing should succeed");
+        let chunk = GeneratedChunk {
+            stars: Vec::new(),
+            unresolved_clusters: vec![unresolved_cluster()],
+        };
+        writer
+            .write_chunk(0, chunk)
+            .expect("Writing should succeed");
+        let output = String::from_utf8(writer.out).expect("The output should be UTF-8");
+        let mut lines = output.lines();
+        assert_eq!(
+            lines.next(),
+            Some("object,x_pc,initial_mass_msun,mass_msun,cluster_id,angular_radius_arcsec")
+        );
+        assert_eq!(lines.next(), Some("UnresolvedCluster,100,200000,,7,12.5"));
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn globular_clusters_require_the_object_column() {
+        let mut writer = CsvWriter::new(Vec::new(), ',', vec![Column::X]);
+        let Err(error) = writer.write_header(&header_with_globular_clusters()) else {
+            panic!("Clusters without the object column should be rejected");
+        };
+        assert!(error.to_string().contains("'object'"));
+    }
+}
@@ -0,0 +1,893 @@
+use std::{
+    error::Error,
+    io::{Seek, SeekFrom, Write},
+};
+
+use crate::{
+    generation_statistics::GenerationStatistics, generator::GeneratedChunk, star::Star,
+    star_cluster::UnresolvedCluster,
+};
+
+use super::{
+    column::{Column, Value},
+    CatalogueHeader, CatalogueWriter, WriterState,
+};
+
+/// Headers and data of a FITS file are padded to multiples of this size, in bytes.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#fits>
+pub(crate) const BLOCK_SIZE: usize = 2880;
+/// Size of a single header card, in bytes.
+pub(crate) const CARD_SIZE: usize = 80;
+/// Length of a string value that still fits on one card together with its quotes and the continuation marker.
+const MAX_STRING_PIECE: usize = 67;
+/// Value written to integer columns for missing entries.
+const INTEGER_NULL: i64 = -1;
+
+/// The value of a header keyword.
+enum CardValue {
+    Logical(bool),
+    Integer(i128),
+    Float(f64),
+    Text(String),
+}
+
+/// Formats a header card with the value in the fixed format of the FITS standard.
+fn card(keyword: &str, value: CardValue, comment: &str) -> String {
+    let value = match value {
+        CardValue::Logical(value) => format!("{:>20}", if value { "T" } else { "F" }),
+        CardValue::Integer(value) => format!("{:>20}", value),
+        CardValue::Float(value) => format!("{:>20}", float_value(value)),
+        CardValue::Text(value) => format!("{:<20}", format!("'{:<8}'", value.replace('\'', "''"))),
+    };
+    let mut card = format!("{:<8}= {}", keyword, value);
+    if !comment.is_empty() {
+        card.push_str(" / ");
+        card.push_str(comment);
+    }
+    pad_card(card)
+}
+
+/// Formats a real value in at most 20 characters, leaving it undefined if it is not finite, which the FITS standard cannot represent.
+fn float_value(value: f64) -> String {
+    if !value.is_finite() {
+        return String::new();
+    }
+    let shortest = format!("{:?}", value).to_uppercase();
+    if shortest.len() <= 20 {
+        shortest
+    } else {
+        format!("{:.12E}", value)
+    }
+}
+
+fn pad_card(card: String) -> String {
+    let mut card: String = card.chars().take(CARD_SIZE).collect();
+    while card.len() < CARD_SIZE {
+        card.push(' ');
+    }
+    card
+}
+
+/// Formats a string that may be too long for a single card, following the `CONTINUE` long string convention.
+fn long_string_cards(keyword: &str, value: &str) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    for character in value.chars() {
+        let escaped = if character == '\'' {
+            "''".to_string()
+        } else {
+            character.to_string()
+        };
+        let current = pieces.len() - 1;
+        if pieces[current].len() + escaped.len() > MAX_STRING_PIECE {
+            pieces.push(String::new());
+        }
+        let current = pieces.len() - 1;
+        pieces[current].push_str(&escaped);
+    }
+    let last = pieces.len() - 1;
+    pieces
+        .iter()
+        .enumerate()
+        .map(|(i, piece)| {
+            let marker = if i < last { "&" } else { "" };
+            let card = if i == 0 {
+                format!("{:<8}= '{}{}'", keyword, piece, marker)
+            } else {
+                format!("CONTINUE  '{}{}'", piece, marker)
+            };
+            pad_card(card)
+        })
+        .collect()
+}
+
+/// Joins the cards, terminates them with `END` and pads them to a whole number of blocks.
+fn header_bytes(cards: &[String]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = cards.iter().flat_map(|card| card.bytes()).collect();
+    bytes.extend(pad_card("END".to_string()).bytes());
+    let padding = (BLOCK_SIZE - bytes.len() % BLOCK_SIZE) % BLOCK_SIZE;
+    bytes.extend(std::iter::repeat_n(b' ', padding));
+    bytes
+}
+
+/// The zeros that pad data of the given length to a whole number of blocks.
+fn data_padding(length: u64) -> Vec<u8> {
+    vec![0; (BLOCK_SIZE - length as usize % BLOCK_SIZE) % BLOCK_SIZE]
+}
+
+/// The binary table data type of a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Double,
+    Long,
+    Logical,
+    Text(usize),
+}
+
+impl Format {
+    fn of(column: Column) -> Self {
+        match column {
+            Column::System | Column::Component | Column::ClusterId => Format::Long,
+            Column::Resolved => Format::Logical,
+            Column::Population => Format::Text(9),
+            Column::Subpopulation => Format::Text(10),
+            Column::VariabilityClass => Format::Text(16),
+            Column::Object => Format::Text(17),
+            _ => Format::Double,
+        }
+    }
+
+    fn tform(&self) -> String {
+        match self {
+            Format::Double => "D".to_string(),
+            Format::Long => "K".to_string(),
+            Format::Logical => "L".to_string(),
+            Format::Text(width) => format!("{}A", width),
+        }
+    }
+
+    fn width(&self) -> usize {
+        match self {
+            Format::Double | Format::Long => 8,
+            Format::Logical => 1,
+            Format::Text(width) => *width,
+        }
+    }
+
+    /// Appends the value in big-endian byte order, with missing values as NaN, the integer null value or an undefined logical.
+    fn encode(&self, value: Value, bytes: &mut Vec<u8>) {
+        match (self, value) {
+            (Format::Double, Value::Float(value)) => {
+                bytes.extend(value.unwrap_or(f64::NAN).to_be_bytes())
+            }
+            (Format::Long, Value::Integer(value)) => bytes.extend(
+                value
+                    .and_then(|value| i64::try_from(value).ok())
+                    .unwrap_or(INTEGER_NULL)
+                    .to_be_bytes(),
+            ),
+            (Format::Logical, Value::Flag(value)) => bytes.push(match value {
+                Some(true) => b'T',
+                Some(false) => b'F',
+                None => 0,
+            }),
+            (Format::Text(width), Value::Text(text)) => {
+                let text: Vec<u8> = text.bytes().take(*width).collect();
+                bytes.extend(&text);
+                bytes.extend(std::iter::repeat_n(b' ', width - text.len()));
+            }
+            (format, _) => bytes.extend(std::iter::repeat_n(0, format.width())),
+        }
+    }
+}
+
+/// The number of bytes of a table row with the given columns.
+pub(crate) fn row_width(columns: &[Column]) -> usize {
+    columns
+        .iter()
+        .map(|column| Format::of(*column).width())
+        .sum()
+}
+
+/// The FITS unit string of a column.
+fn unit(column: Column) -> &'static str {
+    match column {
+        Column::X | Column::Y | Column::Z | Column::Distance => "pc",
+        Column::VelocityX
+        | Column::VelocityY
+        | Column::VelocityZ
+        | Column::RadialVelocity
+        | Column::TangentialVelocity => "km/s",
+        Column::InitialMass | Column::Mass => "solMass",
+        Column::Age | Column::CoolingAge => "Gyr",
+        Column::Metallicity => "dex",
+        Column::Luminosity => "solLum",
+        Column::EffectiveTemperature => "K",
+        Column::AbsoluteMagnitude(_)
+        | Column::ApparentMagnitude(_)
+        | Column::SystemMagnitude(_)
+        | Column::VisualExtinction
+        | Column::Amplitude => "mag",
+        Column::GalacticLongitude
+        | Column::GalacticLatitude
+        | Column::RightAscension
+        | Column::Declination
+        | Column::EclipticLongitude
+        | Column::EclipticLatitude => "deg",
+        Column::ProperMotionRa | Column::ProperMotionDec => "mas/yr",
+        Column::OrbitalPeriod | Column::VariabilityPeriod => "d",
+        Column::SemiMajorAxis => "AU",
+        Column::AngularRadius => "arcsec",
+        _ => "",
+    }
+}
+
+/// The primary header, which records the code version and the generation parameters.
+fn primary_header(header: &CatalogueHeader) -> Result<Vec<String>, Box<dyn Error>> {
+    let params = &header.generation_parameters;
+    let (x, y, z) = header.observer_position_in_pc;
+    let band = params
+        .magnitude_limit_band
+        .map_or("Bolometric".to_string(), |band| format!("{:?}", band));
+    let mut cards = vec![
+        card(
+            "SIMPLE",
+            CardValue::Logical(true),
+            "conforms to FITS standard",
+        ),
+        card("BITPIX", CardValue::Integer(8), "array data type"),
+        card("NAXIS", CardValue::Integer(0), "no primary data"),
+        card(
+            "EXTEND",
+            CardValue::Logical(true),
+            "the stars follow as a table",
+        ),
+        card("CREATOR", CardValue::Text("ursa_lumi".to_string()), ""),
+        card(
+            "VERSION",
+            CardValue::Text(env!("CARGO_PKG_VERSION").to_string()),
+            "version of ursa_lumi",
+        ),
+        card(
+            "LONGSTRN",
+            CardValue::Text("OGIP 1.0".to_string()),
+            "CONTINUE long string convention",
+        ),
+        card(
+            "EPOCHKYR",
+            CardValue::Float(header.epoch_in_kyr),
+            "[kyr] epoch of positions and velocities",
+        ),
+        card(
+            "OBS_X",
+            CardValue::Float(x),
+            "[pc] observer position at epoch",
+        ),
+        card(
+            "OBS_Y",
+            CardValue::Float(y),
+            "[pc] observer position at epoch",
+        ),
+        card(
+            "OBS_Z",
+            CardValue::Float(z),
+            "[pc] observer position at epoch",
+        ),
+        card(
+            "MAGLIM",
+            CardValue::Float(params.apparent_magnitude_limit),
+            "[mag] apparent magnitude limit",
+        ),
+        card(
+            "MAGBAND",
+            CardValue::Text(band),
+            "band of the magnitude limit",
+        ),
+        card(
+            "MAXDIST",
+            CardValue::Float(params.max_distance_in_pc),
+            "[pc] maximal distance to the observer",
+        ),
+        card(
+            "CHUNKSIZ",
+            CardValue::Float(params.chunksize_in_pc),
+            "[pc] edge length of a chunk",
+        ),
+        card(
+            "SEED",
+            CardValue::Integer(params.seed.into()),
+            "seed of the random streams",
+        ),
+        card(
+            "IMPSAMP",
+            CardValue::Logical(params.importance_sampling),
+            "importance sampling",
+        ),
+        card(
+            "COLOUR",
+            CardValue::Logical(params.include_colour),
+            "colours included",
+        ),
+        card(
+            "COMPANIO",
+            CardValue::Logical(params.include_companions),
+            "companions included",
+        ),
+        card(
+            "VARIABLE",
+            CardValue::Logical(params.include_variability),
+            "light curves included",
+        ),
+        card(
+            "CLUSTERS",
+            CardValue::Logical(params.include_clusters),
+            "open clusters and OB associations",
+        ),
+        card(
+            "GLOBCLUS",
+            CardValue::Logical(params.include_globular_clusters),
+            "globular clusters",
+        ),
+        card(
+            "DARKHALO",
+            CardValue::Text(format!("{:?}", params.dark_halo)),
+            "dark matter halo of the potential",
+        ),
+    ];
+    cards.extend(long_string_cards("PARAMS", &serde_json::to_string(params)?));
+    Ok(cards)
+}
+
+/// Writes the catalogue as a FITS file with a binary table extension holding one row per star and companion, followed by one holding the unresolved clusters.
+///
+/// The number of rows and the padding of the data are updated after every chunk, so an interrupted run leaves a valid FITS file with a table of the completed chunks. The unresolved clusters are few, so they are kept until the file is finished.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#fits>
+pub(crate) struct FitsWriter<W: Write + Seek> {
+    out: W,
+    columns: Vec<Column>,
+    next_system: u64,
+    rows: u64,
+    /// Number of bytes written so far, which are a multiple of the block size up to the end of the table header.
+    position: u64,
+    naxis2_offset: u64,
+    unresolved_clusters: Vec<UnresolvedCluster>,
+}
+
+impl<W: Write + Seek> FitsWriter<W> {
+    pub(crate) fn new(out: W, columns: Vec<Column>) -> Self {
+        FitsWriter {
+            out,
+            columns,
+            next_system: 0,
+            rows: 0,
+            position: 0,
+            naxis2_offset: 0,
+            unresolved_clusters: Vec::new(),
+        }
+    }
+
+    fn naxis2_card(rows: u64) -> String {
+        card("NAXIS2", CardValue::Integer(rows.into()), "number of rows")
+    }
+
+    /// The header of a binary table with the selected columns and the given number of rows.
+    fn table_header(&self, rows: u64, name: &str) -> Vec<String> {
+        let row_width = row_width(&self.columns);
+        let mut cards = vec![
+            card(
+                "XTENSION",
+                CardValue::Text("BINTABLE".to_string()),
+                "binary table extension",
+            ),
+            card("BITPIX", CardValue::Integer(8), "array data type"),
+            card("NAXIS", CardValue::Integer(2), "two-dimensional table"),
+            card(
+                "NAXIS1",
+                CardValue::Integer(row_width as i128),
+                "bytes per row",
+            ),
+            Self::naxis2_card(rows),
+            card("PCOUNT", CardValue::Integer(0), "no heap"),
+            card("GCOUNT", CardValue::Integer(1), "one table"),
+            card(
+                "TFIELDS",
+                CardValue::Integer(self.columns.len() as i128),
+                "number of columns",
+            ),
+        ];
+        for (i, column) in self.columns.iter().enumerate() {
+            let n = i + 1;
+            let format = Format::of(*column);
+            cards.push(card(
+                &format!("TTYPE{}", n),
+                CardValue::Text(column.name()),
+                "",
+            ));
+            cards.push(card(
+                &format!("TFORM{}", n),
+                CardValue::Text(format.tform()),
+                "",
+            ));
+            if !unit(*column).is_empty() {
+                cards.push(card(
+                    &format!("TUNIT{}", n),
+                    CardValue::Text(unit(*column).to_string()),
+                    "",
+                ));
+            }
+            if format == Format::Long {
+                cards.push(card(
+                    &format!("TNULL{}", n),
+                    CardValue::Integer(INTEGER_NULL.into()),
+                    "",
+                ));
+            }
+        }
+        cards.push(card("EXTNAME", CardValue::Text(name.to_string()), ""));
+        cards
+    }
+
+    fn encode_star(&self, star: &Star, system: u64, component: usize, bytes: &mut Vec<u8>) {
+        for column in self.columns.iter() {
+            Format::of(*column).encode(column.value(star, system, component), bytes);
+        }
+    }
+
+    /// Overwrites the `NAXIS2` card in place with the current number of rows.
+    fn update_number_of_rows(&mut self) -> Result<(), Box<dyn Error>> {
+        let end = self.out.stream_position()?;
+        self.out.seek(SeekFrom::Start(self.naxis2_offset))?;
+        let card = Self::naxis2_card(self.rows);
+        self.out.write_all(card.as_bytes())?;
+        self.out.seek(SeekFrom::Start(end))?;
+        Ok(())
+    }
+
+    /// Pads the data written so far to a full block and moves back to its end, such that the next rows overwrite the padding.
+    fn pad_data(&mut self) -> Result<(), Box<dyn Error>> {
+        self.out.write_all(&data_padding(self.position))?;
+        self.out.seek(SeekFrom::Start(self.position))?;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> CatalogueWriter for FitsWriter<W> {
+    fn write_header(&mut self, header: &CatalogueHeader) -> Result<(), Box<dyn Error>> {
+        self.out
+            .write_all(&header_bytes(&primary_header(header)?))?;
+        let table_header = self.table_header(self.rows, "STARS");
+        let naxis2_index = table_header
+            .iter()
+            .position(|card| card.starts_with("NAXIS2"))
+            .ok_or("The table header lacks the number of rows")?;
+        self.naxis2_offset = self.out.stream_position()? + (naxis2_index * CARD_SIZE) as u64;
+        self.out.write_all(&header_bytes(&table_header))?;
+        self.position = self.out.stream_position()?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, _index: usize, chunk: GeneratedChunk) -> Result<(), Box<dyn Error>> {
+        let mut bytes = Vec::new();
+        for star in chunk.stars.iter() {
+            let system = self.next_system;
+            self.next_system += 1;
+            self.encode_star(star, system, 0, &mut bytes);
+            self.rows += 1;
+            for (i, companion) in star.companions.iter().enumerate() {
+                self.encode_star(companion, system, i + 1, &mut bytes);
+                self.rows += 1;
+            }
+        }
+        self.unresolved_clusters.extend(chunk.unresolved_clusters);
+        self.out.write_all(&bytes)?;
+        self.position += bytes.len() as u64;
+        self.update_number_of_rows()?;
+        self.pad_data()?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Pads the table of the stars and appends the table of the unresolved clusters.
+    fn finish(&mut self, _statistics: &GenerationStatistics) -> Result<(), Box<dyn Error>> {
+        self.out.write_all(&data_padding(self.position))?;
+        let clusters = self.unresolved_clusters.len() as u64;
+        self.out
+            .write_all(&header_bytes(&self.table_header(clusters, "CLUSTERS")))?;
+        let mut bytes = Vec::new();
+        for cluster in self.unresolved_clusters.iter() {
+            for column in self.columns.iter() {
+                Format::of(*column).encode(column.cluster_value(cluster), &mut bytes);
+            }
+        }
+        bytes.extend(data_padding(bytes.len() as u64));
+        self.out.write_all(&bytes)?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn state(&self) -> WriterState {
+        WriterState {
+            offset: self.position,
+            systems: self.next_system,
+            rows: self.rows,
+            rows_card_offset: self.naxis2_offset,
+            unresolved_clusters: self.unresolved_clusters.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Also restores the number of rows in the table header, which may have been updated after the checkpoint, and the padding of the data.
+    fn resume(&mut self, state: &WriterState) -> Result<(), Box<dyn Error>> {
+        self.position = state.offset;
+        self.next_system = state.systems;
+        self.rows = state.rows;
+        self.naxis2_offset = state.rows_card_offset;
+        self.unresolved_clusters = state.unresolved_clusters.clone();
+        self.update_number_of_rows()?;
+        self.pad_data()?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        generation_parameters::GenerationParameters,
+        multiplicity::Orbit,
+        photometry::Magnitudes,
+        population::{Population, Subpopulation},
+        star_cluster::{ClusterKind, StarCluster},
+    };
+
+    use super::*;
+
+    /// Written by hand following the FITS standard, see the comments in the file.
+    const REFERENCE_HEADERS: &str = include_str!("../../data/fits_reference_header.txt");
+
+    fn header() -> CatalogueHeader {
+        CatalogueHeader {
+            generation_parameters: GenerationParameters {
+                observer_position_in_pc: Some((8200., 0., 20.8)),
+                apparent_magnitude_limit: 6.,
+                max_distance_in_pc: 50.,
+                chunksize_in_pc: 15.,
+                ..Default::default()
+            },
+            epoch_in_kyr: 0.,
+            observer_position_in_pc: (8200., 0., 20.8),
+        }
+    }
+
+    fn columns() -> Vec<Column> {
+        vec![
+            Column::System,
+            Column::Component,
+            Column::X,
+            Column::Population,
+            Column::Resolved,
+            Column::ClusterId,
+        ]
+    }
+
+    fn binary() -> Star {
+        let star = Star {
+            position_in_pc: (8210., 1., 2.),
+            velocity_in_km_per_s: (0., 230., 0.),
+            initial_mass_in_solar_masses: 1.,
+            mass_in_solar_masses: 1.,
+            age_in_gyr: 4.6,
+            metallicity_in_dex: 0.,
+            luminosity_in_solar_units: 1.,
+            effective_temperature_in_k: 5772.,
+            population: Population::ThickDisc(Subpopulation::Alive),
+            cooling_age_in_gyr: None,
+            absolute_magnitudes: Magnitudes::default(),
+            apparent_magnitudes: Magnitudes::default(),
+            system_apparent_magnitudes: None,
+            visual_extinction_in_mag: 0.,
+            sky_coordinates: Default::default(),
+            astrometry: Default::default(),
+            colour: None,
+            orbit: None,
+            companions: Vec::new(),
+            variability: None,
+            cluster_id: Some(42),
+        };
+        let companion = Star {
+            orbit: Some(Orbit {
+                mass_ratio: 0.5,
+                period_in_days: 100.,
+                eccentricity: 0.2,
+                semi_major_axis_in_au: 1.,
+                angular_separation_in_arcsec: 0.1,
+                resolved: true,
+            }),
+            cluster_id: None,
+            ..star.clone()
+        };
+        Star {
+            companions: vec![companion],
+            ..star
+        }
+    }
+
+    fn unresolved_cluster() -> UnresolvedCluster {
+        UnresolvedCluster {
+            cluster: StarCluster {
+                id: 7,
+                kind: ClusterKind::GlobularCluster,
+                age_in_gyr: 12.,
+                metallicity_in_dex: -1.5,
+                center_in_pc: (100., 200., 300.),
+                velocity_in_km_per_s: (10., 20., 30.),
+                mass_in_solar_masses: 2e5,
+                scale_radius_in_pc: 3.,
+            },
+            absolute_magnitudes: Magnitudes::default(),
+            apparent_magnitudes: Magnitudes::default(),
+            visual_extinction_in_mag: 0.1,
+            angular_radius_in_arcsec: 12.5,
+            sky_coordinates: Default::default(),
+            astrometry: Default::default(),
+            colour: None,
+        }
+    }
+
+    fn write(finish: bool) -> Vec<u8> {
+        write_with_clusters(finish, Vec::new())
+    }
+
+    fn write_with_clusters(finish: bool, unresolved_clusters: Vec<UnresolvedCluster>) -> Vec<u8> {
+        let mut writer = FitsWriter::new(Cursor::new(Vec::new()), columns());
+        writer
+            .write_header(&header())
+            .expect("Writing should succeed");
+        let chunk = GeneratedChunk {
+            stars: vec![binary()],
+            unresolved_clusters,
+        };
+        writer
+            .write_chunk(0, chunk)
+            .expect("Writing should succeed");
+        if finish {
+            writer
+                .finish(&GenerationStatistics::default())
+                .expect("Writing should succeed");
+        }
+        writer.out.into_inner()
+    }
+
+    fn cards(bytes: &[u8]) -> Vec<&str> {
+        bytes
+            .chunks(CARD_SIZE)
+            .map(|card| std::str::from_utf8(card).expect("Cards should be ASCII"))
+            .collect()
+    }
+
+    /// The cards of every header of the reference file, padded to the card size.
+    fn reference_headers() -> Vec<Vec<String>> {
+        let mut headers = vec![Vec::new()];
+        for line in REFERENCE_HEADERS.lines() {
+            if line.starts_with('#') {
+                continue;
+            }
+            let current = headers.len() - 1;
+            headers[current].push(format!("{:<80}", line));
+            if line == "END" {
+                headers.push(Vec::new());
+            }
+        }
+        headers.pop();
+        headers
+    }
+
+    fn integer_value(card: &str) -> usize {
+        card[10..30]
+            .trim()
+            .parse()
+            .expect("The value is an integer")
+    }
+
+    /// The cards of every header of a FITS file, skipping the data between them.
+    fn written_headers(output: &[u8]) -> Vec<Vec<&str>> {
+        let mut headers = Vec::new();
+        let mut position = 0;
+        while position < output.len() {
+            let mut header = Vec::new();
+            for bytes in output[position..].chunks(CARD_SIZE) {
+                let card = std::str::from_utf8(bytes).expect("Cards should be ASCII");
+                position += CARD_SIZE;
+                header.push(card);
+                if card.trim_end() == "END" {
+                    break;
+                }
+            }
+            position = position.next_multiple_of(BLOCK_SIZE);
+            let dimension = |keyword: &str| {
+                header
+                    .iter()
+                    .find(|card| card.starts_with(keyword))
+                    .map_or(0, |card| integer_value(card))
+            };
+            let data = dimension("NAXIS1 ") * dimension("NAXIS2 ");
+            position = (position + data).next_multiple_of(BLOCK_SIZE);
+            headers.push(header);
+        }
+        headers
+    }
+
+    /// The length of the primary header and the header of the stars, after which the rows of the stars start.
+    fn star_data_offset() -> usize {
+        reference_headers()[..2]
+            .iter()
+            .map(|header| (header.len() * CARD_SIZE).next_multiple_of(BLOCK_SIZE))
+            .sum()
+    }
+
+    #[test]
+    fn headers_match_the_reference_file() {
+        let output = write(true);
+        let written = written_headers(&output);
+        let reference = reference_headers();
+        assert_eq!(written.len(), reference.len());
+        for (written, reference) in written.iter().zip(reference.iter()) {
+            assert_eq!(written.len(), reference.len());
+            for (written, reference) in written.iter().zip(reference.iter()) {
+                if reference.starts_with("VERSION ") {
+                    assert!(written.contains(&format!("'{:<8}'", env!("CARGO_PKG_VERSION"))));
+                } else {
+                    assert_eq!(written, reference);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cards_follow_the_fixed_format() {
+        let output = write(true);
+        assert_eq!(output.len() % BLOCK_SIZE, 0);
+        for card in written_headers(&output).concat() {
+            assert_eq!(card.len(), CARD_SIZE);
+            assert!(card.bytes().all(|byte| (b' '..=b'~').contains(&byte)));
+            if &card[8..10] != "= " {
+                continue;
+            }
+            if card.as_bytes()[10] == b'\'' {
+                assert!(card[11..].contains('\''), "{}", card);
+            } else {
+                assert_ne!(card.as_bytes()[29], b' ', "{}", card);
+                assert!(card[10..29].trim_start().find(' ').is_none(), "{}", card);
+                assert!(card[30..].trim().is_empty() || card[30..].starts_with(" / "));
+            }
+        }
+    }
+
+    #[test]
+    fn rows_are_big_endian_and_counted_after_every_chunk() {
+        let output = write(false);
+        assert_eq!(output.len() % BLOCK_SIZE, 0);
+        let data = &output[star_data_offset()..];
+        let row_width = 8 + 8 + 8 + 9 + 1 + 8;
+        assert_eq!(data.len(), BLOCK_SIZE);
+        assert!(data[2 * row_width..].iter().all(|&byte| byte == 0));
+        assert!(cards(&output[..star_data_offset()])
+            .iter()
+            .any(|card| card.starts_with("NAXIS1  =                   42")));
+        assert!(cards(&output[..star_data_offset()])
+            .iter()
+            .any(|card| card.starts_with("NAXIS2  =                    2")));
+
+        let (primary, companion) = data.split_at(row_width);
+        assert_eq!(
+            &primary[..16],
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            &companion[..16],
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]
+        );
+        assert_eq!(&primary[16..24], &8210f64.to_be_bytes());
+        assert_eq!(&primary[24..33], b"ThickDisc");
+        assert_eq!(primary[33], 0);
+        assert_eq!(companion[33], b'T');
+        assert_eq!(&primary[34..42], &42i64.to_be_bytes());
+        assert_eq!(&companion[34..42], &INTEGER_NULL.to_be_bytes());
+    }
+
+    #[test]
+    fn unresolved_clusters_follow_in_a_table_of_their_own() {
+        let output = write_with_clusters(true, vec![unresolved_cluster()]);
+        assert_eq!(output.len() % BLOCK_SIZE, 0);
+        let clusters = &output[star_data_offset() + BLOCK_SIZE..];
+        let header: Vec<&str> = cards(&clusters[..BLOCK_SIZE]);
+        assert!(header[0].starts_with("XTENSION= 'BINTABLE'"));
+        assert!(header
+            .iter()
+            .any(|card| card.starts_with("NAXIS2  =                    1")));
+        assert!(header
+            .iter()
+            .any(|card| card.starts_with("EXTNAME = 'CLUSTERS'")));
+
+        let row = &clusters[BLOCK_SIZE..BLOCK_SIZE + 42];
+        assert_eq!(&row[..8], &INTEGER_NULL.to_be_bytes());
+        assert_eq!(&row[8..16], &INTEGER_NULL.to_be_bytes());
+        assert_eq!(&row[16..24], &100f64.to_be_bytes());
+        assert_eq!(&row[24..33], b"Spheroid ");
+        assert_eq!(row[33], 0);
+        assert_eq!(&row[34..42], &7i64.to_be_bytes());
+        assert!(clusters[BLOCK_SIZE + 42..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn unresolved_clusters_are_kept_in_the_checkpoint_until_the_file_is_finished() {
+        let mut writer = FitsWriter::new(Cursor::new(Vec::new()), columns());
+        writer
+            .write_header(&header())
+            .expect("Writing should succeed");
+        let chunk = GeneratedChunk {
+            stars: Vec::new(),
+            unresolved_clusters: vec![unresolved_cluster()],
+        };
+        writer
+            .write_chunk(0, chunk)
+            .expect("Writing should succeed");
+        let state = writer.state();
+        assert_eq!(state.unresolved_clusters, vec![unresolved_cluster()]);
+
+        let mut resumed = FitsWriter::new(writer.out, columns());
+        resumed.resume(&state).expect("Resuming should succeed");
+        assert_eq!(resumed.state(), state);
+    }
+
+    #[test]
+    fn long_strings_are_continued_over_several_cards() {
+        let value = "It's a long value. ".repeat(10);
+        let cards = long_string_cards("LONG", &value);
+        assert!(cards.len() > 1);
+        let mut joined = String::new();
+        for (i, card) in cards.iter().enumerate() {
+            assert_eq!(card.len(), CARD_SIZE);
+            let prefix = if i == 0 { "LONG    = '" } else { "CONTINUE  '" };
+            assert!(card.starts_with(prefix));
+            let content = card[prefix.len()..].trim_end();
+            let content = content
+                .strip_suffix('\'')
+                .expect("Strings end with a quote");
+            joined.push_str(content.strip_suffix('&').unwrap_or(content));
+        }
+        assert_eq!(joined.replace("''", "'"), value);
+    }
+
+    #[test]
+    fn real_values_fit_into_the_value_field() {
+        for value in [
+            0.,
+            -1.5,
+            1e-7,
+            8200.123456789,
+            -1.2345678901234567e-300,
+            f64::MAX,
+        ] {
+            let card = card("REAL", CardValue::Float(value), "comment");
+            assert_eq!(card.len(), CARD_SIZE);
+            assert_eq!(&card[30..33], " / ");
+            let parsed: f64 = card[10..30].trim().parse().expect("The value is a number");
+            assert!((parsed - value).abs() <= 1e-12 * value.abs());
+        }
+    }
+
+    #[test]
+    fn non_finite_real_values_are_undefined() {
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let card = card("REAL", CardValue::Float(value), "comment");
+            assert_eq!(card.len(), CARD_SIZE);
+            assert!(card[10..30].trim().is_empty());
+            assert_eq!(&card[30..33], " / ");
+        }
+    }
+}
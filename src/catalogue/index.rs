@@ -0,0 +1,375 @@
+use std::{
+    error::Error,
+    io::{Read, Seek},
+};
+
+use crate::{
+    coordinates::{galactic_from_position, to_unit_vector},
+    galactocentric_frame::GalactocentricFrame,
+    star::Star,
+};
+
+use super::{binary::BinaryReader, CatalogueHeader, Record};
+
+/// The location of the stars of one chunk in a binary catalogue, together with the box that contains them.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#spatial-index>
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct IndexEntry {
+    pub(crate) chunk_index: u64,
+    /// Position of the first record of the chunk, in bytes from the start of the file.
+    pub(crate) offset: u64,
+    /// Galactocentric corner of the bounding box of the stars and their companions with the smallest coordinates, in units of parsec.
+    pub(crate) min_in_pc: (f64, f64, f64),
+    /// Galactocentric corner of the bounding box with the largest coordinates, in units of parsec.
+    pub(crate) max_in_pc: (f64, f64, f64),
+    pub(crate) number_of_stars: u64,
+}
+
+impl IndexEntry {
+    /// The entry of a chunk with the given stars, or `None` if there are no stars to find.
+    pub(crate) fn new(chunk_index: u64, offset: u64, stars: &[Star]) -> Option<Self> {
+        let mut positions = stars.iter().flat_map(|star| {
+            std::iter::once(star.position_in_pc)
+                .chain(star.companions.iter().map(|c| c.position_in_pc))
+        });
+        let first = positions.next()?;
+        let (min_in_pc, max_in_pc) = positions.fold((first, first), |(min, max), p| {
+            (
+                (min.0.min(p.0), min.1.min(p.1), min.2.min(p.2)),
+                (max.0.max(p.0), max.1.max(p.1), max.2.max(p.2)),
+            )
+        });
+        Some(IndexEntry {
+            chunk_index,
+            offset,
+            min_in_pc,
+            max_in_pc,
+            number_of_stars: stars.len() as u64,
+        })
+    }
+}
+
+/// A region of space to search a catalogue for.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#spatial-index>
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Query {
+    /// All stars inside a box with the given galactocentric corners, in units of parsec.
+    Box {
+        /// The corner with the smallest coordinates.
+        min_in_pc: (f64, f64, f64),
+        /// The corner with the largest coordinates.
+        max_in_pc: (f64, f64, f64),
+    },
+    /// All stars within the given angle of a direction in galactic coordinates as seen by the observer, in units of degrees.
+    Cone {
+        /// Galactic longitude of the center of the cone.
+        longitude_in_deg: f64,
+        /// Galactic latitude of the center of the cone.
+        latitude_in_deg: f64,
+        /// Angle between the center and the edge of the cone.
+        radius_in_deg: f64,
+    },
+}
+
+impl Query {
+    /// Whether any point of the box may match, erring on the side of caution.
+    fn may_contain(
+        &self,
+        entry: &IndexEntry,
+        observer_position_in_pc: (f64, f64, f64),
+        frame: &GalactocentricFrame,
+    ) -> bool {
+        match *self {
+            Query::Box {
+                min_in_pc,
+                max_in_pc,
+            } => {
+                entry.min_in_pc.0 <= max_in_pc.0
+                    && entry.min_in_pc.1 <= max_in_pc.1
+                    && entry.min_in_pc.2 <= max_in_pc.2
+                    && entry.max_in_pc.0 >= min_in_pc.0
+                    && entry.max_in_pc.1 >= min_in_pc.1
+                    && entry.max_in_pc.2 >= min_in_pc.2
+            }
+            Query::Cone {
+                longitude_in_deg,
+                latitude_in_deg,
+                radius_in_deg,
+            } => {
+                let (min, max) = (entry.min_in_pc, entry.max_in_pc);
+                let center = (
+                    (min.0 + max.0) / 2.,
+                    (min.1 + max.1) / 2.,
+                    (min.2 + max.2) / 2.,
+                );
+                let half_diagonal =
+                    ((max.0 - min.0).powi(2) + (max.1 - min.1).powi(2) + (max.2 - min.2).powi(2))
+                        .sqrt()
+                        / 2.;
+                let galactic = galactic_from_position(center, observer_position_in_pc, frame);
+                if galactic.distance_in_pc <= half_diagonal {
+                    return true;
+                }
+                let angular_radius = (half_diagonal / galactic.distance_in_pc)
+                    .asin()
+                    .to_degrees();
+                angular_separation_in_deg(
+                    (longitude_in_deg, latitude_in_deg),
+                    (galactic.longitude_in_deg, galactic.latitude_in_deg),
+                ) <= radius_in_deg + angular_radius
+            }
+        }
+    }
+
+    fn contains(
+        &self,
+        position_in_pc: (f64, f64, f64),
+        observer_position_in_pc: (f64, f64, f64),
+        frame: &GalactocentricFrame,
+    ) -> bool {
+        match *self {
+            Query::Box {
+                min_in_pc,
+                max_in_pc,
+            } => {
+                let (x, y, z) = position_in_pc;
+                (min_in_pc.0..=max_in_pc.0).contains(&x)
+                    && (min_in_pc.1..=max_in_pc.1).contains(&y)
+                    && (min_in_pc.2..=max_in_pc.2).contains(&z)
+            }
+            Query::Cone {
+                longitude_in_deg,
+                latitude_in_deg,
+                radius_in_deg,
+            } => {
+                let galactic =
+                    galactic_from_position(position_in_pc, observer_position_in_pc, frame);
+                angular_separation_in_deg(
+                    (longitude_in_deg, latitude_in_deg),
+                    (galactic.longitude_in_deg, galactic.latitude_in_deg),
+                ) <= radius_in_deg
+            }
+        }
+    }
+}
+
+/// The angle between two directions given as longitude and latitude, in units of degrees.
+fn angular_separation_in_deg(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let a = to_unit_vector(a.0, a.1);
+    let b = to_unit_vector(b.0, b.1);
+    let cos = a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    cos.clamp(-1., 1.).acos().to_degrees()
+}
+
+/// A function that is handed every star matching a query as soon as it is read, and may abort the query by returning an error.
+pub type MatchCallback<'a> = dyn FnMut(Star) -> Result<(), Box<dyn Error>> + 'a;
+
+/// Hands all stars of a complete binary catalogue that match the query to the given function, reading only the chunks whose bounding box may match, and returns the header of the catalogue.
+///
+/// No more than one star is held in memory, however many match.
+/// ```no_run
+/// use std::{fs::File, io::BufReader};
+/// use ursa_lumi::{query_catalogue, Query};
+///
+/// let input = BufReader::new(File::open("catalogue.bin")?);
+/// let query = Query::Cone {
+///     longitude_in_deg: 0.,
+///     latitude_in_deg: 0.,
+///     radius_in_deg: 1.,
+/// };
+/// let mut stars = Vec::new();
+/// query_catalogue(input, &query, &mut |star| {
+///     stars.push(star);
+///     Ok(())
+/// })?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#spatial-index>
+pub fn query_catalogue<R: Read + Seek>(
+    input: R,
+    query: &Query,
+    on_match: &mut MatchCallback,
+) -> Result<CatalogueHeader, Box<dyn Error>> {
+    let mut reader = BinaryReader::new(input)?;
+    let header = reader.header().clone();
+    let observer = header.observer_position_in_pc;
+    let frame = header.generation_parameters.galactocentric_frame;
+    for entry in reader.read_index()? {
+        if !query.may_contain(&entry, observer, &frame) {
+            continue;
+        }
+        reader.seek(entry.offset)?;
+        while let Some(record) = reader.read_record()? {
+            match record {
+                Record::Star(star) if query.contains(star.position_in_pc, observer, &frame) => {
+                    on_match(*star)?
+                }
+                Record::ChunkCompleted(_) => break,
+                _ => {}
+            }
+        }
+    }
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        catalogue::{binary::BinaryWriter, write_catalogue, CatalogueWriter},
+        generation_parameters::GenerationParameters,
+        generator::GeneratedChunk,
+    };
+
+    use super::*;
+
+    fn catalogue() -> Vec<u8> {
+        let params = GenerationParameters {
+            observer_position_in_pc: Some((8200., 0., 0.)),
+            apparent_magnitude_limit: 7.,
+            max_distance_in_pc: 60.,
+            chunksize_in_pc: 15.,
+            ..Default::default()
+        };
+        let mut catalogue = Vec::new();
+        write_catalogue(
+            &params,
+            &mut BinaryWriter::new(&mut catalogue, Cursor::new(Vec::new())),
+        )
+        .expect("Generation should succeed");
+        catalogue
+    }
+
+    fn query(catalogue: &[u8], query: &Query) -> Vec<Star> {
+        let mut found = Vec::new();
+        query_catalogue(Cursor::new(catalogue), query, &mut |star| {
+            found.push(star);
+            Ok(())
+        })
+        .expect("The query should succeed");
+        found
+    }
+
+    fn full_scan(catalogue: &[u8], query: &Query) -> Vec<Star> {
+        let mut reader = BinaryReader::new(catalogue).expect("The header should be valid");
+        let header = reader.header().clone();
+        let frame = header.generation_parameters.galactocentric_frame;
+        let mut stars = Vec::new();
+        while let Some(record) = reader.read_record().expect("The record should be valid") {
+            if let Record::Star(star) = record {
+                if query.contains(star.position_in_pc, header.observer_position_in_pc, &frame) {
+                    stars.push(*star);
+                }
+            }
+        }
+        stars
+    }
+
+    fn assert_same_stars(mut found: Vec<Star>, mut expected: Vec<Star>) {
+        let key = |star: &Star| star.position_in_pc.0;
+        found.sort_by(|a, b| key(a).total_cmp(&key(b)));
+        expected.sort_by(|a, b| key(a).total_cmp(&key(b)));
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn box_query_finds_the_same_stars_as_a_full_scan() {
+        let catalogue = catalogue();
+        let box_query = Query::Box {
+            min_in_pc: (8180., -10., -20.),
+            max_in_pc: (8230., 25., 5.),
+        };
+        let found = query(&catalogue, &box_query);
+        let expected = full_scan(&catalogue, &box_query);
+        assert!(!expected.is_empty());
+        let whole_sky = Query::Cone {
+            longitude_in_deg: 0.,
+            latitude_in_deg: 0.,
+            radius_in_deg: 180.,
+        };
+        assert!(expected.len() < full_scan(&catalogue, &whole_sky).len());
+        assert_same_stars(found, expected);
+    }
+
+    #[test]
+    fn cone_query_finds_the_same_stars_as_a_full_scan() {
+        let catalogue = catalogue();
+        for (longitude_in_deg, latitude_in_deg) in [(0., 0.), (90., 30.), (200., -60.)] {
+            let cone = Query::Cone {
+                longitude_in_deg,
+                latitude_in_deg,
+                radius_in_deg: 40.,
+            };
+            let found = query(&catalogue, &cone);
+            let expected = full_scan(&catalogue, &cone);
+            assert!(!expected.is_empty());
+            assert_same_stars(found, expected);
+        }
+    }
+
+    #[test]
+    fn distant_chunks_are_skipped() {
+        let catalogue = catalogue();
+        let mut reader =
+            BinaryReader::new(Cursor::new(&catalogue)).expect("The header should be valid");
+        let index = reader.read_index().expect("The index should be valid");
+        let frame = reader.header().generation_parameters.galactocentric_frame;
+        let observer = reader.header().observer_position_in_pc;
+        let query = Query::Box {
+            min_in_pc: (8200., 0., 0.),
+            max_in_pc: (8210., 10., 10.),
+        };
+        let visited = index
+            .iter()
+            .filter(|entry| query.may_contain(entry, observer, &frame))
+            .count();
+        assert!(visited > 0);
+        assert!(visited < index.len() / 4);
+    }
+
+    #[test]
+    fn incomplete_catalogues_cannot_be_queried() {
+        let mut incomplete = Vec::new();
+        let mut writer = BinaryWriter::new(&mut incomplete, Cursor::new(Vec::new()));
+        let header = CatalogueHeader {
+            generation_parameters: GenerationParameters::default(),
+            epoch_in_kyr: 0.,
+            observer_position_in_pc: (8200., 0., 0.),
+        };
+        writer
+            .write_header(&header)
+            .expect("Writing should succeed");
+        writer
+            .write_chunk(0, GeneratedChunk::default())
+            .expect("Writing should succeed");
+        let query = Query::Box {
+            min_in_pc: (0., 0., 0.),
+            max_in_pc: (1e4, 1e4, 1e4),
+        };
+        assert!(query_catalogue(Cursor::new(&incomplete), &query, &mut |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn matches_are_handed_over_while_reading_and_can_abort_the_query() {
+        let catalogue = catalogue();
+        let whole_sky = Query::Cone {
+            longitude_in_deg: 0.,
+            latitude_in_deg: 0.,
+            radius_in_deg: 180.,
+        };
+        let mut handed_over = 0;
+        let result = query_catalogue(Cursor::new(&catalogue), &whole_sky, &mut |_| {
+            handed_over += 1;
+            if handed_over == 3 {
+                return Err("Enough stars".into());
+            }
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(handed_over, 3);
+    }
+}
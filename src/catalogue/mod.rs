@@ -0,0 +1,463 @@
+use std::{error::Error, io::BufRead};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    galactic_chunk::GalacticChunk,
+    generation_parameters::GenerationParameters,
+    generation_statistics::GenerationStatistics,
+    generator::{distance_in_pc, GeneratedChunk, Generator},
+    progress::{Progress, ProgressCallback},
+    star::Star,
+    star_cluster::UnresolvedCluster,
+};
+
+use self::{
+    binary::BinaryReader,
+    checkpoint::{Checkpoint, Checkpointer},
+    ndjson::NdjsonReader,
+};
+
+pub(crate) mod binary;
+pub(crate) mod checkpoint;
+pub(crate) mod column;
+pub(crate) mod csv;
+pub(crate) mod fits;
+pub(crate) mod index;
+pub(crate) mod ndjson;
+pub(crate) mod summary;
+
+/// The file format of the generated catalogue.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#output>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum OutputFormat {
+    /// Newline-delimited JSON, one record per line.
+    Ndjson,
+    /// Compact little-endian binary with fixed-width star records.
+    Binary,
+    /// Comma-separated values, one row per star.
+    Csv,
+    /// Tab-separated values, one row per star.
+    Tsv,
+    /// A FITS binary table, one row per star.
+    Fits,
+}
+
+/// Everything about a catalogue that is known before the first star is generated.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#output>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogueHeader {
+    pub(crate) generation_parameters: GenerationParameters,
+    /// The time relative to the present at which the positions and velocities of the stars are given, in units of kiloyears.
+    #[serde(default)]
+    pub(crate) epoch_in_kyr: f64,
+    /// The position of the observer at that epoch, from which magnitudes and sky coordinates are computed.
+    pub(crate) observer_position_in_pc: (f64, f64, f64),
+}
+
+impl CatalogueHeader {
+    /// The parameters the catalogue was generated with.
+    pub fn generation_parameters(&self) -> &GenerationParameters {
+        &self.generation_parameters
+    }
+
+    /// The time relative to the present at which the positions and velocities of the stars are given, in units of kiloyears.
+    pub fn epoch_in_kyr(&self) -> f64 {
+        self.epoch_in_kyr
+    }
+
+    /// The position of the observer at the epoch of the catalogue, from which magnitudes and sky coordinates are computed.
+    pub fn observer_position_in_pc(&self) -> (f64, f64, f64) {
+        self.observer_position_in_pc
+    }
+}
+
+/// A single entry of a catalogue, in the order in which it is written.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#output>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Record {
+    /// The first record of every catalogue.
+    Header(CatalogueHeader),
+    /// A star that is brighter than the magnitude limit, with its companions nested inside it.
+    Star(Box<Star>),
+    /// A globular cluster that is seen as a single object.
+    UnresolvedCluster(Box<UnresolvedCluster>),
+    /// Marks that all stars of the chunk with the given index have been written.
+    ChunkCompleted(usize),
+    /// The last record of a complete catalogue.
+    Statistics(GenerationStatistics),
+}
+
+/// A sink for a catalogue that is written while it is being generated.
+///
+/// The header is written first, then every chunk in the order in which it is generated, and the statistics last.
+///
+/// Every chunk is flushed to the output before `write_chunk` returns, so that a checkpoint taken afterwards describes output that has actually been written.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#output>
+pub(crate) trait CatalogueWriter {
+    fn write_header(&mut self, header: &CatalogueHeader) -> Result<(), Box<dyn Error>>;
+
+    fn write_chunk(&mut self, index: usize, chunk: GeneratedChunk) -> Result<(), Box<dyn Error>>;
+
+    fn finish(&mut self, statistics: &GenerationStatistics) -> Result<(), Box<dyn Error>>;
+
+    /// Everything besides the output itself that is needed to continue writing after the chunks written so far.
+    fn state(&self) -> WriterState;
+
+    /// Continues writing after the chunks of an earlier run, instead of starting with the header.
+    ///
+    /// The output has to be positioned at the offset of the state, with everything after it removed.
+    fn resume(&mut self, state: &WriterState) -> Result<(), Box<dyn Error>>;
+}
+
+/// The state of a writer after a chunk, as saved in a checkpoint.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#checkpoints>
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WriterState {
+    /// Number of bytes written so far.
+    pub(crate) offset: u64,
+    /// Number of star systems written so far, which continues the numbering of the rows of the tables.
+    #[serde(default)]
+    pub(crate) systems: u64,
+    /// Number of rows of the FITS table, including companions.
+    #[serde(default)]
+    pub(crate) rows: u64,
+    /// Position of the card of the FITS table header holding the number of rows.
+    #[serde(default)]
+    pub(crate) rows_card_offset: u64,
+    /// Number of entries of the spatial index of a binary catalogue written so far.
+    #[serde(default)]
+    pub(crate) index_entries: u64,
+    /// Unresolved clusters of the FITS format written so far, which are only written to the file when it is finished.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) unresolved_clusters: Vec<UnresolvedCluster>,
+}
+
+/// Generates the catalogue described by the generation parameters and hands it to the writer chunk by chunk, so that no more than one chunk is held in memory.
+///
+/// The progress is handed to the given function after every chunk, and checkpoints are saved if a checkpointer is given.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#output>
+pub(crate) fn write_catalogue_with_progress(
+    generation_parameters: &GenerationParameters,
+    writer: &mut (impl CatalogueWriter + ?Sized),
+    progress: &mut ProgressCallback,
+    checkpointer: Option<&mut Checkpointer>,
+) -> Result<GenerationStatistics, Box<dyn Error>> {
+    let generator = Generator::new(generation_parameters)?;
+    writer.write_header(&CatalogueHeader {
+        generation_parameters: generation_parameters.clone(),
+        epoch_in_kyr: generation_parameters.epoch.offset_in_kyr,
+        observer_position_in_pc: generator.observer_position_in_pc(),
+    })?;
+    let statistics = GenerationStatistics::default();
+    generate_remaining_chunks(
+        &generator,
+        generation_parameters,
+        0,
+        statistics,
+        writer,
+        progress,
+        checkpointer,
+    )
+}
+
+/// Continues an interrupted run after its last checkpoint.
+///
+/// The writer has to write to the output of the interrupted run, truncated to the offset of the checkpoint. The result is the same as if the run had never been interrupted.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#checkpoints>
+pub(crate) fn resume_catalogue(
+    checkpoint: &Checkpoint,
+    writer: &mut (impl CatalogueWriter + ?Sized),
+    progress: &mut ProgressCallback,
+    checkpointer: &mut Checkpointer,
+) -> Result<GenerationStatistics, Box<dyn Error>> {
+    let generation_parameters = &checkpoint.generation_parameters;
+    let generator = Generator::new(generation_parameters)?;
+    writer.resume(&checkpoint.writer)?;
+    generate_remaining_chunks(
+        &generator,
+        generation_parameters,
+        checkpoint.chunks_completed,
+        checkpoint.statistics.clone(),
+        writer,
+        progress,
+        Some(checkpointer),
+    )
+}
+
+/// Grows a complete catalogue to a larger maximal distance, by copying its chunks and generating only the new ones.
+///
+/// The result is the same as if the catalogue had been generated with the larger distance from the start.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#extending-a-catalogue>
+pub(crate) fn extend_catalogue<R: BufRead>(
+    reader: &mut CatalogueReader<R>,
+    max_distance_in_pc: f64,
+    writer: &mut (impl CatalogueWriter + ?Sized),
+    progress: &mut ProgressCallback,
+) -> Result<GenerationStatistics, Box<dyn Error>> {
+    let header = reader.header().clone();
+    let previous_parameters = &header.generation_parameters;
+    if max_distance_in_pc <= previous_parameters.max_distance_in_pc {
+        return Err(format!(
+            "The catalogue already extends to {} pc",
+            previous_parameters.max_distance_in_pc
+        )
+        .into());
+    }
+    let previous_chunks = GalacticChunk::number_of_chunks(previous_parameters) as usize;
+    let generation_parameters = GenerationParameters {
+        max_distance_in_pc,
+        ..previous_parameters.clone()
+    };
+    let generator = Generator::new(&generation_parameters)?;
+    writer.write_header(&CatalogueHeader {
+        generation_parameters: generation_parameters.clone(),
+        ..header
+    })?;
+    let (copied, statistics) = copy_chunks(reader, writer)?;
+    let Some(statistics) = statistics.filter(|_| copied == previous_chunks) else {
+        return Err("Only complete catalogues can be extended".into());
+    };
+    generate_remaining_chunks(
+        &generator,
+        &generation_parameters,
+        copied,
+        statistics,
+        writer,
+        progress,
+        None,
+    )
+}
+
+/// Writes a complete catalogue again, usually in another format.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#output>
+pub(crate) fn convert_catalogue<R: BufRead>(
+    reader: &mut CatalogueReader<R>,
+    writer: &mut (impl CatalogueWriter + ?Sized),
+) -> Result<GenerationStatistics, Box<dyn Error>> {
+    writer.write_header(&reader.header().clone())?;
+    let (_, statistics) = copy_chunks(reader, writer)?;
+    let Some(statistics) = statistics else {
+        return Err("Only complete catalogues can be converted".into());
+    };
+    writer.finish(&statistics)?;
+    Ok(statistics)
+}
+
+/// Generates all chunks from the given index on, continuing the statistics of the chunks before.
+fn generate_remaining_chunks(
+    generator: &Generator,
+    generation_parameters: &GenerationParameters,
+    first_index: usize,
+    mut statistics: GenerationStatistics,
+    writer: &mut (impl CatalogueWriter + ?Sized),
+    progress: &mut ProgressCallback,
+    mut checkpointer: Option<&mut Checkpointer>,
+) -> Result<GenerationStatistics, Box<dyn Error>> {
+    let chunks = GalacticChunk::generate_chunks(generation_parameters);
+    let total_chunks = chunks.len();
+    for (index, chunk) in chunks.enumerate().skip(first_index) {
+        let generated = generator.generate_chunk(index, &chunk, &mut statistics);
+        writer.write_chunk(index, generated)?;
+        if let Some(checkpointer) = checkpointer.as_deref_mut() {
+            checkpointer.save_if_due(index + 1, &statistics, &*writer)?;
+        }
+        progress(&Progress {
+            chunks_completed: index + 1,
+            chunks_before_run: first_index,
+            total_chunks,
+            distance_in_pc: distance_in_pc(
+                chunk.center(generation_parameters.chunksize_in_pc),
+                generation_parameters.observer_position_in_pc(),
+            ),
+            statistics: &statistics,
+        })?;
+    }
+    writer.finish(&statistics)?;
+    if let Some(checkpointer) = checkpointer {
+        checkpointer.remove()?;
+    }
+    Ok(statistics)
+}
+
+/// Hands every complete chunk of the catalogue to the writer, and returns their number together with the statistics if the catalogue is complete.
+fn copy_chunks<R: BufRead>(
+    reader: &mut CatalogueReader<R>,
+    writer: &mut (impl CatalogueWriter + ?Sized),
+) -> Result<(usize, Option<GenerationStatistics>), Box<dyn Error>> {
+    let mut chunk = GeneratedChunk::default();
+    let mut copied = 0;
+    while let Some(record) = reader.read_record()? {
+        match record {
+            Record::Header(_) => return Err("The catalogue contains a second header".into()),
+            Record::Star(star) => chunk.stars.push(*star),
+            Record::UnresolvedCluster(cluster) => chunk.unresolved_clusters.push(*cluster),
+            Record::ChunkCompleted(index) => {
+                writer.write_chunk(index, std::mem::take(&mut chunk))?;
+                copied += 1;
+            }
+            Record::Statistics(statistics) => return Ok((copied, Some(statistics))),
+        }
+    }
+    Ok((copied, None))
+}
+
+/// Reads a catalogue in any of the formats that contain all of its records, recognising the format by the first bytes.
+///
+/// These are the `ndjson` and `binary` formats. The tables of the `csv`, `tsv` and `fits` formats omit records and cannot be read again.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#output>
+pub struct CatalogueReader<R: BufRead>(FormatReader<R>);
+
+enum FormatReader<R: BufRead> {
+    Ndjson(NdjsonReader<R>),
+    Binary(BinaryReader<R>),
+}
+
+impl<R: BufRead> CatalogueReader<R> {
+    /// Reads the header of the catalogue, leaving the input positioned at the first record after it.
+    pub fn new(mut input: R) -> Result<Self, Box<dyn Error>> {
+        if input.fill_buf()?.starts_with(binary::MAGIC) {
+            Ok(CatalogueReader(FormatReader::Binary(BinaryReader::new(
+                input,
+            )?)))
+        } else {
+            Ok(CatalogueReader(FormatReader::Ndjson(NdjsonReader::new(
+                input,
+            )?)))
+        }
+    }
+
+    /// The header of the catalogue.
+    pub fn header(&self) -> &CatalogueHeader {
+        match &self.0 {
+            FormatReader::Ndjson(reader) => reader.header(),
+            FormatReader::Binary(reader) => reader.header(),
+        }
+    }
+
+    /// The next record after the header, or `None` at the end of the catalogue.
+    pub fn read_record(&mut self) -> Result<Option<Record>, Box<dyn Error>> {
+        match &mut self.0 {
+            FormatReader::Ndjson(reader) => reader.read_record(),
+            FormatReader::Binary(reader) => reader.read_record(),
+        }
+    }
+
+    /// The format of the catalogue.
+    pub(crate) fn format(&self) -> OutputFormat {
+        match self.0 {
+            FormatReader::Ndjson(_) => OutputFormat::Ndjson,
+            FormatReader::Binary(_) => OutputFormat::Binary,
+        }
+    }
+}
+
+/// Writes a whole catalogue without reporting progress or saving checkpoints.
+#[cfg(test)]
+pub(crate) fn write_catalogue(
+    generation_parameters: &GenerationParameters,
+    writer: &mut (impl CatalogueWriter + ?Sized),
+) -> Result<GenerationStatistics, Box<dyn Error>> {
+    write_catalogue_with_progress(generation_parameters, writer, &mut |_| Ok(()), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::catalogue::{binary::BinaryWriter, ndjson::NdjsonWriter};
+
+    use super::*;
+
+    fn params(max_distance_in_pc: f64) -> GenerationParameters {
+        GenerationParameters {
+            observer_position_in_pc: Some((8200., 0., 0.)),
+            apparent_magnitude_limit: 6.,
+            max_distance_in_pc,
+            chunksize_in_pc: 15.,
+            include_companions: true,
+            ..Default::default()
+        }
+    }
+
+    fn ndjson(params: &GenerationParameters) -> Vec<u8> {
+        let mut catalogue = Vec::new();
+        write_catalogue(params, &mut NdjsonWriter::new(&mut catalogue))
+            .expect("Generation should succeed");
+        catalogue
+    }
+
+    #[test]
+    fn extended_catalogue_equals_one_generated_at_the_larger_distance() {
+        let small = ndjson(&params(40.));
+        let mut reader =
+            CatalogueReader::new(small.as_slice()).expect("The header should be valid");
+        let mut extended = Vec::new();
+        extend_catalogue(
+            &mut reader,
+            70.,
+            &mut NdjsonWriter::new(&mut extended),
+            &mut |_| Ok(()),
+        )
+        .expect("Extending should succeed");
+        assert!(extended.len() > small.len());
+        assert!(extended == ndjson(&params(70.)));
+    }
+
+    #[test]
+    fn catalogues_cannot_be_shrunk_or_extended_when_incomplete() {
+        let small = ndjson(&params(40.));
+        let mut reader =
+            CatalogueReader::new(small.as_slice()).expect("The header should be valid");
+        assert!(extend_catalogue(
+            &mut reader,
+            30.,
+            &mut NdjsonWriter::new(Vec::new()),
+            &mut |_| Ok(())
+        )
+        .is_err());
+
+        let cut = small[..small.len() - 1]
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .expect("There should be several lines");
+        let incomplete = &small[..=cut];
+        let mut reader = CatalogueReader::new(incomplete).expect("The header should be valid");
+        assert!(extend_catalogue(
+            &mut reader,
+            70.,
+            &mut NdjsonWriter::new(Vec::new()),
+            &mut |_| Ok(())
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn converting_from_binary_gives_the_same_catalogue() {
+        let params = params(40.);
+        let mut binary = Vec::new();
+        write_catalogue(
+            &params,
+            &mut BinaryWriter::new(&mut binary, Cursor::new(Vec::new())),
+        )
+        .expect("Generation should succeed");
+        let mut reader =
+            CatalogueReader::new(binary.as_slice()).expect("The header should be valid");
+        let mut converted = Vec::new();
+        convert_catalogue(&mut reader, &mut NdjsonWriter::new(&mut converted))
+            .expect("Converting should succeed");
+        assert!(converted == ndjson(&params));
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    error::Error,
+    io::{BufRead, Write},
+};
+
+use crate::{generation_statistics::GenerationStatistics, generator::GeneratedChunk};
+
+use super::{CatalogueHeader, CatalogueWriter, Record, WriterState};
+
+/// Writes one JSON object per line, flushing after every chunk.
+///
+/// If the run is interrupted, every complete line of the output is still a valid record.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#output>
+pub(crate) struct NdjsonWriter<W: Write> {
+    out: W,
+    /// Number of bytes written so far.
+    position: u64,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub(crate) fn new(out: W) -> Self {
+        NdjsonWriter { out, position: 0 }
+    }
+
+    fn write_record(&mut self, record: &Record) -> Result<(), Box<dyn Error>> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.out.write_all(&line)?;
+        self.position += line.len() as u64;
+        Ok(())
+    }
+}
+
+impl<W: Write> CatalogueWriter for NdjsonWriter<W> {
+    fn write_header(&mut self, header: &CatalogueHeader) -> Result<(), Box<dyn Error>> {
+        self.write_record(&Record::Header(header.clone()))?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, index: usize, chunk: GeneratedChunk) -> Result<(), Box<dyn Error>> {
+        for star in chunk.stars {
+            self.write_record(&Record::Star(Box::new(star)))?;
+        }
+        for cluster in chunk.unresolved_clusters {
+            self.write_record(&Record::UnresolvedCluster(Box::new(cluster)))?;
+        }
+        self.write_record(&Record::ChunkCompleted(index))?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self, statistics: &GenerationStatistics) -> Result<(), Box<dyn Error>> {
+        self.write_record(&Record::Statistics(statistics.clone()))?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn state(&self) -> WriterState {
+        WriterState {
+            offset: self.position,
+            ..Default::default()
+        }
+    }
+
+    fn resume(&mut self, state: &WriterState) -> Result<(), Box<dyn Error>> {
+        self.position = state.offset;
+        Ok(())
+    }
+}
+
+/// Reads a catalogue in the JSON format, record by record.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#output>
+pub(crate) struct NdjsonReader<R: BufRead> {
+    input: R,
+    header: CatalogueHeader,
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    /// Reads the header, and fails if the first line of the input is not one.
+    pub(crate) fn new(mut input: R) -> Result<Self, Box<dyn Error>> {
+        match read_line(&mut input)? {
+            Some(Record::Header(header)) => Ok(NdjsonReader { input, header }),
+            _ => Err("The input does not start with the header of an Ursa Lumi catalogue".into()),
+        }
+    }
+
+    pub(crate) fn header(&self) -> &CatalogueHeader {
+        &self.header
+    }
+
+    /// The next record after the header, or `None` at the end of the input.
+    ///
+    /// A last line without a line break was cut off while it was written, and is ignored.
+    pub(crate) fn read_record(&mut self) -> Result<Option<Record>, Box<dyn Error>> {
+        read_line(&mut self.input)
+    }
+}
+
+fn read_line(input: &mut impl BufRead) -> Result<Option<Record>, Box<dyn Error>> {
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    if !line.ends_with('\n') {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{catalogue::write_catalogue, generation_parameters::GenerationParameters};
+
+    use super::*;
+
+    fn generate() -> (Vec<u8>, GenerationStatistics) {
+        let params = GenerationParameters {
+            observer_position_in_pc: Some((8200., 0., 0.)),
+            apparent_magnitude_limit: 6.,
+            max_distance_in_pc: 50.,
+            chunksize_in_pc: 15.,
+            ..Default::default()
+        };
+        let mut writer = NdjsonWriter::new(Vec::new());
+        let statistics = write_catalogue(&params, &mut writer).expect("Generation should succeed");
+        (writer.out, statistics)
+    }
+
+    fn parse(line: &str) -> Record {
+        serde_json::from_str(line).expect("Every line should be a valid record")
+    }
+
+    #[test]
+    fn catalogue_starts_with_header_and_ends_with_statistics() {
+        let (output, statistics) = generate();
+        let output = String::from_utf8(output).expect("The output should be UTF-8");
+        let records: Vec<Record> = output.lines().map(parse).collect();
+
+        assert!(matches!(records.first(), Some(Record::Header(_))));
+        match records.last() {
+            Some(Record::Statistics(written)) => assert_eq!(written, &statistics),
+            _ => panic!("The last record should contain the statistics"),
+        }
+        let stars = records
+            .iter()
+            .filter(|record| matches!(record, Record::Star(_)))
+            .count();
+        assert!(stars > 0);
+        assert_eq!(stars as u64, statistics.stars_kept);
+        let chunks = records
+            .iter()
+            .filter(|record| matches!(record, Record::ChunkCompleted(_)))
+            .count();
+        assert_eq!(chunks, statistics.chunks_processed);
+    }
+
+    #[test]
+    fn truncated_catalogue_consists_of_complete_records() {
+        let (output, _) = generate();
+        let output = String::from_utf8(output).expect("The output should be UTF-8");
+        let cut = output.len() / 2;
+        let truncated = &output[..cut];
+        let complete = &truncated[..=truncated.rfind('\n').expect("There should be a line")];
+
+        let records: Vec<Record> = complete.lines().map(parse).collect();
+        assert!(matches!(records.first(), Some(Record::Header(_))));
+        assert!(!records
+            .iter()
+            .any(|record| matches!(record, Record::Statistics(_))));
+    }
+
+    #[test]
+    fn reader_stops_before_a_cut_off_line() {
+        let (output, statistics) = generate();
+        let mut reader = NdjsonReader::new(output.as_slice()).expect("The header should be valid");
+        let mut records = Vec::new();
+        while let Some(record) = reader.read_record().expect("The record should be valid") {
+            records.push(record);
+        }
+        assert!(matches!(records.last(), Some(Record::Statistics(s)) if s == &statistics));
+
+        let truncated = &output[..output.len() - 1];
+        let mut reader = NdjsonReader::new(truncated).expect("The header should be valid");
+        let mut count = 0;
+        while reader
+            .read_record()
+            .expect("The record should be valid")
+            .is_some()
+        {
+            count += 1;
+        }
+        assert_eq!(count, records.len() - 1);
+    }
+}
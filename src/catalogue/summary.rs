@@ -0,0 +1,145 @@
+use std::{collections::BTreeMap, error::Error, io::BufRead};
+
+use serde::Serialize;
+
+use crate::{generation_statistics::GenerationStatistics, generator::distance_in_pc, star::Star};
+
+use super::{
+    column::{population_name, variability_class_name},
+    CatalogueReader, Record,
+};
+
+/// An overview of the contents of a catalogue.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#inspecting-a-catalogue>
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct CatalogueSummary {
+    pub(crate) chunks_completed: usize,
+    /// Whether the catalogue ends with the statistics of its generation, which only catalogues of finished runs do.
+    pub(crate) complete: bool,
+    pub(crate) stars: u64,
+    pub(crate) companions: u64,
+    pub(crate) white_dwarfs: u64,
+    pub(crate) unresolved_clusters: u64,
+    /// Number of stars and companions of every population.
+    pub(crate) stars_per_population: BTreeMap<&'static str, u64>,
+    /// Number of stars and companions of every class of variable stars.
+    pub(crate) variable_stars_per_class: BTreeMap<&'static str, u64>,
+    /// Apparent magnitude of the brightest star, in the band of the magnitude limit.
+    pub(crate) brightest_magnitude: Option<f64>,
+    /// Apparent magnitude of the faintest star, in the band of the magnitude limit.
+    pub(crate) faintest_magnitude: Option<f64>,
+    pub(crate) nearest_star_in_pc: Option<f64>,
+    pub(crate) farthest_star_in_pc: Option<f64>,
+    pub(crate) generation_statistics: Option<GenerationStatistics>,
+}
+
+impl CatalogueSummary {
+    /// Reads the whole catalogue and counts its contents.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#inspecting-a-catalogue>
+    pub(crate) fn of<R: BufRead>(reader: &mut CatalogueReader<R>) -> Result<Self, Box<dyn Error>> {
+        let header = reader.header().clone();
+        let band = header.generation_parameters.magnitude_limit_band;
+        let mut summary = CatalogueSummary::default();
+        while let Some(record) = reader.read_record()? {
+            match record {
+                Record::Header(_) => return Err("The catalogue contains a second header".into()),
+                Record::Star(star) => {
+                    summary.stars += 1;
+                    summary.count(&star);
+                    let magnitude = star.apparent_magnitudes.get(band);
+                    summary.brightest_magnitude = Some(
+                        summary
+                            .brightest_magnitude
+                            .map_or(magnitude, |m| m.min(magnitude)),
+                    );
+                    summary.faintest_magnitude = Some(
+                        summary
+                            .faintest_magnitude
+                            .map_or(magnitude, |m| m.max(magnitude)),
+                    );
+                    let distance =
+                        distance_in_pc(star.position_in_pc, header.observer_position_in_pc);
+                    summary.nearest_star_in_pc = Some(
+                        summary
+                            .nearest_star_in_pc
+                            .map_or(distance, |d| d.min(distance)),
+                    );
+                    summary.farthest_star_in_pc = Some(
+                        summary
+                            .farthest_star_in_pc
+                            .map_or(distance, |d| d.max(distance)),
+                    );
+                    for companion in star.companions.iter() {
+                        summary.companions += 1;
+                        summary.count(companion);
+                    }
+                }
+                Record::UnresolvedCluster(_) => summary.unresolved_clusters += 1,
+                Record::ChunkCompleted(_) => summary.chunks_completed += 1,
+                Record::Statistics(statistics) => {
+                    summary.complete = true;
+                    summary.generation_statistics = Some(statistics);
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    fn count(&mut self, star: &Star) {
+        if star.cooling_age_in_gyr.is_some() {
+            self.white_dwarfs += 1;
+        }
+        *self
+            .stars_per_population
+            .entry(population_name(star.population))
+            .or_default() += 1;
+        if let Some(variability) = &star.variability {
+            *self
+                .variable_stars_per_class
+                .entry(variability_class_name(variability.class))
+                .or_default() += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        catalogue::{ndjson::NdjsonWriter, write_catalogue},
+        generation_parameters::GenerationParameters,
+    };
+
+    use super::*;
+
+    #[test]
+    fn summary_agrees_with_the_generation_statistics() {
+        let params = GenerationParameters {
+            observer_position_in_pc: Some((8200., 0., 0.)),
+            apparent_magnitude_limit: 6.,
+            max_distance_in_pc: 50.,
+            chunksize_in_pc: 15.,
+            include_companions: true,
+            ..Default::default()
+        };
+        let mut catalogue = Vec::new();
+        let statistics = write_catalogue(&params, &mut NdjsonWriter::new(&mut catalogue))
+            .expect("Generation should succeed");
+        let mut reader =
+            CatalogueReader::new(catalogue.as_slice()).expect("The header should be valid");
+        let summary = CatalogueSummary::of(&mut reader).expect("The catalogue should be valid");
+
+        assert!(summary.complete);
+        assert_eq!(summary.chunks_completed, statistics.chunks_processed);
+        assert_eq!(summary.stars, statistics.stars_kept);
+        assert_eq!(summary.companions, statistics.companions_kept);
+        let per_population: u64 = summary.stars_per_population.values().sum();
+        assert_eq!(per_population, summary.stars + summary.companions);
+        let faintest = summary.faintest_magnitude.expect("There should be stars");
+        assert!(faintest <= params.apparent_magnitude_limit);
+        let farthest = summary.farthest_star_in_pc.expect("There should be stars");
+        assert!(farthest <= params.max_distance_in_pc * 2.);
+        assert_eq!(summary.generation_statistics, Some(statistics));
+    }
+}
@@ -0,0 +1,432 @@
+use clap::{Args, Parser, Subcommand};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    catalogue::{
+        binary::{index_path_for, BinaryWriter},
+        checkpoint::{Checkpoint, Checkpointer},
+        column::Column,
+        convert_catalogue,
+        csv::CsvWriter,
+        extend_catalogue,
+        fits::FitsWriter,
+        index::{query_catalogue, Query},
+        ndjson::NdjsonWriter,
+        resume_catalogue,
+        summary::CatalogueSummary,
+        write_catalogue_with_progress, CatalogueReader, CatalogueWriter, OutputFormat, Record,
+    },
+    dry_run::DryRunEstimate,
+    galactic_chunk::GalacticChunk,
+    generation_parameters::GenerationParameters,
+    generation_statistics::GenerationStatistics,
+    generator::Generator,
+    photometry::placeholder_warning,
+    progress::{Progress, ProgressCallback, ProgressFormat, ProgressReporter},
+};
+
+/// The error of a run that failed after its error was already reported, such that it should not be printed again.
+#[derive(Debug)]
+pub struct AlreadyReported;
+
+impl std::fmt::Display for AlreadyReported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The error was already reported")
+    }
+}
+
+impl std::error::Error for AlreadyReported {}
+
+/// Parses the command line arguments and runs the requested subcommand.
+pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+    match Arguments::parse().command {
+        Command::Generate(args) => generate(args),
+        Command::Extend(args) => extend(args),
+        Command::Inspect(args) => inspect(args),
+        Command::Stats(args) => stats(args),
+        Command::Convert(args) => convert(args),
+        Command::Validate(args) => validate(args),
+        Command::Query(args) => run_query(args),
+    }
+}
+
+fn generate(args: GenerateArguments) -> Result<(), Box<dyn std::error::Error>> {
+    if args.dry_run {
+        let params = read_params(&args.params)?;
+        let columns = select_columns(args.format, args.columns)?;
+        return print_json(&DryRunEstimate::new(&params, args.format, &columns)?);
+    }
+    let Some(out) = args.out else {
+        return Err("Generating a catalogue needs --out".into());
+    };
+    let params = read_params(&args.params);
+    let warning = params.as_ref().ok().and_then(placeholder_warning);
+    report_progress(args.progress, warning, |progress| {
+        let params = params?;
+        let columns = select_columns(args.format, args.columns)?;
+        let column_names: Vec<String> = columns.iter().map(Column::name).collect();
+        let checkpoint_path = Checkpoint::path_for(&out);
+        let mut checkpointer = Checkpointer::new(
+            checkpoint_path.clone(),
+            Duration::try_from_secs_f64(args.checkpoint_interval)?,
+            &params,
+            args.format,
+            column_names.clone(),
+        );
+        let statistics = if args.resume {
+            let checkpoint = Checkpoint::load(&checkpoint_path)?;
+            checkpoint.check_matches(&params, args.format, &column_names)?;
+            let mut writer = reopen_writer(&out, args.format, columns, checkpoint.writer.offset)?;
+            resume_catalogue(&checkpoint, writer.as_mut(), progress, &mut checkpointer)?
+        } else {
+            let mut writer = create_writer(&out, args.format, columns)?;
+            let mut progress = |state: &Progress| {
+                progress(state)?;
+                match args.stop_after_chunks {
+                    Some(chunks) if state.chunks_completed >= chunks => {
+                        Err(format!("Stopped after {} chunks as requested", chunks).into())
+                    }
+                    _ => Ok(()),
+                }
+            };
+            write_catalogue_with_progress(
+                &params,
+                writer.as_mut(),
+                &mut progress,
+                Some(&mut checkpointer),
+            )?
+        };
+        remove_index_spill(&out)?;
+        Ok(statistics)
+    })
+}
+
+fn extend(args: ExtendArguments) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = open_catalogue(&args.catalogue);
+    let warning = reader
+        .as_ref()
+        .ok()
+        .and_then(|reader| placeholder_warning(reader.header().generation_parameters()));
+    report_progress(args.progress, warning, |progress| {
+        if args.out == args.catalogue {
+            return Err("The extended catalogue has to be written to a new file".into());
+        }
+        let mut reader = reader?;
+        let mut writer = create_writer(&args.out, reader.format(), Vec::new())?;
+        let statistics =
+            extend_catalogue(&mut reader, args.max_distance, writer.as_mut(), progress)?;
+        remove_index_spill(&args.out)?;
+        Ok(statistics)
+    })
+}
+
+fn inspect(args: CatalogueArguments) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = open_catalogue(&args.catalogue)?;
+    print_json(reader.header())
+}
+
+fn stats(args: CatalogueArguments) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = open_catalogue(&args.catalogue)?;
+    print_json(&CatalogueSummary::of(&mut reader)?)
+}
+
+fn convert(args: ConvertArguments) -> Result<(), Box<dyn std::error::Error>> {
+    if args.out == args.catalogue {
+        return Err("The converted catalogue has to be written to a new file".into());
+    }
+    let mut reader = open_catalogue(&args.catalogue)?;
+    let mut writer = create_writer(&args.out, args.format, args.columns)?;
+    convert_catalogue(&mut reader, writer.as_mut())?;
+    remove_index_spill(&args.out)?;
+    Ok(())
+}
+
+fn validate(args: ValidateArguments) -> Result<(), Box<dyn std::error::Error>> {
+    let params = read_params(&args.params)?;
+    Generator::new(&params)?;
+    println!(
+        "The generation parameters are valid, the catalogue consists of {} chunks.",
+        GalacticChunk::number_of_chunks(&params)
+    );
+    if let Some(warning) = placeholder_warning(&params) {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(())
+}
+
+fn run_query(args: QueryArguments) -> Result<(), Box<dyn std::error::Error>> {
+    let query = match (args.box_in_pc.as_slice(), args.cone.as_slice()) {
+        ([x0, y0, z0, x1, y1, z1], []) => Query::Box {
+            min_in_pc: (*x0, *y0, *z0),
+            max_in_pc: (*x1, *y1, *z1),
+        },
+        ([], [l, b, radius]) => Query::Cone {
+            longitude_in_deg: *l,
+            latitude_in_deg: *b,
+            radius_in_deg: *radius,
+        },
+        _ => return Err("A query needs either six values for --box or three for --cone".into()),
+    };
+    let input = BufReader::new(File::open(args.catalogue)?);
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    query_catalogue(input, &query, &mut |star| {
+        serde_json::to_writer(&mut out, &Record::Star(Box::new(star)))?;
+        writeln!(out)?;
+        Ok(())
+    })?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Runs a generation while reporting its progress on the standard error stream.
+///
+/// If the progress is reported as JSON and the run fails, the error is only reported as part of the stream, and `AlreadyReported` is returned in its place.
+fn report_progress(
+    args: ProgressArguments,
+    warning: Option<&str>,
+    run: impl FnOnce(&mut ProgressCallback) -> Result<GenerationStatistics, Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.quiet {
+        run(&mut |_| Ok(()))?;
+        return Ok(());
+    }
+    let mut reporter = ProgressReporter::new(std::io::stderr(), args.progress);
+    if let Some(warning) = warning {
+        reporter.warn(warning)?;
+    }
+    let result = run(&mut |progress| reporter.report(progress));
+    match result {
+        Ok(statistics) => reporter.finish(&statistics),
+        Err(error) => {
+            reporter.fail(error.as_ref())?;
+            if args.progress == ProgressFormat::Json {
+                return Err(AlreadyReported.into());
+            }
+            Err(error)
+        }
+    }
+}
+
+fn read_params(path: &Path) -> Result<GenerationParameters, Box<dyn std::error::Error>> {
+    let params: GenerationParameters = serde_json::from_reader(File::open(path)?)?;
+    params.validate()?;
+    Ok(params)
+}
+
+fn open_catalogue(
+    path: &Path,
+) -> Result<CatalogueReader<BufReader<File>>, Box<dyn std::error::Error>> {
+    CatalogueReader::new(BufReader::new(File::open(path)?))
+}
+
+fn select_columns(
+    format: OutputFormat,
+    columns: Vec<Column>,
+) -> Result<Vec<Column>, Box<dyn std::error::Error>> {
+    Ok(match format {
+        OutputFormat::Csv | OutputFormat::Tsv | OutputFormat::Fits if columns.is_empty() => {
+            Column::all()
+        }
+        OutputFormat::Csv | OutputFormat::Tsv | OutputFormat::Fits => columns,
+        OutputFormat::Ndjson | OutputFormat::Binary if columns.is_empty() => Vec::new(),
+        OutputFormat::Ndjson | OutputFormat::Binary => {
+            return Err("Columns can only be selected for the csv, tsv and fits formats".into())
+        }
+    })
+}
+
+fn create_writer(
+    path: &Path,
+    format: OutputFormat,
+    columns: Vec<Column>,
+) -> Result<Box<dyn CatalogueWriter>, Box<dyn std::error::Error>> {
+    let out = BufWriter::new(File::create(path)?);
+    writer_for(path, out, format, columns, false)
+}
+
+/// A writer that continues the output of an interrupted run, after removing everything behind the given offset.
+fn reopen_writer(
+    path: &Path,
+    format: OutputFormat,
+    columns: Vec<Column>,
+    offset: u64,
+) -> Result<Box<dyn CatalogueWriter>, Box<dyn std::error::Error>> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    if file.metadata()?.len() < offset {
+        return Err("The output is shorter than recorded in the checkpoint".into());
+    }
+    file.set_len(offset)?;
+    file.seek(SeekFrom::End(0))?;
+    writer_for(path, BufWriter::new(file), format, columns, true)
+}
+
+fn writer_for(
+    path: &Path,
+    out: BufWriter<File>,
+    format: OutputFormat,
+    columns: Vec<Column>,
+    resume: bool,
+) -> Result<Box<dyn CatalogueWriter>, Box<dyn std::error::Error>> {
+    let columns = select_columns(format, columns)?;
+    Ok(match format {
+        OutputFormat::Ndjson => Box::new(NdjsonWriter::new(out)),
+        OutputFormat::Binary => {
+            // The index entries of an interrupted run are kept, the writer continues after those recorded in the checkpoint.
+            let index = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(!resume)
+                .open(index_path_for(path))?;
+            Box::new(BinaryWriter::new(out, index))
+        }
+        OutputFormat::Csv => Box::new(CsvWriter::new(out, ',', columns)),
+        OutputFormat::Tsv => Box::new(CsvWriter::new(out, '\t', columns)),
+        OutputFormat::Fits => Box::new(FitsWriter::new(out, columns)),
+    })
+}
+
+/// Removes the file in which the index entries of a binary catalogue are collected, once they are part of the complete catalogue.
+fn remove_index_spill(out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let path = index_path_for(out);
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn print_json(value: &impl serde::Serialize) -> Result<(), Box<dyn std::error::Error>> {
+    let mut out = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut out, value)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Arguments {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generates a new catalogue.
+    Generate(GenerateArguments),
+    /// Grows a complete catalogue to a larger maximal distance, writing the result to a new file in the same format.
+    Extend(ExtendArguments),
+    /// Prints the header of a catalogue, including its generation parameters, as JSON.
+    Inspect(CatalogueArguments),
+    /// Prints a summary of the contents of a catalogue as JSON.
+    Stats(CatalogueArguments),
+    /// Writes a complete catalogue again in another format.
+    Convert(ConvertArguments),
+    /// Checks a file of generation parameters without generating anything.
+    Validate(ValidateArguments),
+    /// Prints the stars of a binary catalogue inside a box or a cone as newline-delimited JSON.
+    Query(QueryArguments),
+}
+
+#[derive(Args)]
+struct GenerateArguments {
+    #[arg(short, long, value_name = "FILE")]
+    params: PathBuf,
+    #[arg(short, long, value_name = "FILE", required_unless_present = "dry_run")]
+    out: Option<PathBuf>,
+    /// Prints estimates of the number of chunks and stars, memory, disk space and runtime as JSON instead of generating anything.
+    #[arg(long)]
+    dry_run: bool,
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Ndjson)]
+    format: OutputFormat,
+    /// Comma-separated list of the columns to write in the csv, tsv and fits formats, all by default.
+    ///
+    /// The band magnitude columns are experimental unless a published table of bolometric corrections is provided in the parameters.
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<Column>,
+    /// Continues an interrupted run with the same arguments from the last checkpoint next to the output.
+    #[arg(long, conflicts_with = "dry_run")]
+    resume: bool,
+    /// The minimal time between two checkpoints, in units of seconds.
+    #[arg(long, value_name = "SECONDS", default_value_t = 60.)]
+    checkpoint_interval: f64,
+    /// Aborts the run after the given number of chunks as if it had been killed, leaving the output and the checkpoint behind. Only meant for testing `--resume`.
+    #[arg(long, value_name = "CHUNKS", hide = true, conflicts_with = "resume")]
+    stop_after_chunks: Option<usize>,
+    #[command(flatten)]
+    progress: ProgressArguments,
+}
+
+#[derive(Args)]
+struct ProgressArguments {
+    /// Do not report the progress.
+    #[arg(short, long, conflicts_with = "progress")]
+    quiet: bool,
+    /// How the progress is reported on the standard error stream.
+    #[arg(long, value_enum, default_value_t = ProgressFormat::Text)]
+    progress: ProgressFormat,
+}
+
+#[derive(Args)]
+struct ExtendArguments {
+    /// A complete catalogue in the ndjson or binary format.
+    #[arg(short, long, value_name = "FILE")]
+    catalogue: PathBuf,
+    /// The new maximal distance from the observer, in units of parsec.
+    #[arg(short, long, value_name = "PC")]
+    max_distance: f64,
+    #[arg(short, long, value_name = "FILE")]
+    out: PathBuf,
+    #[command(flatten)]
+    progress: ProgressArguments,
+}
+
+#[derive(Args)]
+struct CatalogueArguments {
+    /// A catalogue in the ndjson or binary format.
+    #[arg(short, long, value_name = "FILE")]
+    catalogue: PathBuf,
+}
+
+#[derive(Args)]
+struct ConvertArguments {
+    /// A complete catalogue in the ndjson or binary format.
+    #[arg(short, long, value_name = "FILE")]
+    catalogue: PathBuf,
+    #[arg(short, long, value_name = "FILE")]
+    out: PathBuf,
+    #[arg(short, long, value_enum)]
+    format: OutputFormat,
+    /// Comma-separated list of the columns to write in the csv, tsv and fits formats, all by default.
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<Column>,
+}
+
+#[derive(Args)]
+struct ValidateArguments {
+    #[arg(short, long, value_name = "FILE")]
+    params: PathBuf,
+}
+
+#[derive(Args)]
+struct QueryArguments {
+    /// A complete catalogue written with `--format binary`.
+    #[arg(short, long, value_name = "FILE")]
+    catalogue: PathBuf,
+    /// Galactocentric box as `x_min,y_min,z_min,x_max,y_max,z_max`, in units of parsec.
+    #[arg(
+        long = "box",
+        value_delimiter = ',',
+        allow_hyphen_values = true,
+        conflicts_with = "cone",
+        required_unless_present = "cone"
+    )]
+    box_in_pc: Vec<f64>,
+    /// Cone around a direction in galactic coordinates as `l,b,radius`, in units of degrees.
+    #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+    cone: Vec<f64>,
+}
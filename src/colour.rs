@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+
+/// The CIE 1931 colour matching functions shipped with the crate.
+const COLOUR_MATCHING_FUNCTIONS: &str =
+    include_str!("../data/cie1931_colour_matching_functions.csv");
+
+/// Second radiation constant `h c / k_B`, in units of nanometre Kelvin.
+const SECOND_RADIATION_CONSTANT_IN_NM_K: f64 = 1.438_776_877e7;
+
+/// Conversion from CIE XYZ to linear sRGB with a D65 white point.
+const XYZ_TO_LINEAR_SRGB: [[f64; 3]; 3] = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.9692660, 1.8760108, 0.0415560],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+
+/// The display colour and brightness of a star.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#colour>
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Colour {
+    /// Linear sRGB components, scaled such that the largest one is 1.
+    pub linear_srgb: (f64, f64, f64),
+    /// Gamma encoded sRGB components between 0 and 1.
+    pub srgb: (f64, f64, f64),
+    /// Flux relative to that of a star exactly at the apparent magnitude limit.
+    pub relative_brightness: f64,
+}
+
+/// The CIE 1931 2° standard observer, tabulated on a regular wavelength grid.
+#[derive(Debug, Clone)]
+pub(crate) struct ColourMatchingFunctions {
+    wavelengths_in_nm: Vec<f64>,
+    xyz_bars: Vec<(f64, f64, f64)>,
+}
+
+impl ColourMatchingFunctions {
+    /// Loads the colour matching functions shipped with the crate.
+    pub(crate) fn new() -> Self {
+        let mut wavelengths_in_nm = Vec::new();
+        let mut xyz_bars = Vec::new();
+        for line in COLOUR_MATCHING_FUNCTIONS.lines() {
+            if line.starts_with('#') || line.starts_with("wavelength") {
+                continue;
+            }
+            let values: Vec<f64> = line.split(',').filter_map(|v| v.parse().ok()).collect();
+            if let [wavelength, x, y, z] = values[..] {
+                wavelengths_in_nm.push(wavelength);
+                xyz_bars.push((x, y, z));
+            }
+        }
+        Self {
+            wavelengths_in_nm,
+            xyz_bars,
+        }
+    }
+
+    /// The CIE XYZ tristimulus values of a blackbody, scaled such that `Y = 1`.
+    pub(crate) fn blackbody_xyz(&self, temperature_in_k: f64) -> (f64, f64, f64) {
+        let (mut x, mut y, mut z) = (0., 0., 0.);
+        for (&wavelength, &(x_bar, y_bar, z_bar)) in
+            self.wavelengths_in_nm.iter().zip(self.xyz_bars.iter())
+        {
+            let radiance = planck(wavelength, temperature_in_k);
+            x += radiance * x_bar;
+            y += radiance * y_bar;
+            z += radiance * z_bar;
+        }
+        if y <= 0. {
+            return (0., 0., 0.);
+        }
+        (x / y, 1., z / y)
+    }
+
+    /// The colour of a blackbody with the given temperature, for a star of the given apparent magnitude.
+    pub(crate) fn colour(
+        &self,
+        temperature_in_k: f64,
+        apparent_magnitude: f64,
+        apparent_magnitude_limit: f64,
+    ) -> Colour {
+        colour_from_xyz(
+            self.blackbody_xyz(temperature_in_k),
+            apparent_magnitude,
+            apparent_magnitude_limit,
+        )
+    }
+}
+
+/// The colour of light with the given CIE XYZ tristimulus values, for an object of the given apparent magnitude.
+pub(crate) fn colour_from_xyz(
+    xyz: (f64, f64, f64),
+    apparent_magnitude: f64,
+    apparent_magnitude_limit: f64,
+) -> Colour {
+    let rgb =
+        XYZ_TO_LINEAR_SRGB.map(|row| (row[0] * xyz.0 + row[1] * xyz.1 + row[2] * xyz.2).max(0.));
+    let max = rgb.iter().cloned().fold(0., f64::max);
+    let linear = if max > 0. {
+        rgb.map(|component| component / max)
+    } else {
+        rgb
+    };
+    let encoded = linear.map(gamma_encode);
+    Colour {
+        linear_srgb: (linear[0], linear[1], linear[2]),
+        srgb: (encoded[0], encoded[1], encoded[2]),
+        relative_brightness: 10_f64.powf(-0.4 * (apparent_magnitude - apparent_magnitude_limit)),
+    }
+}
+
+/// Spectral radiance of a blackbody, up to a constant factor.
+fn planck(wavelength_in_nm: f64, temperature_in_k: f64) -> f64 {
+    let exponent = SECOND_RADIATION_CONSTANT_IN_NM_K / (wavelength_in_nm * temperature_in_k);
+    1. / (wavelength_in_nm.powi(5) * exponent.exp_m1())
+}
+
+/// The sRGB transfer function.
+fn gamma_encode(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_diff, stellar_evolution::SOLAR_EFFECTIVE_TEMPERATURE_IN_K};
+
+    use super::*;
+
+    const CHROMATICITY_ACCURACY: f64 = 3e-3;
+
+    /// The chromaticity coordinates (x, y) of CIE XYZ tristimulus values.
+    fn chromaticity(xyz: (f64, f64, f64)) -> (f64, f64) {
+        let sum = xyz.0 + xyz.1 + xyz.2;
+        (xyz.0 / sum, xyz.1 / sum)
+    }
+
+    #[test]
+    fn planckian_locus_matches_reference_points() {
+        let cmf = ColourMatchingFunctions::new();
+        let reference = [
+            (2856., 0.4476, 0.4074),
+            (5000., 0.3451, 0.3516),
+            (6500., 0.3135, 0.3236),
+            (10000., 0.2807, 0.2884),
+        ];
+        for (temperature, x, y) in reference {
+            let (cx, cy) = chromaticity(cmf.blackbody_xyz(temperature));
+            assert_diff!(
+                x,
+                cx,
+                CHROMATICITY_ACCURACY,
+                "x at {} K is {}",
+                temperature,
+                cx
+            );
+            assert_diff!(
+                y,
+                cy,
+                CHROMATICITY_ACCURACY,
+                "y at {} K is {}",
+                temperature,
+                cy
+            );
+        }
+    }
+
+    #[test]
+    fn sun_is_a_slightly_warm_white() {
+        let cmf = ColourMatchingFunctions::new();
+        let sun = cmf.colour(SOLAR_EFFECTIVE_TEMPERATURE_IN_K, 0., 0.);
+        let (r, g, b) = sun.srgb;
+        assert_diff!(1., r, 1e-10);
+        assert!(g > 0.9 && g < r);
+        assert!(b > 0.8 && b < g);
+    }
+
+    #[test]
+    fn cool_stars_are_red_and_hot_stars_are_blue() {
+        let cmf = ColourMatchingFunctions::new();
+        let (r, _, b) = cmf.colour(3000., 0., 0.).linear_srgb;
+        assert!(r > b);
+        let (r, _, b) = cmf.colour(20000., 0., 0.).linear_srgb;
+        assert!(b > r);
+    }
+
+    #[test]
+    fn brightness_is_relative_to_magnitude_limit() {
+        let cmf = ColourMatchingFunctions::new();
+        assert_diff!(1., cmf.colour(6000., 6., 6.).relative_brightness, 1e-10);
+        assert_diff!(100., cmf.colour(6000., 1., 6.).relative_brightness, 1e-8);
+    }
+}
@@ -0,0 +1,395 @@
+use serde::{Deserialize, Serialize};
+
+use crate::galactocentric_frame::GalactocentricFrame;
+
+/// Rotation from ICRS equatorial to galactic unit vectors, as defined for Hipparcos and Gaia.
+const ICRS_TO_GALACTIC: [[f64; 3]; 3] = [
+    [
+        -0.054_875_560_416_215_4,
+        -0.873_437_090_234_885,
+        -0.483_835_015_548_713_2,
+    ],
+    [
+        0.494_109_427_875_583_7,
+        -0.444_829_629_960_011_2,
+        0.746_982_244_497_219,
+    ],
+    [
+        -0.867_666_149_019_004_7,
+        -0.198_076_373_431_201_5,
+        0.455_983_776_175_066_9,
+    ],
+];
+/// Obliquity of the ecliptic at J2000 (IAU 2006), in units of arcseconds.
+const OBLIQUITY_IN_ARCSEC: f64 = 84_381.406;
+
+/// A celestial frame in which sky coordinates can be included in the output.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#sky-coordinates>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SkyFrame {
+    /// Heliocentric galactic longitude and latitude, together with the distance.
+    Galactic,
+    /// ICRS right ascension and declination.
+    Equatorial,
+    /// Ecliptic longitude and latitude with respect to the mean equinox of J2000.
+    Ecliptic,
+}
+
+/// Heliocentric galactic coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GalacticCoordinates {
+    /// Galactic longitude, between 0 and 360 degrees.
+    pub longitude_in_deg: f64,
+    /// Galactic latitude, between -90 and 90 degrees.
+    pub latitude_in_deg: f64,
+    /// Distance from the observer, in units of parsec.
+    pub distance_in_pc: f64,
+}
+
+/// ICRS equatorial coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EquatorialCoordinates {
+    /// Right ascension, between 0 and 360 degrees.
+    pub right_ascension_in_deg: f64,
+    /// Declination, between -90 and 90 degrees.
+    pub declination_in_deg: f64,
+}
+
+/// Ecliptic coordinates with respect to the mean equinox of J2000.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EclipticCoordinates {
+    /// Ecliptic longitude, between 0 and 360 degrees.
+    pub longitude_in_deg: f64,
+    /// Ecliptic latitude, between -90 and 90 degrees.
+    pub latitude_in_deg: f64,
+}
+
+/// The position of a star on the sky of the observer, in the frames requested in the generation parameters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SkyCoordinates {
+    /// The galactic coordinates, if requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub galactic: Option<GalacticCoordinates>,
+    /// The equatorial coordinates, if requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equatorial: Option<EquatorialCoordinates>,
+    /// The ecliptic coordinates, if requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ecliptic: Option<EclipticCoordinates>,
+}
+
+impl SkyCoordinates {
+    /// The sky coordinates of a galactocentric position as seen by the observer, in the given frames.
+    pub(crate) fn new(
+        position_in_pc: (f64, f64, f64),
+        observer_position_in_pc: (f64, f64, f64),
+        galactocentric_frame: &GalactocentricFrame,
+        frames: &[SkyFrame],
+    ) -> Self {
+        if frames.is_empty() {
+            return Self::default();
+        }
+        let galactic = galactic_from_position(
+            position_in_pc,
+            observer_position_in_pc,
+            galactocentric_frame,
+        );
+        let equatorial = equatorial_from_galactic(&galactic);
+        Self {
+            galactic: frames.contains(&SkyFrame::Galactic).then_some(galactic),
+            equatorial: frames.contains(&SkyFrame::Equatorial).then_some(equatorial),
+            ecliptic: frames
+                .contains(&SkyFrame::Ecliptic)
+                .then(|| ecliptic_from_equatorial(&equatorial)),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.galactic.is_none() && self.equatorial.is_none() && self.ecliptic.is_none()
+    }
+}
+
+/// The galactic coordinates of a galactocentric position, as seen by the observer.
+///
+/// The axes of the galactic frame are those of the sun, see [`GalactocentricFrame::galactic_axes`], while its origin is the observer.
+pub(crate) fn galactic_from_position(
+    position_in_pc: (f64, f64, f64),
+    observer_position_in_pc: (f64, f64, f64),
+    galactocentric_frame: &GalactocentricFrame,
+) -> GalacticCoordinates {
+    let position = galactocentric_frame.model_frame_vector(position_in_pc);
+    let observer = galactocentric_frame.model_frame_vector(observer_position_in_pc);
+    let relative = [
+        position.0 - observer.0,
+        position.1 - observer.1,
+        position.2 - observer.2,
+    ];
+    let distance_in_pc = relative.iter().map(|r| r * r).sum::<f64>().sqrt();
+    let (longitude_in_deg, latitude_in_deg) =
+        to_angles(multiply(&galactocentric_frame.galactic_axes(), relative));
+    GalacticCoordinates {
+        longitude_in_deg,
+        latitude_in_deg,
+        distance_in_pc,
+    }
+}
+
+pub(crate) fn equatorial_from_galactic(galactic: &GalacticCoordinates) -> EquatorialCoordinates {
+    let galactic = to_unit_vector(galactic.longitude_in_deg, galactic.latitude_in_deg);
+    let (right_ascension_in_deg, declination_in_deg) =
+        to_angles(multiply_transposed(&ICRS_TO_GALACTIC, galactic));
+    EquatorialCoordinates {
+        right_ascension_in_deg,
+        declination_in_deg,
+    }
+}
+
+pub(crate) fn ecliptic_from_equatorial(equatorial: &EquatorialCoordinates) -> EclipticCoordinates {
+    let [x, y, z] = to_unit_vector(
+        equatorial.right_ascension_in_deg,
+        equatorial.declination_in_deg,
+    );
+    let (sin, cos) = obliquity().sin_cos();
+    let (longitude_in_deg, latitude_in_deg) = to_angles([x, y * cos + z * sin, -y * sin + z * cos]);
+    EclipticCoordinates {
+        longitude_in_deg,
+        latitude_in_deg,
+    }
+}
+
+/// The components of a vector given in the model frame along the ICRS equatorial axes.
+pub(crate) fn equatorial_vector(
+    model_vector: [f64; 3],
+    galactocentric_frame: &GalactocentricFrame,
+) -> [f64; 3] {
+    let galactic = multiply(&galactocentric_frame.galactic_axes(), model_vector);
+    multiply_transposed(&ICRS_TO_GALACTIC, galactic)
+}
+
+fn obliquity() -> f64 {
+    (OBLIQUITY_IN_ARCSEC / 3600.).to_radians()
+}
+
+pub(crate) fn to_unit_vector(longitude_in_deg: f64, latitude_in_deg: f64) -> [f64; 3] {
+    let (sin_lon, cos_lon) = longitude_in_deg.to_radians().sin_cos();
+    let (sin_lat, cos_lat) = latitude_in_deg.to_radians().sin_cos();
+    [cos_lat * cos_lon, cos_lat * sin_lon, sin_lat]
+}
+
+/// Longitude in [0°, 360°) and latitude in [-90°, 90°] of a vector, which need not be normalised.
+fn to_angles(vector: [f64; 3]) -> (f64, f64) {
+    let [x, y, z] = vector;
+    let longitude = y.atan2(x).to_degrees().rem_euclid(360.);
+    let latitude = z.atan2((x * x + y * y).sqrt()).to_degrees();
+    (longitude, latitude)
+}
+
+fn multiply(matrix: &[[f64; 3]; 3], vector: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2])
+}
+
+fn multiply_transposed(matrix: &[[f64; 3]; 3], vector: [f64; 3]) -> [f64; 3] {
+    [0, 1, 2]
+        .map(|i| matrix[0][i] * vector[0] + matrix[1][i] * vector[1] + matrix[2][i] * vector[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assert_diff,
+        galactocentric_frame::{Handedness, XAxisDirection},
+    };
+
+    use super::*;
+
+    /// Accuracy of the reference coordinates, in units of degrees.
+    const REFERENCE_ACCURACY: f64 = 1e-3;
+
+    /// Name, right ascension, declination, galactic longitude and galactic latitude.
+    const REFERENCE_OBJECTS: [(&str, f64, f64, f64, f64); 4] = [
+        ("Galactic center", 266.404_99, -28.936_17, 0., 0.),
+        ("North galactic pole", 192.859_48, 27.128_25, 0., 90.),
+        ("Vega", 279.234_73, 38.783_69, 67.448_2, 19.237_3),
+        ("Sirius", 101.287_16, -16.716_12, 227.230_3, -8.890_3),
+    ];
+
+    /// The galactocentric position of an object with the given galactic coordinates.
+    fn position_from_galactic(
+        galactic: &GalacticCoordinates,
+        observer_position_in_pc: (f64, f64, f64),
+        galactocentric_frame: &GalactocentricFrame,
+    ) -> (f64, f64, f64) {
+        let direction = to_unit_vector(galactic.longitude_in_deg, galactic.latitude_in_deg)
+            .map(|component| component * galactic.distance_in_pc);
+        let [x, y, z] = multiply_transposed(&galactocentric_frame.galactic_axes(), direction);
+        let observer = galactocentric_frame.model_frame_vector(observer_position_in_pc);
+        galactocentric_frame.vector_from_model_frame((
+            observer.0 + x,
+            observer.1 + y,
+            observer.2 + z,
+        ))
+    }
+
+    /// The galactic coordinates of an equatorial direction, at the given distance.
+    fn galactic_from_equatorial(
+        equatorial: &EquatorialCoordinates,
+        distance_in_pc: f64,
+    ) -> GalacticCoordinates {
+        let equatorial = to_unit_vector(
+            equatorial.right_ascension_in_deg,
+            equatorial.declination_in_deg,
+        );
+        let (longitude_in_deg, latitude_in_deg) =
+            to_angles(multiply(&ICRS_TO_GALACTIC, equatorial));
+        GalacticCoordinates {
+            longitude_in_deg,
+            latitude_in_deg,
+            distance_in_pc,
+        }
+    }
+
+    fn equatorial_from_ecliptic(ecliptic: &EclipticCoordinates) -> EquatorialCoordinates {
+        let [x, y, z] = to_unit_vector(ecliptic.longitude_in_deg, ecliptic.latitude_in_deg);
+        let (sin, cos) = obliquity().sin_cos();
+        let (right_ascension_in_deg, declination_in_deg) =
+            to_angles([x, y * cos - z * sin, y * sin + z * cos]);
+        EquatorialCoordinates {
+            right_ascension_in_deg,
+            declination_in_deg,
+        }
+    }
+
+    fn all_galactocentric_frames() -> Vec<GalactocentricFrame> {
+        let mut frames = Vec::new();
+        for x_axis in [XAxisDirection::TowardsSun, XAxisDirection::AwayFromSun] {
+            for handedness in [Handedness::Right, Handedness::Left] {
+                frames.push(GalactocentricFrame {
+                    x_axis,
+                    handedness,
+                    ..Default::default()
+                });
+            }
+        }
+        frames
+    }
+
+    fn assert_angle_diff(expected: f64, actual: f64, name: &str) {
+        let difference = (expected - actual + 180.).rem_euclid(360.) - 180.;
+        assert!(
+            difference.abs() < REFERENCE_ACCURACY,
+            "{}: expected {}, got {}",
+            name,
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn reference_objects_have_known_galactic_coordinates() {
+        for (name, ra, dec, l, b) in REFERENCE_OBJECTS {
+            let equatorial = EquatorialCoordinates {
+                right_ascension_in_deg: ra,
+                declination_in_deg: dec,
+            };
+            let galactic = galactic_from_equatorial(&equatorial, 1.);
+            assert_angle_diff(b, galactic.latitude_in_deg, name);
+            if b.abs() < 90. {
+                assert_angle_diff(l, galactic.longitude_in_deg, name);
+            }
+        }
+    }
+
+    #[test]
+    fn reference_objects_have_known_equatorial_coordinates() {
+        for (name, ra, dec, l, b) in REFERENCE_OBJECTS {
+            let galactic = GalacticCoordinates {
+                longitude_in_deg: l,
+                latitude_in_deg: b,
+                distance_in_pc: 1.,
+            };
+            let equatorial = equatorial_from_galactic(&galactic);
+            assert_angle_diff(ra, equatorial.right_ascension_in_deg, name);
+            assert_angle_diff(dec, equatorial.declination_in_deg, name);
+        }
+    }
+
+    #[test]
+    fn reference_points_have_known_ecliptic_coordinates() {
+        let references = [
+            ("Vernal equinox", 0., 0., 0., 0.),
+            ("Summer solstice", 90., 23.439_28, 90., 0.),
+            ("North ecliptic pole", 270., 66.560_72, 90., 90.),
+        ];
+        for (name, ra, dec, lambda, beta) in references {
+            let equatorial = EquatorialCoordinates {
+                right_ascension_in_deg: ra,
+                declination_in_deg: dec,
+            };
+            let ecliptic = ecliptic_from_equatorial(&equatorial);
+            assert_angle_diff(beta, ecliptic.latitude_in_deg, name);
+            if beta.abs() < 90. {
+                assert_angle_diff(lambda, ecliptic.longitude_in_deg, name);
+            }
+        }
+    }
+
+    #[test]
+    fn galactic_center_lies_at_zero_longitude() {
+        for frame in all_galactocentric_frames() {
+            let sun = frame.solar_position_in_pc();
+            let galactic = galactic_from_position((0., 0., 0.), sun, &frame);
+            assert_diff!(0., galactic.latitude_in_deg, 1e-10);
+            assert_angle_diff(0., galactic.longitude_in_deg, "Galactic center");
+            assert_diff!(
+                frame.solar_radius_in_pc.hypot(frame.solar_height_in_pc),
+                galactic.distance_in_pc,
+                1e-8
+            );
+        }
+    }
+
+    #[test]
+    fn positions_survive_round_trip_through_all_frames() {
+        for frame in all_galactocentric_frames() {
+            let observer = frame.solar_position_in_pc();
+            for position in [
+                (8100., 50., 20.),
+                (8300., -400., -90.),
+                (-8200., 10., 3000.),
+                (0., 0., 0.),
+            ] {
+                let galactic = galactic_from_position(position, observer, &frame);
+                let ecliptic = ecliptic_from_equatorial(&equatorial_from_galactic(&galactic));
+                let equatorial = equatorial_from_ecliptic(&ecliptic);
+                let galactic = galactic_from_equatorial(&equatorial, galactic.distance_in_pc);
+                let (x, y, z) = position_from_galactic(&galactic, observer, &frame);
+                assert_diff!(position.0, x, 1e-8);
+                assert_diff!(position.1, y, 1e-8);
+                assert_diff!(position.2, z, 1e-8);
+            }
+        }
+    }
+
+    #[test]
+    fn galactic_rotation_points_towards_positive_longitude() {
+        for frame in all_galactocentric_frames() {
+            let sun = frame.model_frame_vector(frame.solar_position_in_pc());
+            let ahead = frame.vector_from_model_frame((sun.0, sun.1 + 100., sun.2));
+            let galactic = galactic_from_position(ahead, frame.solar_position_in_pc(), &frame);
+            assert_angle_diff(90., galactic.longitude_in_deg, "Direction of rotation");
+        }
+    }
+
+    #[test]
+    fn only_requested_frames_are_included() {
+        let frame = GalactocentricFrame::default();
+        let observer = frame.solar_position_in_pc();
+        let coordinates =
+            SkyCoordinates::new((8100., 0., 0.), observer, &frame, &[SkyFrame::Ecliptic]);
+        assert!(coordinates.galactic.is_none());
+        assert!(coordinates.equatorial.is_none());
+        assert!(coordinates.ecliptic.is_some());
+        assert!(SkyCoordinates::new((8100., 0., 0.), observer, &frame, &[]).is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+use std::{collections::BTreeMap, error::Error, f64::consts::PI, mem::size_of};
+
+use serde::Serialize;
+
+use crate::{
+    catalogue::{
+        binary::{INDEX_ENTRY_SIZE, STAR_RECORD_SIZE},
+        column::{population_name, Column},
+        fits::row_width,
+        OutputFormat,
+    },
+    galactic_chunk::GalacticChunk,
+    generation_parameters::GenerationParameters,
+    generator::Generator,
+    star::Star,
+};
+
+/// Number of spherical shells around the observer over which the star counts are integrated.
+const RADIAL_STEPS: usize = 96;
+/// Number of directions per shell over which the star counts are integrated.
+const DIRECTIONS: usize = 256;
+/// Radius of the innermost sphere around the observer, relative to the chunk size.
+const INNERMOST_RADIUS_IN_CHUNKS: f64 = 0.01;
+/// Time spent on every sampled star, measured with a release build on a single core of one reference desktop CPU, in seconds.
+const SECONDS_PER_SAMPLED_STAR: f64 = 1.5e-7;
+/// Time spent on every star that passes the cut, in addition to sampling it, in seconds.
+///
+/// Observing and writing a star takes much longer than sampling and discarding it.
+const SECONDS_PER_KEPT_STAR: f64 = 5e-5;
+/// Time spent on every chunk, independent of its stars, in seconds.
+const SECONDS_PER_CHUNK: f64 = 2e-6;
+/// Typical length of a `Star` line in the JSON format without optional fields, in bytes.
+const BYTES_PER_JSON_STAR: f64 = 1100.;
+/// Typical length of a `ChunkCompleted` line in the JSON format, in bytes.
+const BYTES_PER_JSON_CHUNK: f64 = 26.;
+/// Typical length of an entry of a row in the CSV and TSV formats, including its separator, in bytes.
+const BYTES_PER_TEXT_ENTRY: f64 = 16.;
+
+/// The expected number of stars of one population.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub(crate) struct PopulationEstimate {
+    pub(crate) before_cut: f64,
+    pub(crate) after_cut: f64,
+}
+
+/// What a run with the given generation parameters is expected to produce and cost, computed without sampling any stars.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#dry-run>
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct DryRunEstimate {
+    pub(crate) chunks: u64,
+    /// Expected number of stars of every population, before and after the magnitude cut.
+    pub(crate) stars_per_population: BTreeMap<&'static str, PopulationEstimate>,
+    pub(crate) stars_before_cut: f64,
+    /// Expected number of stars that are sampled, which are fewer than all stars if importance sampling is enabled.
+    pub(crate) stars_sampled: f64,
+    pub(crate) stars_after_cut: f64,
+    /// Expected number of stars after the cut in the chunk with the most of them.
+    pub(crate) stars_in_largest_chunk: f64,
+    /// Peak memory, dominated by the creation of the chunks and the stars of the largest chunk.
+    pub(crate) memory_in_bytes: f64,
+    pub(crate) disk_in_bytes: f64,
+    /// Runtime on a single core, rounded to one significant digit.
+    ///
+    /// The time per star is measured on one reference machine and can be several times shorter or longer on another, so only the order of magnitude is meaningful.
+    pub(crate) runtime_order_of_magnitude_in_s: f64,
+}
+
+impl DryRunEstimate {
+    /// Integrates the density laws over the chunks around the observer, and the IMF and age range of every age bin over the luminosities above the magnitude limit.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#dry-run>
+    pub(crate) fn new(
+        generation_parameters: &GenerationParameters,
+        format: OutputFormat,
+        columns: &[Column],
+    ) -> Result<Self, Box<dyn Error>> {
+        let generator = Generator::new(generation_parameters)?;
+        let distributions = generator.luminosity_distributions();
+        let chunksize = generation_parameters.chunksize_in_pc;
+        let chunks = GalacticChunk::number_of_chunks(generation_parameters);
+        let observer = generation_parameters.observer_position_in_pc();
+        let observer_chunk = GalacticChunk::containing(generation_parameters, observer);
+        // The chunks reach beyond the maximal distance, so the integration covers them all and skips the positions outside of them.
+        let outermost = generation_parameters.max_distance_in_pc + 3_f64.sqrt() * chunksize;
+
+        let mut estimate = DryRunEstimate {
+            chunks,
+            ..Default::default()
+        };
+        let innermost = INNERMOST_RADIUS_IN_CHUNKS * chunksize;
+        let mut stars_in_observer_chunk = 0.;
+        let mut add = |position, volume: f64| {
+            let chunk = GalacticChunk::containing(generation_parameters, position);
+            if !chunk.is_generated(generation_parameters) {
+                return;
+            }
+            let mut after_cut_density = 0.;
+            for expected in generator.expected_stars(position, volume, &distributions) {
+                let population = estimate
+                    .stars_per_population
+                    .entry(population_name(expected.population))
+                    .or_default();
+                population.before_cut += expected.all;
+                population.after_cut += expected.visible;
+                estimate.stars_before_cut += expected.all;
+                estimate.stars_sampled += expected.sampled;
+                estimate.stars_after_cut += expected.visible;
+                after_cut_density += expected.visible / volume;
+            }
+            if chunk.corner == observer_chunk.corner {
+                stars_in_observer_chunk += after_cut_density * volume;
+            } else {
+                estimate.stars_in_largest_chunk = estimate
+                    .stars_in_largest_chunk
+                    .max(after_cut_density * chunksize.powi(3));
+            }
+        };
+        add(observer, 4. / 3. * PI * innermost.powi(3));
+        let ratio = outermost / innermost;
+        for step in 0..RADIAL_STEPS {
+            let inner = innermost * ratio.powf(step as f64 / RADIAL_STEPS as f64);
+            let outer = innermost * ratio.powf((step + 1) as f64 / RADIAL_STEPS as f64);
+            let volume = 4. / 3. * PI * (outer.powi(3) - inner.powi(3)) / DIRECTIONS as f64;
+            let radius = ((inner.powi(3) + outer.powi(3)) / 2.).cbrt();
+            for direction in fibonacci_sphere() {
+                let (x, y, z) = observer;
+                let position = (
+                    x + radius * direction.0,
+                    y + radius * direction.1,
+                    z + radius * direction.2,
+                );
+                add(position, volume);
+            }
+        }
+
+        estimate.stars_in_largest_chunk =
+            estimate.stars_in_largest_chunk.max(stars_in_observer_chunk);
+        let chunks = chunks as f64;
+        estimate.memory_in_bytes =
+            GalacticChunk::memory_of_chunk_generation_in_bytes(generation_parameters) as f64
+                + estimate.stars_in_largest_chunk * size_of::<Star>() as f64;
+        let (bytes_per_star, bytes_per_chunk) = record_sizes(format, columns);
+        estimate.disk_in_bytes =
+            estimate.stars_after_cut * bytes_per_star + chunks * bytes_per_chunk;
+        estimate.runtime_order_of_magnitude_in_s = round_to_one_significant_digit(
+            estimate.stars_sampled * SECONDS_PER_SAMPLED_STAR
+                + estimate.stars_after_cut * SECONDS_PER_KEPT_STAR
+                + chunks * SECONDS_PER_CHUNK,
+        );
+        Ok(estimate)
+    }
+}
+
+fn round_to_one_significant_digit(value: f64) -> f64 {
+    if value <= 0. {
+        return 0.;
+    }
+    let magnitude = 10_f64.powf(value.log10().floor());
+    (value / magnitude).round() * magnitude
+}
+
+/// Directions spread evenly over the sphere, as unit vectors.
+fn fibonacci_sphere() -> impl Iterator<Item = (f64, f64, f64)> {
+    let golden_angle = PI * (3. - 5_f64.sqrt());
+    (0..DIRECTIONS).map(move |i| {
+        let z = 1. - 2. * (i as f64 + 0.5) / DIRECTIONS as f64;
+        let r = (1. - z * z).sqrt();
+        let phi = golden_angle * i as f64;
+        (r * phi.cos(), r * phi.sin(), z)
+    })
+}
+
+/// The typical number of bytes of a star without companions and of the end of a chunk in the given format.
+fn record_sizes(format: OutputFormat, columns: &[Column]) -> (f64, f64) {
+    match format {
+        OutputFormat::Ndjson => (BYTES_PER_JSON_STAR, BYTES_PER_JSON_CHUNK),
+        OutputFormat::Binary => (
+            (1 + STAR_RECORD_SIZE) as f64,
+            (1 + size_of::<u64>() + INDEX_ENTRY_SIZE) as f64,
+        ),
+        OutputFormat::Csv | OutputFormat::Tsv => (columns.len() as f64 * BYTES_PER_TEXT_ENTRY, 0.),
+        OutputFormat::Fits => (row_width(columns) as f64, 0.),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assert_ratio,
+        catalogue::{ndjson::NdjsonWriter, write_catalogue},
+    };
+
+    use super::*;
+
+    #[test]
+    fn estimate_agrees_with_a_generated_catalogue() {
+        let params = GenerationParameters {
+            observer_position_in_pc: Some((8200., 0., 0.)),
+            apparent_magnitude_limit: 7.,
+            max_distance_in_pc: 100.,
+            chunksize_in_pc: 15.,
+            ..Default::default()
+        };
+        let estimate = DryRunEstimate::new(&params, OutputFormat::Ndjson, &[])
+            .expect("The estimate should succeed");
+        let mut catalogue = Vec::new();
+        let statistics = write_catalogue(&params, &mut NdjsonWriter::new(&mut catalogue))
+            .expect("Generation should succeed");
+
+        assert_eq!(estimate.chunks as usize, statistics.chunks_processed);
+        assert_ratio!(estimate.stars_sampled, statistics.stars_sampled, 0.1);
+        assert_ratio!(estimate.stars_after_cut, statistics.stars_kept, 0.1);
+        assert_ratio!(estimate.disk_in_bytes, catalogue.len(), 0.1);
+        let per_population: f64 = estimate
+            .stars_per_population
+            .values()
+            .map(|population| population.after_cut)
+            .sum();
+        assert_ratio!(per_population, estimate.stars_after_cut, 1e-9);
+    }
+
+    #[test]
+    fn estimate_agrees_with_a_generated_catalogue_inside_the_bulge() {
+        let params = GenerationParameters {
+            observer_position_in_pc: Some((100., 200., 300.)),
+            apparent_magnitude_limit: 0.,
+            max_distance_in_pc: 40.,
+            chunksize_in_pc: 20.,
+            ..Default::default()
+        };
+        let estimate = DryRunEstimate::new(&params, OutputFormat::Ndjson, &[])
+            .expect("The estimate should succeed");
+        let mut catalogue = Vec::new();
+        let statistics = write_catalogue(&params, &mut NdjsonWriter::new(&mut catalogue))
+            .expect("Generation should succeed");
+
+        let bulge = estimate.stars_per_population["Bulge"].after_cut;
+        assert!(bulge > 0.9 * estimate.stars_after_cut);
+        assert_ratio!(estimate.stars_sampled, statistics.stars_sampled, 0.1);
+        assert_ratio!(estimate.stars_after_cut, statistics.stars_kept, 0.1);
+        assert_ratio!(estimate.disk_in_bytes, catalogue.len(), 0.1);
+    }
+
+    #[test]
+    fn importance_sampling_reduces_only_the_sampled_stars() {
+        let params = GenerationParameters {
+            observer_position_in_pc: Some((8200., 0., 0.)),
+            apparent_magnitude_limit: 6.,
+            max_distance_in_pc: 500.,
+            chunksize_in_pc: 15.,
+            ..Default::default()
+        };
+        let importance = GenerationParameters {
+            importance_sampling: true,
+            ..params.clone()
+        };
+        let full = DryRunEstimate::new(&params, OutputFormat::Binary, &[])
+            .expect("The estimate should succeed");
+        let reduced = DryRunEstimate::new(&importance, OutputFormat::Binary, &[])
+            .expect("The estimate should succeed");
+        assert!(reduced.stars_sampled < full.stars_sampled / 2.);
+        assert!(reduced.runtime_order_of_magnitude_in_s < full.runtime_order_of_magnitude_in_s);
+        assert_ratio!(reduced.stars_before_cut, full.stars_before_cut, 1e-9);
+        assert_ratio!(reduced.stars_after_cut, full.stars_after_cut, 1e-9);
+    }
+
+    #[test]
+    fn chunks_up_to_the_galactic_center_are_counted_without_creating_them() {
+        let params = GenerationParameters {
+            observer_position_in_pc: Some((8200., 0., 0.)),
+            apparent_magnitude_limit: 6.,
+            max_distance_in_pc: 8200.,
+            chunksize_in_pc: 15.,
+            ..Default::default()
+        };
+        let estimate = DryRunEstimate::new(&params, OutputFormat::Ndjson, &[])
+            .expect("The estimate should succeed");
+        assert_ratio!(estimate.chunks, 7e8, 0.05);
+        // The chunks are created lazily, so they take far less memory than a list of all of them.
+        assert!(estimate.memory_in_bytes < 1e8);
+        assert!(
+            estimate.memory_in_bytes
+                < 1e-2 * (estimate.chunks as usize * size_of::<GalacticChunk>()) as f64
+        );
+    }
+}
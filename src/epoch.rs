@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+use crate::galactic_potential::GalacticPotential;
+
+/// Distance travelled in one kiloyear at one km/s, in units of parsec.
+const PC_PER_KM_PER_S_KYR: f64 = 1.022_712_165e-3;
+/// Time step used to integrate orbits in the galactic potential, in units of kiloyears.
+const INTEGRATION_STEP_IN_KYR: f64 = 1.;
+/// An upper bound for the speed of a star relative to the observer, in units of km/s.
+///
+/// This is about twice the local escape velocity, which only stars in the far tails of the velocity ellipsoids exceed.
+const MAX_RELATIVE_SPEED_IN_KM_PER_S: f64 = 1000.;
+
+/// How stars are moved from the present to the epoch of the catalogue.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#epoch>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Propagation {
+    /// Stars move on straight lines with constant velocity.
+    #[default]
+    Linear,
+    /// Stars orbit in the gravitational potential of the galaxy.
+    GalacticPotential,
+}
+
+/// The time at which the positions of the stars are given.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#epoch>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Epoch {
+    /// Time relative to the present, negative for the past, in units of kiloyears.
+    pub offset_in_kyr: f64,
+    /// How the stars are moved from their present positions to the epoch.
+    pub propagation: Propagation,
+}
+
+impl Epoch {
+    pub(crate) fn is_present(&self) -> bool {
+        self.offset_in_kyr == 0.
+    }
+
+    /// How much closer to the observer a star can get between the present and this epoch, in units of parsec.
+    pub(crate) fn max_approach_in_pc(&self) -> f64 {
+        self.offset_in_kyr.abs() * MAX_RELATIVE_SPEED_IN_KM_PER_S * PC_PER_KM_PER_S_KYR
+    }
+
+    /// Moves a position and velocity in the model frame from the present to this epoch.
+    pub(crate) fn propagate(
+        &self,
+        position_in_pc: (f64, f64, f64),
+        velocity_in_km_per_s: (f64, f64, f64),
+        potential: &GalacticPotential,
+    ) -> ((f64, f64, f64), (f64, f64, f64)) {
+        if self.is_present() {
+            return (position_in_pc, velocity_in_km_per_s);
+        }
+        match self.propagation {
+            Propagation::Linear => {
+                let time = self.offset_in_kyr * PC_PER_KM_PER_S_KYR;
+                (
+                    advance(position_in_pc, velocity_in_km_per_s, time),
+                    velocity_in_km_per_s,
+                )
+            }
+            Propagation::GalacticPotential => integrate_orbit(
+                position_in_pc,
+                velocity_in_km_per_s,
+                self.offset_in_kyr,
+                potential,
+            ),
+        }
+    }
+}
+
+/// Integrates an orbit in the galactic potential with the leapfrog scheme, which conserves energy well over many orbits.
+fn integrate_orbit(
+    position_in_pc: (f64, f64, f64),
+    velocity_in_km_per_s: (f64, f64, f64),
+    offset_in_kyr: f64,
+    potential: &GalacticPotential,
+) -> ((f64, f64, f64), (f64, f64, f64)) {
+    let steps = (offset_in_kyr.abs() / INTEGRATION_STEP_IN_KYR)
+        .ceil()
+        .max(1.) as usize;
+    // Time step in units of parsec per km/s, such that velocity times step is a distance in parsec.
+    let step = offset_in_kyr / steps as f64 * PC_PER_KM_PER_S_KYR;
+    let mut position = position_in_pc;
+    let mut velocity = velocity_in_km_per_s;
+    for _ in 0..steps {
+        velocity = advance(velocity, potential.acceleration(position), step / 2.);
+        position = advance(position, velocity, step);
+        velocity = advance(velocity, potential.acceleration(position), step / 2.);
+    }
+    (position, velocity)
+}
+
+fn advance(value: (f64, f64, f64), rate: (f64, f64, f64), time: f64) -> (f64, f64, f64) {
+    (
+        value.0 + rate.0 * time,
+        value.1 + rate.1 * time,
+        value.2 + rate.2 * time,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assert_diff, assert_ratio,
+        galactic_potential::DarkHalo,
+        galactocentric_frame::{GalactocentricFrame, DEFAULT_SOLAR_RADIUS_IN_PC},
+    };
+
+    use super::*;
+
+    const SUN: (f64, f64, f64) = (DEFAULT_SOLAR_RADIUS_IN_PC, 0., 0.);
+
+    fn epoch(offset_in_kyr: f64, propagation: Propagation) -> Epoch {
+        Epoch {
+            offset_in_kyr,
+            propagation,
+        }
+    }
+
+    fn potential() -> GalacticPotential {
+        GalacticPotential::new(DarkHalo::Nfw, &GalactocentricFrame::default())
+    }
+
+    #[test]
+    fn present_epoch_leaves_stars_in_place() {
+        for propagation in [Propagation::Linear, Propagation::GalacticPotential] {
+            let velocity = (10., 200., -5.);
+            assert_eq!(
+                (SUN, velocity),
+                epoch(0., propagation).propagate(SUN, velocity, &potential())
+            );
+        }
+    }
+
+    #[test]
+    fn linear_propagation_moves_one_parsec_per_million_years_at_one_km_per_s() {
+        let (position, velocity) =
+            epoch(1000., Propagation::Linear).propagate((0., 0., 0.), (1., 0., -1.), &potential());
+        assert_diff!(1.0227, position.0, 1e-4);
+        assert_diff!(-1.0227, position.2, 1e-4);
+        assert_eq!((1., 0., -1.), velocity);
+    }
+
+    #[test]
+    fn circular_orbits_stay_circular() {
+        let circular = potential().circular_velocity_in_km_per_s(DEFAULT_SOLAR_RADIUS_IN_PC);
+        let (position, velocity) = epoch(50_000., Propagation::GalacticPotential).propagate(
+            SUN,
+            (0., circular, 0.),
+            &potential(),
+        );
+        assert_ratio!(
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+            position.0.hypot(position.1),
+            1e-6
+        );
+        assert_ratio!(circular, velocity.0.hypot(velocity.1), 1e-6);
+        assert!(position.1 > 0.);
+    }
+
+    #[test]
+    fn short_orbits_are_nearly_straight() {
+        let velocity = (30., 220., 10.);
+        let offset = 100.;
+        let (linear, _) = epoch(offset, Propagation::Linear).propagate(SUN, velocity, &potential());
+        let (orbit, _) =
+            epoch(offset, Propagation::GalacticPotential).propagate(SUN, velocity, &potential());
+        assert_diff!(linear.0, orbit.0, 0.1);
+        assert_diff!(linear.1, orbit.1, 0.1);
+        assert_diff!(linear.2, orbit.2, 0.1);
+    }
+
+    #[test]
+    fn propagating_back_and_forth_returns_to_the_start() {
+        let velocity = (-40., 180., 25.);
+        let (position, velocity_then) =
+            epoch(-300., Propagation::GalacticPotential).propagate(SUN, velocity, &potential());
+        let (back, velocity_now) = epoch(300., Propagation::GalacticPotential).propagate(
+            position,
+            velocity_then,
+            &potential(),
+        );
+        assert_diff!(SUN.0, back.0, 1e-6);
+        assert_diff!(SUN.1, back.1, 1e-6);
+        assert_diff!(velocity.1, velocity_now.1, 1e-6);
+    }
+}
@@ -0,0 +1,340 @@
+use std::{error::Error, fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    galactocentric_frame::GalactocentricFrame,
+    generator::distance_in_pc,
+    interpolation::{bracket, grid_axis},
+    photometry::Magnitudes,
+};
+
+/// Visual extinction per parsec in the galactic plane at the solar radius, in units of magnitudes per parsec.
+const LOCAL_EXTINCTION_IN_MAG_PER_PC: f64 = 0.7e-3;
+/// Radial scale length of the dust disc, in units of parsec.
+const DUST_SCALE_LENGTH_IN_PC: f64 = 2300.;
+/// Vertical scale height of the dust disc, in units of parsec.
+const DUST_SCALE_HEIGHT_IN_PC: f64 = 134.4;
+/// Length of the steps used to integrate the extinction along a line of sight, in units of parsec.
+const INTEGRATION_STEP_IN_PC: f64 = 5.;
+
+/// Extinction in a band relative to the visual extinction `A_V`, for `R_V = 3.1`.
+const JOHNSON_B_TO_V_RATIO: f64 = 1.324;
+const GAIA_G_TO_V_RATIO: f64 = 0.789;
+const GAIA_BP_TO_V_RATIO: f64 = 1.002;
+const GAIA_RP_TO_V_RATIO: f64 = 0.589;
+/// The bolometric extinction depends on the spectrum of the star. It is approximated by that of the broad Gaia G band.
+const BOLOMETRIC_TO_V_RATIO: f64 = GAIA_G_TO_V_RATIO;
+
+/// The dust distribution used to dim stars along their line of sight.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#extinction>
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExtinctionModel {
+    /// A dust disc falling off exponentially with galactocentric radius and height.
+    ExponentialDisc,
+    /// A 3D grid of dust densities read from the given file.
+    Grid(PathBuf),
+}
+
+/// A loaded extinction model, ready to be integrated along lines of sight.
+#[derive(Debug, Clone)]
+pub(crate) enum Extinction {
+    ExponentialDisc { solar_radius_in_pc: f64 },
+    Grid(DustGrid),
+}
+
+impl Extinction {
+    pub(crate) fn load(
+        model: &ExtinctionModel,
+        frame: &GalactocentricFrame,
+    ) -> Result<Self, Box<dyn Error>> {
+        match model {
+            ExtinctionModel::ExponentialDisc => Ok(Self::ExponentialDisc {
+                solar_radius_in_pc: frame.solar_radius_in_pc,
+            }),
+            ExtinctionModel::Grid(path) => {
+                Ok(Self::Grid(DustGrid::parse(&fs::read_to_string(path)?)?))
+            }
+        }
+    }
+
+    /// The visual extinction `A_V` between the two positions, in units of magnitudes.
+    ///
+    /// The dust density is integrated with the midpoint rule in steps of at most [`INTEGRATION_STEP_IN_PC`].
+    pub(crate) fn visual_extinction(&self, from: (f64, f64, f64), to: (f64, f64, f64)) -> f64 {
+        let distance = distance_in_pc(from, to);
+        let steps = (distance / INTEGRATION_STEP_IN_PC).ceil().max(1.) as usize;
+        let step_length = distance / steps as f64;
+        (0..steps)
+            .map(|step| {
+                let t = (step as f64 + 0.5) / steps as f64;
+                let position = (
+                    from.0 + t * (to.0 - from.0),
+                    from.1 + t * (to.1 - from.1),
+                    from.2 + t * (to.2 - from.2),
+                );
+                self.density(position) * step_length
+            })
+            .sum()
+    }
+
+    /// The visual extinction per parsec at the given position in the galactocentric frame, in units of magnitudes per parsec.
+    fn density(&self, position: (f64, f64, f64)) -> f64 {
+        match self {
+            Extinction::ExponentialDisc { solar_radius_in_pc } => {
+                let (x, y, z) = position;
+                let radius = (x * x + y * y).sqrt();
+                LOCAL_EXTINCTION_IN_MAG_PER_PC
+                    * (-(radius - solar_radius_in_pc) / DUST_SCALE_LENGTH_IN_PC).exp()
+                    * (-z.abs() / DUST_SCALE_HEIGHT_IN_PC).exp()
+            }
+            Extinction::Grid(grid) => grid.density(position),
+        }
+    }
+}
+
+/// The names of the columns of a dust grid, in order.
+const COLUMNS: [&str; 4] = ["x_in_pc", "y_in_pc", "z_in_pc", "extinction_in_mag_per_pc"];
+
+/// Visual extinction per parsec tabulated on a regular galactocentric grid.
+#[derive(Debug, Clone)]
+pub(crate) struct DustGrid {
+    xs: Vec<f64>,
+    ys: Vec<f64>,
+    zs: Vec<f64>,
+    /// Extinction per parsec, indexed by x, y and z in this order.
+    densities: Vec<f64>,
+}
+
+impl DustGrid {
+    /// Parses a comma separated table with the columns x, y, z and visual extinction per parsec.
+    ///
+    /// Lines starting with `#` and the header line are ignored.
+    fn parse(content: &str) -> Result<Self, Box<dyn Error>> {
+        let mut rows = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('x') {
+                continue;
+            }
+            let values = line
+                .split(',')
+                .map(|value| value.trim().parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()?;
+            if values.len() != COLUMNS.len() {
+                return Err(format!("Expected 4 columns in dust grid line '{}'", line).into());
+            }
+            if let Some((_, column)) = values.iter().zip(COLUMNS).find(|(v, _)| !v.is_finite()) {
+                return Err(format!(
+                    "The dust grid line '{}' has an invalid value in column {}",
+                    line, column
+                )
+                .into());
+            }
+            rows.push(values);
+        }
+
+        let xs = grid_axis(&rows, 0);
+        let ys = grid_axis(&rows, 1);
+        let zs = grid_axis(&rows, 2);
+        if xs.len() < 2 || ys.len() < 2 || zs.len() < 2 {
+            return Err("The dust grid needs at least two values per axis".into());
+        }
+
+        let size = xs.len() * ys.len() * zs.len();
+        let mut densities = vec![0.; size];
+        let mut filled = vec![false; size];
+        for row in rows.iter() {
+            let position = |axis: &[f64], column: usize| {
+                axis.iter()
+                    .position(|&v| v == row[column])
+                    .unwrap_or_default()
+            };
+            let i = position(&xs, 0);
+            let j = position(&ys, 1);
+            let k = position(&zs, 2);
+            let index = (i * ys.len() + j) * zs.len() + k;
+            if filled[index] {
+                return Err(format!(
+                    "The dust grid contains more than one entry for the point ({}, {}, {})",
+                    row[0], row[1], row[2]
+                )
+                .into());
+            }
+            densities[index] = row[3];
+            filled[index] = true;
+        }
+        if let Some(index) = filled.iter().position(|&f| !f) {
+            let k = index % zs.len();
+            let j = index / zs.len() % ys.len();
+            let i = index / zs.len() / ys.len();
+            return Err(format!(
+                "The dust grid has no entry for the point ({}, {}, {})",
+                xs[i], ys[j], zs[k]
+            )
+            .into());
+        }
+
+        Ok(Self {
+            xs,
+            ys,
+            zs,
+            densities,
+        })
+    }
+
+    /// The extinction per parsec, interpolated linearly inside the grid and zero outside of it.
+    fn density(&self, position: (f64, f64, f64)) -> f64 {
+        let (x, y, z) = position;
+        let inside = |axis: &Vec<f64>, value: f64| {
+            axis.first().is_some_and(|&first| value >= first)
+                && axis.last().is_some_and(|&last| value <= last)
+        };
+        if !inside(&self.xs, x) || !inside(&self.ys, y) || !inside(&self.zs, z) {
+            return 0.;
+        }
+        let (i, u) = bracket(&self.xs, x);
+        let (j, v) = bracket(&self.ys, y);
+        let (k, w) = bracket(&self.zs, z);
+        let mut result = 0.;
+        for (di, wi) in [(0, 1. - u), (1, u)] {
+            for (dj, wj) in [(0, 1. - v), (1, v)] {
+                for (dk, wk) in [(0, 1. - w), (1, w)] {
+                    let index = ((i + di) * self.ys.len() + j + dj) * self.zs.len() + k + dk;
+                    result += wi * wj * wk * self.densities[index];
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Dims the magnitudes of a star by the given visual extinction, reddening its colours in the process.
+pub(crate) fn redden(magnitudes: &Magnitudes, visual_extinction_in_mag: f64) -> Magnitudes {
+    let a_v = visual_extinction_in_mag;
+    Magnitudes {
+        bolometric: magnitudes.bolometric + BOLOMETRIC_TO_V_RATIO * a_v,
+        johnson_b: magnitudes.johnson_b + JOHNSON_B_TO_V_RATIO * a_v,
+        johnson_v: magnitudes.johnson_v + a_v,
+        gaia_g: magnitudes.gaia_g + GAIA_G_TO_V_RATIO * a_v,
+        gaia_bp: magnitudes.gaia_bp + GAIA_BP_TO_V_RATIO * a_v,
+        gaia_rp: magnitudes.gaia_rp + GAIA_RP_TO_V_RATIO * a_v,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_diff, assert_ratio, galactocentric_frame::DEFAULT_SOLAR_RADIUS_IN_PC};
+
+    use super::*;
+
+    const SUN: (f64, f64, f64) = (DEFAULT_SOLAR_RADIUS_IN_PC, 0., 0.);
+
+    #[test]
+    fn disc_extinction_in_the_plane_matches_local_value() {
+        let extinction = Extinction::ExponentialDisc {
+            solar_radius_in_pc: DEFAULT_SOLAR_RADIUS_IN_PC,
+        };
+        let a_v = extinction.visual_extinction(SUN, (DEFAULT_SOLAR_RADIUS_IN_PC, 1000., 0.));
+        assert_ratio!(0.7, a_v, 0.02);
+    }
+
+    #[test]
+    fn disc_extinction_towards_the_pole_is_limited_by_the_scale_height() {
+        let extinction = Extinction::ExponentialDisc {
+            solar_radius_in_pc: DEFAULT_SOLAR_RADIUS_IN_PC,
+        };
+        let a_v = extinction.visual_extinction(SUN, (DEFAULT_SOLAR_RADIUS_IN_PC, 0., 5000.));
+        assert_ratio!(
+            LOCAL_EXTINCTION_IN_MAG_PER_PC * DUST_SCALE_HEIGHT_IN_PC,
+            a_v,
+            0.01
+        );
+    }
+
+    #[test]
+    fn extinction_grows_with_distance() {
+        let extinction = Extinction::ExponentialDisc {
+            solar_radius_in_pc: DEFAULT_SOLAR_RADIUS_IN_PC,
+        };
+        let mut previous = 0.;
+        for distance in [10., 100., 1000., 5000.] {
+            let a_v =
+                extinction.visual_extinction(SUN, (DEFAULT_SOLAR_RADIUS_IN_PC - distance, 0., 0.));
+            assert!(a_v > previous);
+            previous = a_v;
+        }
+    }
+
+    fn uniform_grid(density: f64) -> DustGrid {
+        let mut content = String::from("x_in_pc,y_in_pc,z_in_pc,extinction_in_mag_per_pc\n");
+        for x in [8000., 8400.] {
+            for y in [-200., 200.] {
+                for z in [-100., 100.] {
+                    content += &format!("{},{},{},{}\n", x, y, z, density);
+                }
+            }
+        }
+        DustGrid::parse(&content).expect("The grid should be valid")
+    }
+
+    #[test]
+    fn uniform_grid_extinction_is_proportional_to_distance() {
+        let extinction = Extinction::Grid(uniform_grid(1e-3));
+        let a_v = extinction.visual_extinction(SUN, (DEFAULT_SOLAR_RADIUS_IN_PC + 100., 50., 0.));
+        let distance = distance_in_pc(SUN, (DEFAULT_SOLAR_RADIUS_IN_PC + 100., 50., 0.));
+        assert_ratio!(1e-3 * distance, a_v, 1e-10);
+    }
+
+    #[test]
+    fn grid_has_no_dust_outside() {
+        let grid = uniform_grid(1e-3);
+        assert_eq!(0., grid.density((DEFAULT_SOLAR_RADIUS_IN_PC, 0., 150.)));
+        assert_eq!(0., grid.density((9000., 0., 0.)));
+    }
+
+    #[test]
+    fn incomplete_grids_are_rejected() {
+        let content = "x_in_pc,y_in_pc,z_in_pc,extinction_in_mag_per_pc
+0,0,0,1
+1,0,0,1
+0,1,0,1
+0,0,1,1";
+        let error = DustGrid::parse(content)
+            .expect_err("The grid should be rejected")
+            .to_string();
+        assert!(
+            error.contains("no entry for the point (0, 1, 1)"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn duplicate_grid_points_are_reported() {
+        let mut content = String::from("x_in_pc,y_in_pc,z_in_pc,extinction_in_mag_per_pc\n");
+        for x in [0., 1.] {
+            for y in [0., 1.] {
+                for z in [0., 1.] {
+                    content += &format!("{},{},{},1\n", x, y, z);
+                }
+            }
+        }
+        content += "1,0,1,2\n";
+        let error = DustGrid::parse(&content)
+            .expect_err("The grid should be rejected")
+            .to_string();
+        assert!(
+            error.contains("more than one entry for the point (1, 0, 1)"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn reddening_follows_the_extinction_law() {
+        let reddened = redden(&Magnitudes::default(), 3.1);
+        assert_diff!(3.1, reddened.johnson_v, 1e-10);
+        assert_diff!(1., reddened.johnson_b - reddened.johnson_v, 0.01);
+        assert!(reddened.gaia_bp > reddened.gaia_rp);
+    }
+}
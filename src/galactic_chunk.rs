@@ -1,75 +1,194 @@
-use crate::generation_parameters::GenerationParameters;
+use std::{
+    cmp::Reverse,
+    collections::{binary_heap::PeekMut, BinaryHeap},
+    mem::size_of,
+};
+
+use crate::{
+    galactocentric_frame::GalactocentricFrame, generation_parameters::GenerationParameters,
+};
 
 /// A chunk of the galaxy.
 #[derive(Debug, Clone)]
-pub(crate) struct GalacticChunk {
+pub struct GalacticChunk {
     /// The defining corner of the chunk, in units of parsec.
     ///
     /// The chunk is a cube spanning ().
-    pub(crate) corner: (f64, f64, f64),
+    pub corner: (f64, f64, f64),
 }
 
 impl GalacticChunk {
     /// Separates the galaxy into chunks, up to the maximal generation distance.
     ///
-    /// Compare https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#chunking-the-galaxy
-    pub(crate) fn generate_chunks(generation_parameters: &GenerationParameters) -> Vec<Self> {
+    /// The chunks are created lazily, one at a time, in order of their distance from the chunk of the observer.
+    /// Chunks at the same distance are ordered in the model frame, so the conventions of the galactocentric frame do not change which chunk has which index.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#chunking-the-galaxy>
+    pub fn generate_chunks(generation_parameters: &GenerationParameters) -> GalacticChunks {
         let n = number_of_chunks_along_axis(generation_parameters);
-        let chunk_numbers_first_octant = chunk_numbers_in_first_octant(n);
-        Self::create_chunks_in_sphere(generation_parameters, chunk_numbers_first_octant)
+        GalacticChunks {
+            origin: Self::origin_chunk(generation_parameters).corner,
+            chunksize_in_pc: generation_parameters.chunksize_in_pc,
+            frame: generation_parameters.galactocentric_frame,
+            chunk_numbers_first_octant: ChunkNumbersInFirstOctant::new(n),
+            mirrored: Vec::with_capacity(8),
+            remaining: Self::number_of_chunks(generation_parameters),
+        }
     }
 
-    fn origin_chunk(generation_parameters: &GenerationParameters) -> Self {
-        let mut corner = generation_parameters.observer_position_in_pc;
-        let chunksize = generation_parameters.chunksize_in_pc;
-        corner.0 -= corner.0 % chunksize;
-        corner.1 -= corner.1 % chunksize;
-        corner.2 -= corner.2 % chunksize;
-        Self { corner }
+    /// The number of chunks `generate_chunks` returns, counted without creating them.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#dry-run>
+    pub fn number_of_chunks(generation_parameters: &GenerationParameters) -> u64 {
+        let n = number_of_chunks_along_axis(generation_parameters);
+        let radius_squared = (n - 1) * (n - 1);
+        let signs = |i: usize| if i == 0 { 1 } else { 2 };
+        let mut count = 0;
+        for x in 0..n {
+            for y in 0..n {
+                let Some(remaining) = radius_squared.checked_sub(x * x + y * y) else {
+                    continue;
+                };
+                let mut z_max = (remaining as f64).sqrt() as usize;
+                while z_max * z_max > remaining {
+                    z_max -= 1;
+                }
+                while (z_max + 1) * (z_max + 1) <= remaining {
+                    z_max += 1;
+                }
+                count += (signs(x) * signs(y) * (2 * z_max + 1)) as u64;
+            }
+        }
+        count
     }
 
-    fn create_chunks_in_sphere(
+    /// The memory the iterator returned by `generate_chunks` holds at most, which is dominated by one entry per column of chunks along the z axis in the first octant.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#dry-run>
+    pub(crate) fn memory_of_chunk_generation_in_bytes(
         generation_parameters: &GenerationParameters,
-        chunk_numbers_first_octant: Vec<(usize, usize, usize)>,
-    ) -> Vec<Self> {
-        let origin_chunk = Self::origin_chunk(generation_parameters);
-        let (x0, y0, z0) = origin_chunk.corner;
-
-        let mut chunks: Vec<Self> = Vec::with_capacity(chunk_numbers_first_octant.len() * 8);
-        let size = generation_parameters.chunksize_in_pc;
-        for (x, y, z) in chunk_numbers_first_octant.iter() {
-            for x_sign in [-1, 1].iter() {
-                if x == &0 && x_sign == &-1 {
-                    continue;
-                }
-                let x = *x as f64;
-                let x_sign = *x_sign as f64;
-                for y_sign in [-1, 1].iter() {
-                    if y == &0 && y_sign == &-1 {
-                        continue;
-                    }
-                    let y = *y as f64;
-                    let y_sign = *y_sign as f64;
-                    for z_sign in [-1, 1].iter() {
-                        if z == &0 && z_sign == &-1 {
-                            continue;
-                        }
-                        let z = *z as f64;
-                        let z_sign = *z_sign as f64;
-                        let corner = (
-                            x0 + x * x_sign * size,
-                            y0 + y * y_sign * size,
-                            z0 + z * z_sign * size,
-                        );
-                        chunks.push(GalacticChunk { corner });
-                    }
+    ) -> u64 {
+        let n = number_of_chunks_along_axis(generation_parameters);
+        let radius_squared = (n - 1) * (n - 1);
+        let columns = (0..n)
+            .flat_map(|x| (0..n).map(move |y| x * x + y * y))
+            .filter(|&distance_squared| distance_squared <= radius_squared)
+            .count();
+        (columns * size_of::<Reverse<ChunkNumber>>() + size_of::<GalacticChunks>()) as u64
+    }
+
+    /// The center of the chunk, in units of parsec.
+    pub fn center(&self, chunksize_in_pc: f64) -> (f64, f64, f64) {
+        let half = chunksize_in_pc / 2.;
+        let (x, y, z) = self.corner;
+        (x + half, y + half, z + half)
+    }
+
+    /// The distance from a point to the closest point of the chunk, in units of parsec.
+    ///
+    /// This is zero if the point lies inside the chunk.
+    pub fn nearest_distance_in_pc(&self, chunksize_in_pc: f64, point: (f64, f64, f64)) -> f64 {
+        let (x0, y0, z0) = self.corner;
+        let (px, py, pz) = point;
+        let gap = |p: f64, lower: f64| (lower - p).max(p - (lower + chunksize_in_pc)).max(0.);
+        let (dx, dy, dz) = (gap(px, x0), gap(py, y0), gap(pz, z0));
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    /// The chunk of [`GalacticChunk::generate_chunks`] that contains the given position.
+    pub(crate) fn containing(
+        generation_parameters: &GenerationParameters,
+        position_in_pc: (f64, f64, f64),
+    ) -> Self {
+        let chunksize = generation_parameters.chunksize_in_pc;
+        let (x0, y0, z0) = Self::origin_chunk(generation_parameters).corner;
+        let (x, y, z) = position_in_pc;
+        let snap = |p: f64, origin: f64| origin + ((p - origin) / chunksize).floor() * chunksize;
+        Self {
+            corner: (snap(x, x0), snap(y, y0), snap(z, z0)),
+        }
+    }
+
+    /// Whether the chunk is one of [`GalacticChunk::generate_chunks`].
+    pub(crate) fn is_generated(&self, generation_parameters: &GenerationParameters) -> bool {
+        let n = number_of_chunks_along_axis(generation_parameters) as f64;
+        let chunksize = generation_parameters.chunksize_in_pc;
+        let (x0, y0, z0) = Self::origin_chunk(generation_parameters).corner;
+        let (x, y, z) = self.corner;
+        let number = |p: f64, origin: f64| ((p - origin) / chunksize).round();
+        let (i, j, k) = (number(x, x0), number(y, y0), number(z, z0));
+        i * i + j * j + k * k <= (n - 1.) * (n - 1.)
+    }
+
+    /// The chunk with the observer at its center.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#chunking-the-galaxy>
+    fn origin_chunk(generation_parameters: &GenerationParameters) -> Self {
+        let (x, y, z) = generation_parameters.observer_position_in_pc();
+        let half = generation_parameters.chunksize_in_pc / 2.;
+        Self {
+            corner: (x - half, y - half, z - half),
+        }
+    }
+}
+
+/// The chunks of the galaxy up to the maximal generation distance, created one at a time.
+///
+/// Instead of all chunks, only the next chunk number of every column along the z axis in the first octant is held.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#chunking-the-galaxy>
+#[derive(Debug, Clone)]
+pub struct GalacticChunks {
+    origin: (f64, f64, f64),
+    chunksize_in_pc: f64,
+    frame: GalactocentricFrame,
+    chunk_numbers_first_octant: ChunkNumbersInFirstOctant,
+    /// The mirror images of the current chunk number in the other octants that are still to be returned, in reverse order.
+    mirrored: Vec<GalacticChunk>,
+    remaining: u64,
+}
+
+impl GalacticChunks {
+    fn mirror(&mut self, (x, y, z): (usize, usize, usize)) {
+        let (x0, y0, z0) = self.origin;
+        let size = self.chunksize_in_pc;
+        let signs = |i: usize| if i == 0 { &[1.][..] } else { &[-1., 1.][..] };
+        for x_sign in signs(x) {
+            for y_sign in signs(y) {
+                for z_sign in signs(z) {
+                    let (dx, dy, dz) = self.frame.vector_from_model_frame((
+                        x as f64 * x_sign * size,
+                        y as f64 * y_sign * size,
+                        z as f64 * z_sign * size,
+                    ));
+                    let corner = (x0 + dx, y0 + dy, z0 + dz);
+                    self.mirrored.push(GalacticChunk { corner });
                 }
             }
         }
-        chunks
+        self.mirrored.reverse();
+    }
+}
+
+impl Iterator for GalacticChunks {
+    type Item = GalacticChunk;
+
+    fn next(&mut self) -> Option<GalacticChunk> {
+        if self.mirrored.is_empty() {
+            let chunk_number = self.chunk_numbers_first_octant.next()?;
+            self.mirror(chunk_number);
+        }
+        self.remaining -= 1;
+        self.mirrored.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
     }
 }
 
+impl ExactSizeIterator for GalacticChunks {}
+
 #[cfg(test)]
 impl PartialEq for GalacticChunk {
     fn eq(&self, other: &Self) -> bool {
@@ -90,30 +209,56 @@ impl From<(f64, f64, f64)> for GalacticChunk {
 #[cfg(test)]
 impl From<&(f64, f64, f64)> for GalacticChunk {
     fn from(corner: &(f64, f64, f64)) -> Self {
-        Self {
-            corner: corner.clone(),
-        }
+        Self { corner: *corner }
     }
 }
 
-fn chunk_numbers_in_first_octant(n: usize) -> Vec<(usize, usize, usize)> {
-    let mut chunks_first_octant = Vec::new();
-    let radius_squared = (n - 1) * (n - 1);
-    for x in 0..n {
-        for y in 0..n {
-            if y * y + x * x > radius_squared {
-                continue;
-            }
-            for z in 0..n {
-                if x * x + y * y + z * z > radius_squared {
-                    continue;
+/// The squared distance of a chunk in the first octant from the origin, followed by its numbers along the three axes.
+type ChunkNumber = (usize, usize, usize, usize);
+
+/// The numbers of the chunks in the first octant within the sphere of radius `n - 1`, sorted by their squared distance from the origin and then by their numbers.
+///
+/// Every column of chunks along the z axis is already sorted, so the columns are merged lazily with a heap holding the next chunk of every column.
+#[derive(Debug, Clone)]
+struct ChunkNumbersInFirstOctant {
+    radius_squared: usize,
+    /// The next chunk of every column that is not exhausted.
+    columns: BinaryHeap<Reverse<ChunkNumber>>,
+}
+
+impl ChunkNumbersInFirstOctant {
+    fn new(n: usize) -> Self {
+        let radius_squared = (n - 1) * (n - 1);
+        let mut columns = BinaryHeap::new();
+        for x in 0..n {
+            for y in 0..n {
+                if x * x + y * y <= radius_squared {
+                    columns.push(Reverse((x * x + y * y, x, y, 0)));
                 }
-                chunks_first_octant.push((x, y, z));
             }
         }
+        ChunkNumbersInFirstOctant {
+            radius_squared,
+            columns,
+        }
+    }
+}
+
+impl Iterator for ChunkNumbersInFirstOctant {
+    type Item = (usize, usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize, usize)> {
+        let mut next = self.columns.peek_mut()?;
+        let Reverse((distance_squared, x, y, z)) = *next;
+        let next_distance_squared = distance_squared + 2 * z + 1;
+        if next_distance_squared <= self.radius_squared {
+            // Replacing the top of the heap only moves it down, which is cheaper than popping and pushing.
+            *next = Reverse((next_distance_squared, x, y, z + 1));
+        } else {
+            PeekMut::pop(next);
+        }
+        Some((x, y, z))
     }
-    chunks_first_octant.sort_by_key(|&(x, y, z)| x * x + y * y + z * z);
-    chunks_first_octant
 }
 
 fn number_of_chunks_along_axis(generation_parameters: &GenerationParameters) -> usize {
@@ -129,22 +274,43 @@ mod test {
     #[test]
     fn test_number_of_chunks_along_axis() {
         let params = GenerationParameters {
-            observer_position_in_pc: (0., 0., 0.),
+            observer_position_in_pc: Some((0., 0., 0.)),
             apparent_magnitude_limit: 0.,
             max_distance_in_pc: 5.,
             chunksize_in_pc: 10.,
+            ..Default::default()
         };
         assert_eq!(number_of_chunks_along_axis(&params), 1);
 
         let params = GenerationParameters {
-            observer_position_in_pc: (0., 0., 0.),
+            observer_position_in_pc: Some((0., 0., 0.)),
             apparent_magnitude_limit: 0.,
             max_distance_in_pc: 55.,
             chunksize_in_pc: 10.,
+            ..Default::default()
         };
         assert_eq!(number_of_chunks_along_axis(&params), 6);
     }
 
+    #[test]
+    fn number_of_chunks_is_counted_without_creating_them() {
+        for max_distance_in_pc in [0.1, 5., 14., 15., 16., 55., 101., 333.] {
+            let params = GenerationParameters {
+                max_distance_in_pc,
+                chunksize_in_pc: 10.,
+                ..Default::default()
+            };
+            assert_eq!(
+                GalacticChunk::number_of_chunks(&params),
+                GalacticChunk::generate_chunks(&params).count() as u64
+            );
+        }
+    }
+
+    fn chunk_numbers_in_first_octant(n: usize) -> Vec<(usize, usize, usize)> {
+        ChunkNumbersInFirstOctant::new(n).collect()
+    }
+
     #[test]
     fn test_chunk_numbers_in_first_octant() {
         assert_eq!(chunk_numbers_in_first_octant(1), vec![(0, 0, 0)]);
@@ -170,18 +336,37 @@ mod test {
         );
     }
 
+    #[test]
+    fn chunk_numbers_are_merged_in_the_order_of_sorting_all_of_them() {
+        let n = 12;
+        let radius_squared = (n - 1) * (n - 1);
+        let mut sorted = Vec::new();
+        for x in 0..n {
+            for y in 0..n {
+                for z in 0..n {
+                    if x * x + y * y + z * z <= radius_squared {
+                        sorted.push((x, y, z));
+                    }
+                }
+            }
+        }
+        sorted.sort_by_key(|&(x, y, z)| x * x + y * y + z * z);
+        assert_eq!(chunk_numbers_in_first_octant(n), sorted);
+    }
+
     #[test]
     fn chunks_with_small_max_generation_distance_contain_origin_chunk() {
         let origin = (10., 20., 30.);
         let params = GenerationParameters {
-            observer_position_in_pc: origin,
+            observer_position_in_pc: Some(origin),
             apparent_magnitude_limit: 0.,
             max_distance_in_pc: 0.1,
             chunksize_in_pc: 10.,
+            ..Default::default()
         };
         let origin_chunk = GalacticChunk::origin_chunk(&params);
 
-        let chunks = GalacticChunk::generate_chunks(&params);
+        let chunks: Vec<GalacticChunk> = GalacticChunk::generate_chunks(&params).collect();
 
         assert_eq!(chunks.len(), 1);
         assert!(chunks[0].eq(&origin_chunk));
@@ -191,12 +376,13 @@ mod test {
     fn chunks_do_not_contain_duplications() {
         for n in 1..10 {
             let params = GenerationParameters {
-                observer_position_in_pc: (0., 0., 0.),
+                observer_position_in_pc: Some((0., 0., 0.)),
                 apparent_magnitude_limit: 0.,
                 max_distance_in_pc: 10. * n as f64,
                 chunksize_in_pc: 10.,
+                ..Default::default()
             };
-            let chunks = GalacticChunk::generate_chunks(&params);
+            let chunks: Vec<GalacticChunk> = GalacticChunk::generate_chunks(&params).collect();
             for (i, chunk) in chunks.iter().enumerate() {
                 for other_chunk in chunks.iter().skip(i + 1) {
                     assert!(!chunk.eq(other_chunk));
@@ -208,10 +394,11 @@ mod test {
     #[test]
     fn chunks_contain_all_expected() {
         let params = GenerationParameters {
-            observer_position_in_pc: (0.5, 0.5, 0.5),
+            observer_position_in_pc: Some((0.5, 0.5, 0.5)),
             apparent_magnitude_limit: 0.,
             max_distance_in_pc: 2.1,
             chunksize_in_pc: 1.,
+            ..Default::default()
         };
         let expected_chunks = vec![
             (0., 0., 0.),
@@ -249,7 +436,8 @@ mod test {
             (-2., 0., 0.),
         ];
 
-        let generated_chunks = GalacticChunk::generate_chunks(&params);
+        let generated_chunks: Vec<GalacticChunk> =
+            GalacticChunk::generate_chunks(&params).collect();
         for chunk in generated_chunks.iter() {
             assert!(
                 expected_chunks.contains(&chunk.corner),
@@ -272,13 +460,14 @@ mod test {
     fn chunks_are_sorted_by_distance() {
         const ACCURACY: f64 = 1.;
         let params = GenerationParameters {
-            observer_position_in_pc: (0., 0., 0.),
+            observer_position_in_pc: Some((0., 0., 0.)),
             apparent_magnitude_limit: 0.,
             max_distance_in_pc: 101.,
             chunksize_in_pc: 10.,
+            ..Default::default()
         };
         let (x0, y0, z0) = GalacticChunk::origin_chunk(&params).corner;
-        let chunks = GalacticChunk::generate_chunks(&params);
+        let chunks: Vec<GalacticChunk> = GalacticChunk::generate_chunks(&params).collect();
         for (i, chunk) in chunks.iter().enumerate() {
             for other_chunk in chunks.iter().skip(i + 1) {
                 let (x1, y1, z1) = chunk.corner;
@@ -292,6 +481,57 @@ mod test {
         }
     }
 
+    #[test]
+    fn every_point_of_a_generated_chunk_is_contained_in_it() {
+        let params = GenerationParameters {
+            observer_position_in_pc: Some((-7., 3., -22.)),
+            max_distance_in_pc: 40.,
+            chunksize_in_pc: 10.,
+            ..Default::default()
+        };
+        for chunk in GalacticChunk::generate_chunks(&params) {
+            let (x, y, z) = chunk.corner;
+            for point in [
+                chunk.center(10.),
+                (x + 0.01, y + 0.01, z + 0.01),
+                (x + 9.99, y + 9.99, z + 9.99),
+            ] {
+                assert_eq!(GalacticChunk::containing(&params, point), chunk);
+            }
+            assert!(chunk.is_generated(&params));
+        }
+        let generated = GalacticChunk::generate_chunks(&params).count();
+        let mut found = 0;
+        for i in -6..6 {
+            for j in -6..6 {
+                for k in -6..6 {
+                    let point = (
+                        -7. + i as f64 * 10.,
+                        3. + j as f64 * 10.,
+                        -22. + k as f64 * 10.,
+                    );
+                    if GalacticChunk::containing(&params, point).is_generated(&params) {
+                        found += 1;
+                    }
+                }
+            }
+        }
+        assert_eq!(found, generated);
+    }
+
+    #[test]
+    fn nearest_distance_is_zero_inside_chunk() {
+        let chunk = GalacticChunk::from((0., 0., 0.));
+        assert_eq!(chunk.nearest_distance_in_pc(10., (5., 5., 5.)), 0.);
+    }
+
+    #[test]
+    fn nearest_distance_is_measured_to_closest_corner() {
+        let chunk = GalacticChunk::from((10., 10., 10.));
+        let distance = chunk.nearest_distance_in_pc(10., (7., 6., 10.));
+        assert!((distance - 5.).abs() < 1e-10);
+    }
+
     #[test]
     #[ignore]
     #[serial]
@@ -299,19 +539,24 @@ mod test {
         const TO_MILKY_WAY_CENTER: f64 = 8200.;
         const MAX_DISTANCE: f64 = TO_MILKY_WAY_CENTER / 3.;
         let params = GenerationParameters {
-            observer_position_in_pc: (10., 20., 30.),
+            observer_position_in_pc: Some((10., 20., 30.)),
             apparent_magnitude_limit: 0.,
             max_distance_in_pc: MAX_DISTANCE,
             chunksize_in_pc: 15.,
+            ..Default::default()
         };
         let start = std::time::Instant::now();
-        let chunks = GalacticChunk::generate_chunks(&params);
+        let mut count = 0;
+        for chunk in GalacticChunk::generate_chunks(&params) {
+            std::hint::black_box(chunk);
+            count += 1;
+        }
         let duration = start.elapsed();
         println!(
             "Generating {} chunks took {:?}, or {:?} per chunk",
-            chunks.len(),
+            count,
             duration,
-            duration / chunks.len() as u32
+            duration / count
         );
         assert!(duration.as_secs_f64() < 1.);
     }
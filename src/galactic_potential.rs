@@ -0,0 +1,338 @@
+use std::f64::consts::PI;
+
+use astro_units::time::gigayear;
+use serde::{Deserialize, Serialize};
+use uom::si::f64::Time;
+
+use crate::{
+    galactocentric_frame::GalactocentricFrame,
+    mass_density::{rho0, SPHEROID_CORE_RADIUS_IN_PC, SPHEROID_POWER_LAW_EXPONENT},
+    population::Population,
+};
+
+/// Newton's gravitational constant, in units of parsec (km/s)^2 per solar mass.
+const GRAVITATIONAL_CONSTANT: f64 = 4.300_917e-3;
+
+/// Mass of the thin disc including its white dwarfs, in units of solar masses.
+///
+/// This is the integral over the density laws of all thin disc age bins.
+const THIN_DISC_MASS_IN_SOLAR_MASSES: f64 = 2.0e10;
+/// Miyamoto-Nagai scale length and scale height of the thin disc, in units of parsec.
+const THIN_DISC_SCALES_IN_PC: (f64, f64) = (3000., 280.);
+/// Mass of the thick disc including its white dwarfs, in units of solar masses.
+///
+/// This is the integral over the density laws of the thick disc.
+const THICK_DISC_MASS_IN_SOLAR_MASSES: f64 = 3.2e9;
+/// Miyamoto-Nagai scale length and scale height of the thick disc, in units of parsec.
+const THICK_DISC_SCALES_IN_PC: (f64, f64) = (4000., 900.);
+/// Dynamical mass of the bulge, in units of solar masses (Bland-Hawthorn & Gerhard 2016).
+const BULGE_MASS_IN_SOLAR_MASSES: f64 = 1.5e10;
+/// Hernquist scale radius of the bulge, in units of parsec.
+const BULGE_SCALE_RADIUS_IN_PC: f64 = 600.;
+/// Scale density and scale radius of the NFW halo, in units of solar masses per cubic parsec and parsec.
+const NFW_HALO: (f64, f64) = (0.0181, 16_000.);
+/// Central density and core radius of the pseudo-isothermal halo, in units of solar masses per cubic parsec and parsec.
+const PSEUDO_ISOTHERMAL_HALO: (f64, f64) = (0.136, 3000.);
+
+/// The density profile of the dark matter halo.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#galactic-potential>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DarkHalo {
+    /// The cuspy profile `ρ = ρ_s / (x (1 + x)^2)` with `x = r / r_s` found in cosmological simulations (Navarro, Frenk & White 1996).
+    #[default]
+    Nfw,
+    /// The cored profile `ρ = ρ_0 / (1 + (r / r_c)^2)`.
+    PseudoIsothermal,
+}
+
+/// The gravitational potential of all stellar populations and the dark matter halo.
+///
+/// Positions are given in the model frame, in units of parsec. The potential is axisymmetric, so the orientation of the frame does not matter.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#galactic-potential>
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GalacticPotential {
+    dark_halo: DarkHalo,
+    /// The radius at which the spheroid density law is normalised, in units of parsec.
+    solar_radius_in_pc: f64,
+    /// The density of the spheroid at the solar radius, in units of solar masses per cubic parsec.
+    spheroid_rho0: f64,
+}
+
+impl GalacticPotential {
+    /// The potential of the given dark halo together with the stellar populations of the galaxy, in the given frame.
+    pub fn new(dark_halo: DarkHalo, frame: &GalactocentricFrame) -> Self {
+        Self {
+            dark_halo,
+            solar_radius_in_pc: frame.solar_radius_in_pc,
+            spheroid_rho0: rho0(Population::Spheroid, Time::new::<gigayear>(0.)),
+        }
+    }
+
+    /// The gravitational potential at the given position, in units of (km/s)^2.
+    ///
+    /// The potential vanishes at infinity, except for the pseudo-isothermal halo, whose potential grows logarithmically and is taken to vanish at the center instead.
+    pub fn potential_in_km2_per_s2(&self, position_in_pc: (f64, f64, f64)) -> f64 {
+        let (x, y, z) = position_in_pc;
+        let radius = x.hypot(y);
+        let r = (x * x + y * y + z * z).sqrt();
+        miyamoto_nagai_potential(
+            THIN_DISC_MASS_IN_SOLAR_MASSES,
+            THIN_DISC_SCALES_IN_PC,
+            radius,
+            z,
+        ) + miyamoto_nagai_potential(
+            THICK_DISC_MASS_IN_SOLAR_MASSES,
+            THICK_DISC_SCALES_IN_PC,
+            radius,
+            z,
+        ) - GRAVITATIONAL_CONSTANT * BULGE_MASS_IN_SOLAR_MASSES / (r + BULGE_SCALE_RADIUS_IN_PC)
+            + self.spheroid_potential(r)
+            + self.halo_potential(r)
+    }
+
+    /// The gravitational acceleration at the given position, in units of (km/s)^2 per parsec.
+    pub fn acceleration(&self, position_in_pc: (f64, f64, f64)) -> (f64, f64, f64) {
+        let (x, y, z) = position_in_pc;
+        let radius = x.hypot(y);
+        let r = (x * x + y * y + z * z).sqrt();
+        let (thin_radial, thin_vertical) = miyamoto_nagai_acceleration(
+            THIN_DISC_MASS_IN_SOLAR_MASSES,
+            THIN_DISC_SCALES_IN_PC,
+            radius,
+            z,
+        );
+        let (thick_radial, thick_vertical) = miyamoto_nagai_acceleration(
+            THICK_DISC_MASS_IN_SOLAR_MASSES,
+            THICK_DISC_SCALES_IN_PC,
+            radius,
+            z,
+        );
+        // Acceleration per unit length towards the center, from the spherical components.
+        let spherical = if r > 0. {
+            GRAVITATIONAL_CONSTANT * self.spherical_enclosed_mass(r) / (r * r * r)
+        } else {
+            0.
+        };
+        // Acceleration per unit cylindrical radius towards the axis, from the discs.
+        let cylindrical = if radius > 0. {
+            -(thin_radial + thick_radial) / radius
+        } else {
+            0.
+        };
+        (
+            -(spherical + cylindrical) * x,
+            -(spherical + cylindrical) * y,
+            -spherical * z + thin_vertical + thick_vertical,
+        )
+    }
+
+    /// The velocity of a circular orbit in the galactic plane at the given galactocentric radius, in units of km/s.
+    pub fn circular_velocity_in_km_per_s(&self, radius_in_pc: f64) -> f64 {
+        let (ax, _, _) = self.acceleration((radius_in_pc, 0., 0.));
+        (-ax * radius_in_pc).max(0.).sqrt()
+    }
+
+    /// The mass of the bulge, spheroid and halo within the given distance from the center, in units of solar masses.
+    fn spherical_enclosed_mass(&self, r: f64) -> f64 {
+        let bulge = BULGE_MASS_IN_SOLAR_MASSES * r * r
+            / ((r + BULGE_SCALE_RADIUS_IN_PC) * (r + BULGE_SCALE_RADIUS_IN_PC));
+        bulge + self.spheroid_enclosed_mass(r) + self.halo_enclosed_mass(r)
+    }
+
+    /// The density of the spheroid at the given distance from the center, treating it as spherical.
+    fn spheroid_density(&self, r: f64) -> f64 {
+        let r = r.max(SPHEROID_CORE_RADIUS_IN_PC);
+        self.spheroid_rho0 * (r / self.solar_radius_in_pc).powf(SPHEROID_POWER_LAW_EXPONENT)
+    }
+
+    fn spheroid_enclosed_mass(&self, r: f64) -> f64 {
+        let core = SPHEROID_CORE_RADIUS_IN_PC;
+        let n = SPHEROID_POWER_LAW_EXPONENT;
+        let core_density = self.spheroid_density(core);
+        if r <= core {
+            4. / 3. * PI * core_density * r * r * r
+        } else {
+            4. / 3. * PI * core_density * core * core * core
+                + 4. * PI * core_density * core.powf(-n) * (r.powf(3. + n) - core.powf(3. + n))
+                    / (3. + n)
+        }
+    }
+
+    /// The potential of a spherical density `Φ(r) = -G M(r) / r - 4 π G ∫_r^∞ ρ(r') r' dr'`.
+    fn spheroid_potential(&self, r: f64) -> f64 {
+        let core = SPHEROID_CORE_RADIUS_IN_PC;
+        let n = SPHEROID_POWER_LAW_EXPONENT;
+        let outer = r.max(core);
+        // The power law falls off faster than r^-2, so the integral converges.
+        let mut integral = -self.spheroid_density(outer) * outer * outer / (2. + n);
+        if r < core {
+            integral += self.spheroid_density(core) * (core * core - r * r) / 2.;
+        }
+        let enclosed = if r > 0. {
+            self.spheroid_enclosed_mass(r) / r
+        } else {
+            0.
+        };
+        -GRAVITATIONAL_CONSTANT * (enclosed + 4. * PI * integral)
+    }
+
+    fn halo_enclosed_mass(&self, r: f64) -> f64 {
+        match self.dark_halo {
+            DarkHalo::Nfw => {
+                let (density, scale) = NFW_HALO;
+                let x = r / scale;
+                4. * PI * density * scale.powi(3) * ((1. + x).ln() - x / (1. + x))
+            }
+            DarkHalo::PseudoIsothermal => {
+                let (density, core) = PSEUDO_ISOTHERMAL_HALO;
+                4. * PI * density * core * core * (r - core * (r / core).atan())
+            }
+        }
+    }
+
+    fn halo_potential(&self, r: f64) -> f64 {
+        match self.dark_halo {
+            DarkHalo::Nfw => {
+                let (density, scale) = NFW_HALO;
+                let prefactor = -4. * PI * GRAVITATIONAL_CONSTANT * density * scale.powi(3);
+                if r > 0. {
+                    prefactor * (1. + r / scale).ln() / r
+                } else {
+                    prefactor / scale
+                }
+            }
+            DarkHalo::PseudoIsothermal => {
+                let (density, core) = PSEUDO_ISOTHERMAL_HALO;
+                let x = r / core;
+                let shape = if x > 0. { x.atan() / x } else { 1. };
+                4. * PI
+                    * GRAVITATIONAL_CONSTANT
+                    * density
+                    * core
+                    * core
+                    * (shape + 0.5 * (1. + x * x).ln() - 1.)
+            }
+        }
+    }
+}
+
+/// The potential `Φ = -G M / sqrt(R^2 + (a + sqrt(z^2 + b^2))^2)` of a Miyamoto-Nagai disc, in units of (km/s)^2.
+fn miyamoto_nagai_potential(mass: f64, scales: (f64, f64), radius: f64, z: f64) -> f64 {
+    let (a, b) = scales;
+    let zeta = (z * z + b * b).sqrt();
+    -GRAVITATIONAL_CONSTANT * mass / (radius * radius + (a + zeta) * (a + zeta)).sqrt()
+}
+
+/// The radial and vertical acceleration of a Miyamoto-Nagai disc, in units of (km/s)^2 per parsec.
+fn miyamoto_nagai_acceleration(mass: f64, scales: (f64, f64), radius: f64, z: f64) -> (f64, f64) {
+    let (a, b) = scales;
+    let zeta = (z * z + b * b).sqrt();
+    let distance_cubed = (radius * radius + (a + zeta) * (a + zeta)).powf(1.5);
+    let factor = -GRAVITATIONAL_CONSTANT * mass / distance_cubed;
+    (factor * radius, factor * z * (a + zeta) / zeta)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assert_diff, assert_ratio,
+        galactocentric_frame::DEFAULT_SOLAR_RADIUS_IN_PC,
+        mass_density::mass_density,
+        population::{AgeBin, Population},
+    };
+
+    use super::*;
+
+    /// Circular velocity at the sun, in units of km/s (Bland-Hawthorn & Gerhard 2016 give 238 ± 15).
+    const OBSERVED_CIRCULAR_VELOCITY_IN_KM_PER_S: f64 = 230.;
+
+    fn potential(dark_halo: DarkHalo) -> GalacticPotential {
+        GalacticPotential::new(dark_halo, &GalactocentricFrame::default())
+    }
+
+    #[test]
+    fn circular_velocity_at_the_sun_matches_the_observed_one() {
+        for dark_halo in [DarkHalo::Nfw, DarkHalo::PseudoIsothermal] {
+            let velocity =
+                potential(dark_halo).circular_velocity_in_km_per_s(DEFAULT_SOLAR_RADIUS_IN_PC);
+            assert_ratio!(OBSERVED_CIRCULAR_VELOCITY_IN_KM_PER_S, velocity, 0.02);
+        }
+    }
+
+    #[test]
+    fn rotation_curve_is_nearly_flat_in_the_disc() {
+        for dark_halo in [DarkHalo::Nfw, DarkHalo::PseudoIsothermal] {
+            let potential = potential(dark_halo);
+            let at_sun = potential.circular_velocity_in_km_per_s(DEFAULT_SOLAR_RADIUS_IN_PC);
+            for radius in [5000., 12_000., 20_000.] {
+                assert_ratio!(at_sun, potential.circular_velocity_in_km_per_s(radius), 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn acceleration_is_the_negative_gradient_of_the_potential() {
+        const STEP: f64 = 1e-3;
+        for dark_halo in [DarkHalo::Nfw, DarkHalo::PseudoIsothermal] {
+            let potential = potential(dark_halo);
+            for position in [
+                (300., 100., 50.),
+                (8200., 0., 20.8),
+                (-5000., 12_000., -3000.),
+            ] {
+                let (x, y, z) = position;
+                let gradient = (
+                    (potential.potential_in_km2_per_s2((x + STEP, y, z))
+                        - potential.potential_in_km2_per_s2((x - STEP, y, z)))
+                        / (2. * STEP),
+                    (potential.potential_in_km2_per_s2((x, y + STEP, z))
+                        - potential.potential_in_km2_per_s2((x, y - STEP, z)))
+                        / (2. * STEP),
+                    (potential.potential_in_km2_per_s2((x, y, z + STEP))
+                        - potential.potential_in_km2_per_s2((x, y, z - STEP)))
+                        / (2. * STEP),
+                );
+                let acceleration = potential.acceleration(position);
+                assert_diff!(-gradient.0, acceleration.0, 1e-6);
+                assert_diff!(-gradient.1, acceleration.1, 1e-6);
+                assert_diff!(-gradient.2, acceleration.2, 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn disc_masses_match_the_density_laws() {
+        let frame = GalactocentricFrame::default();
+        let integrate = |is_included: fn(Population) -> bool| {
+            let mut mass = 0.;
+            for bin in AgeBin::all()
+                .into_iter()
+                .filter(|bin| is_included(bin.population))
+            {
+                for i in 0..300 {
+                    let radius = (i as f64 + 0.5) * 100.;
+                    let mut z = 0.;
+                    let mut dz = 5.;
+                    while z < 20_000. {
+                        let density = mass_density(
+                            bin.population,
+                            bin.representative_age(),
+                            (radius, 0., z + dz / 2.),
+                            &frame,
+                        );
+                        mass += 2. * density * dz * 2. * PI * radius * 100.;
+                        z += dz;
+                        dz *= 1.05;
+                    }
+                }
+            }
+            mass
+        };
+        let thin = integrate(|population| matches!(population, Population::ThinDisc(_)));
+        let thick = integrate(|population| matches!(population, Population::ThickDisc(_)));
+        assert_ratio!(THIN_DISC_MASS_IN_SOLAR_MASSES, thin, 0.05);
+        assert_ratio!(THICK_DISC_MASS_IN_SOLAR_MASSES, thick, 0.05);
+    }
+}
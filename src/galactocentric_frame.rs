@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+/// Distance of the sun from the galactic center, in units of parsec.
+pub(crate) const DEFAULT_SOLAR_RADIUS_IN_PC: f64 = 8200.;
+/// Height of the sun above the galactic midplane, in units of parsec (Bennett & Bovy 2019).
+const DEFAULT_SOLAR_HEIGHT_IN_PC: f64 = 20.8;
+
+/// The direction of the x-axis of the galactocentric frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum XAxisDirection {
+    /// The x-axis points from the galactic center towards the sun.
+    TowardsSun,
+    /// The x-axis points from the sun towards the galactic center, so the sun lies at negative x.
+    AwayFromSun,
+}
+
+/// Whether the axes of the galactocentric frame form a right- or a left-handed coordinate system, which fixes the direction of the y-axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Handedness {
+    /// The axes form a right-handed coordinate system.
+    Right,
+    /// The axes form a left-handed coordinate system.
+    Left,
+}
+
+/// The galactocentric frame in which all positions are given.
+///
+/// The origin is the galactic center and the z-axis points towards the north galactic pole, perpendicular to the galactic midplane.
+/// The sun lies in the x-z plane.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#galactocentric-frame>
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GalactocentricFrame {
+    /// Distance of the sun from the galactic center, projected onto the midplane, in units of parsec.
+    pub solar_radius_in_pc: f64,
+    /// Height of the sun above the galactic midplane, in units of parsec.
+    pub solar_height_in_pc: f64,
+    /// The direction of the x-axis relative to the sun.
+    pub x_axis: XAxisDirection,
+    /// The handedness of the frame.
+    pub handedness: Handedness,
+}
+
+impl Default for GalactocentricFrame {
+    fn default() -> Self {
+        Self {
+            solar_radius_in_pc: DEFAULT_SOLAR_RADIUS_IN_PC,
+            solar_height_in_pc: DEFAULT_SOLAR_HEIGHT_IN_PC,
+            x_axis: XAxisDirection::TowardsSun,
+            handedness: Handedness::Right,
+        }
+    }
+}
+
+impl GalactocentricFrame {
+    /// The position of the sun in this frame, in units of parsec.
+    pub fn solar_position_in_pc(&self) -> (f64, f64, f64) {
+        self.vector_from_model_frame((self.solar_radius_in_pc, 0., self.solar_height_in_pc))
+    }
+
+    /// Converts a position or velocity into the frame the density laws are formulated in.
+    ///
+    /// In this model frame the sun lies on the positive x-axis and the y-axis points in the direction of galactic rotation at the sun, i.e. towards `l = 90°`.
+    /// Note that this makes the model frame left-handed.
+    pub(crate) fn model_frame_vector(&self, vector: (f64, f64, f64)) -> (f64, f64, f64) {
+        let (x_sign, y_sign) = self.axis_signs();
+        (x_sign * vector.0, y_sign * vector.1, vector.2)
+    }
+
+    /// Converts a position or velocity from the model frame into this frame.
+    pub(crate) fn vector_from_model_frame(&self, vector: (f64, f64, f64)) -> (f64, f64, f64) {
+        // Both conversions only flip the signs of axes, so they are their own inverse.
+        self.model_frame_vector(vector)
+    }
+
+    /// Unit vectors in the model frame pointing towards `l = 0°`, `l = 90°` and the north galactic pole.
+    ///
+    /// The galactic plane is tilted against the midplane, such that the sun sees the galactic center at `b = 0°`.
+    pub(crate) fn galactic_axes(&self) -> [[f64; 3]; 3] {
+        let distance = self.solar_radius_in_pc.hypot(self.solar_height_in_pc);
+        let cos = self.solar_radius_in_pc / distance;
+        let sin = self.solar_height_in_pc / distance;
+        [[-cos, 0., -sin], [0., 1., 0.], [-sin, 0., cos]]
+    }
+
+    fn axis_signs(&self) -> (f64, f64) {
+        match (self.x_axis, self.handedness) {
+            (XAxisDirection::TowardsSun, Handedness::Right) => (1., -1.),
+            (XAxisDirection::TowardsSun, Handedness::Left) => (1., 1.),
+            (XAxisDirection::AwayFromSun, Handedness::Right) => (-1., 1.),
+            (XAxisDirection::AwayFromSun, Handedness::Left) => (-1., -1.),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_diff;
+
+    use super::*;
+
+    fn all_frames() -> Vec<GalactocentricFrame> {
+        let mut frames = Vec::new();
+        for x_axis in [XAxisDirection::TowardsSun, XAxisDirection::AwayFromSun] {
+            for handedness in [Handedness::Right, Handedness::Left] {
+                frames.push(GalactocentricFrame {
+                    x_axis,
+                    handedness,
+                    ..Default::default()
+                });
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn sun_lies_on_the_positive_x_axis_of_the_model_frame() {
+        for frame in all_frames() {
+            let (x, y, z) = frame.model_frame_vector(frame.solar_position_in_pc());
+            assert_diff!(DEFAULT_SOLAR_RADIUS_IN_PC, x, 1e-10);
+            assert_diff!(0., y, 1e-10);
+            assert_diff!(DEFAULT_SOLAR_HEIGHT_IN_PC, z, 1e-10);
+        }
+    }
+
+    #[test]
+    fn sun_lies_on_the_negative_x_axis_when_it_points_away_from_it() {
+        let frame = GalactocentricFrame {
+            x_axis: XAxisDirection::AwayFromSun,
+            ..Default::default()
+        };
+        assert!(frame.solar_position_in_pc().0 < 0.);
+    }
+
+    #[test]
+    fn right_handed_frames_satisfy_the_right_hand_rule() {
+        for frame in all_frames() {
+            // The model frame is left-handed, so a right-handed frame has to flip an odd number of axes.
+            let (x, y, _) = frame.model_frame_vector((1., 1., 1.));
+            let flips = [x, y].iter().filter(|&&sign| sign < 0.).count();
+            assert_eq!(frame.handedness == Handedness::Right, flips % 2 == 1);
+        }
+    }
+
+    #[test]
+    fn galactic_axes_are_orthonormal() {
+        let axes = GalactocentricFrame::default().galactic_axes();
+        for (i, a) in axes.iter().enumerate() {
+            for (j, b) in axes.iter().enumerate() {
+                let dot: f64 = a.iter().zip(b.iter()).map(|(a, b)| a * b).sum();
+                assert_diff!(if i == j { 1. } else { 0. }, dot, 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn conversion_round_trips() {
+        let position = (100., -2000., 30.);
+        for frame in all_frames() {
+            assert_eq!(
+                position,
+                frame.vector_from_model_frame(frame.model_frame_vector(position))
+            );
+        }
+    }
+}
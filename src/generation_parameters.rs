@@ -1,12 +1,385 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    coordinates::SkyFrame, epoch::Epoch, extinction::ExtinctionModel, galactic_potential::DarkHalo,
+    galactocentric_frame::GalactocentricFrame, photometry::Band,
+};
+
 /// Parameters for generating a star catalogue.
 ///
-/// Compare https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#generation-parameters
-#[derive(Debug, Serialize, Deserialize)]
-pub(crate) struct GenerationParameters {
-    pub(crate) observer_position_in_pc: (f64, f64, f64),
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#generation-parameters>
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerationParameters {
+    /// The position of the observer in the galactocentric frame. If none is given, the observer is at the position of the sun.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) observer_position_in_pc: Option<(f64, f64, f64)>,
+    /// The conventions of the frame in which all positions are given.
+    #[serde(default)]
+    pub(crate) galactocentric_frame: GalactocentricFrame,
     pub(crate) apparent_magnitude_limit: f64,
+    /// The band the apparent magnitude limit applies to. If none is given, it applies to the bolometric magnitude.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) magnitude_limit_band: Option<Band>,
     pub(crate) max_distance_in_pc: f64,
     pub(crate) chunksize_in_pc: f64,
+    #[serde(default)]
+    pub(crate) seed: u64,
+    /// Only sample stars that are heavy enough to possibly exceed the magnitude limit.
+    #[serde(default)]
+    pub(crate) importance_sampling: bool,
+    /// A file with white dwarf cooling tracks to use instead of the ones shipped with the crate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) white_dwarf_cooling_table: Option<PathBuf>,
+    /// A file with bolometric corrections to use instead of the ones shipped with the crate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) bolometric_correction_table: Option<PathBuf>,
+    /// The dust model used to dim stars along their line of sight. Without one, there is no extinction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) extinction: Option<ExtinctionModel>,
+    /// Attach a display colour to every generated star.
+    #[serde(default)]
+    pub(crate) include_colour: bool,
+    /// The celestial frames in which the sky coordinates of every star are included in the output.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) sky_frames: Vec<SkyFrame>,
+    /// The dark matter halo of the galactic potential in which stars orbit.
+    #[serde(default)]
+    pub(crate) dark_halo: DarkHalo,
+    /// The time at which the positions of the stars are given. Defaults to the present.
+    #[serde(default, skip_serializing_if = "Epoch::is_present")]
+    pub(crate) epoch: Epoch,
+    /// Give stars companions, following the statistics of binary systems.
+    #[serde(default)]
+    pub(crate) include_companions: bool,
+    /// Companions closer to their primary than this angular separation merge with it into one point source. Without it, all companions are resolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) resolution_in_arcsec: Option<f64>,
+    /// Classify stars in instability regions of the HR diagram and eclipsing binaries as variable stars.
+    #[serde(default)]
+    pub(crate) include_variability: bool,
+    /// Place part of the young thin disc stars in open clusters and OB associations.
+    #[serde(default)]
+    pub(crate) include_clusters: bool,
+    /// Place part of the spheroid stars in globular clusters, which are resolved into stars or seen as single objects depending on the magnitude limit.
+    #[serde(default)]
+    pub(crate) include_globular_clusters: bool,
+}
+
+impl GenerationParameters {
+    /// Starts building parameters from the values every catalogue needs, with all optional features disabled.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#generation-parameters>
+    pub fn builder(
+        observer_position_in_pc: (f64, f64, f64),
+        apparent_magnitude_limit: f64,
+        max_distance_in_pc: f64,
+        chunksize_in_pc: f64,
+    ) -> GenerationParametersBuilder {
+        GenerationParametersBuilder {
+            parameters: GenerationParameters {
+                observer_position_in_pc: Some(observer_position_in_pc),
+                apparent_magnitude_limit,
+                max_distance_in_pc,
+                chunksize_in_pc,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Checks that the parameters describe a catalogue that can be generated.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#generation-parameters>
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        let (x, y, z) = self.observer_position_in_pc();
+        if ![x, y, z].iter().all(|c| c.is_finite()) {
+            return Err("The observer position has to be finite".into());
+        }
+        if !self.apparent_magnitude_limit.is_finite() {
+            return Err("The apparent magnitude limit has to be finite".into());
+        }
+        if !(self.max_distance_in_pc.is_finite() && self.max_distance_in_pc > 0.) {
+            return Err("The maximal distance has to be positive".into());
+        }
+        if !(self.chunksize_in_pc.is_finite() && self.chunksize_in_pc > 0.) {
+            return Err("The chunk size has to be positive".into());
+        }
+        if let Some(resolution) = self.resolution_in_arcsec {
+            if !(resolution.is_finite() && resolution >= 0.) {
+                return Err("The resolution must not be negative".into());
+            }
+        }
+        Ok(())
+    }
+
+    /// The position of the observer in the galactocentric frame, which defaults to the position of the sun.
+    pub fn observer_position_in_pc(&self) -> (f64, f64, f64) {
+        self.observer_position_in_pc
+            .unwrap_or_else(|| self.galactocentric_frame.solar_position_in_pc())
+    }
+
+    /// The conventions of the frame in which all positions are given.
+    pub fn galactocentric_frame(&self) -> GalactocentricFrame {
+        self.galactocentric_frame
+    }
+
+    /// The apparent magnitude below which stars are included in the catalogue.
+    pub fn apparent_magnitude_limit(&self) -> f64 {
+        self.apparent_magnitude_limit
+    }
+
+    /// The band the apparent magnitude limit applies to, or none for the bolometric magnitude.
+    pub fn magnitude_limit_band(&self) -> Option<Band> {
+        self.magnitude_limit_band
+    }
+
+    /// The distance from the observer up to which stars are generated, in units of parsec.
+    pub fn max_distance_in_pc(&self) -> f64 {
+        self.max_distance_in_pc
+    }
+
+    /// The edge length of the cubic chunks the generated volume is split into, in units of parsec.
+    pub fn chunksize_in_pc(&self) -> f64 {
+        self.chunksize_in_pc
+    }
+
+    /// The seed of the random number generator, which makes catalogues reproducible.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Whether only stars heavy enough to possibly exceed the magnitude limit are sampled.
+    pub fn importance_sampling(&self) -> bool {
+        self.importance_sampling
+    }
+
+    /// The white dwarf cooling table used instead of the shipped one, if any.
+    pub fn white_dwarf_cooling_table(&self) -> Option<&Path> {
+        self.white_dwarf_cooling_table.as_deref()
+    }
+
+    /// The bolometric correction table used instead of the shipped synthetic placeholder, if any.
+    pub fn bolometric_correction_table(&self) -> Option<&Path> {
+        self.bolometric_correction_table.as_deref()
+    }
+
+    /// The dust model, or none if there is no extinction.
+    pub fn extinction(&self) -> Option<&ExtinctionModel> {
+        self.extinction.as_ref()
+    }
+
+    /// Whether a display colour is attached to every generated star.
+    pub fn include_colour(&self) -> bool {
+        self.include_colour
+    }
+
+    /// The celestial frames in which the sky coordinates of every star are included in the output.
+    pub fn sky_frames(&self) -> &[SkyFrame] {
+        &self.sky_frames
+    }
+
+    /// The dark matter halo of the galactic potential in which stars orbit.
+    pub fn dark_halo(&self) -> DarkHalo {
+        self.dark_halo
+    }
+
+    /// The time at which the positions of the stars are given.
+    pub fn epoch(&self) -> Epoch {
+        self.epoch
+    }
+
+    /// Whether stars are given companions.
+    pub fn include_companions(&self) -> bool {
+        self.include_companions
+    }
+
+    /// The angular separation below which companions merge with their primary, or none if all are resolved.
+    pub fn resolution_in_arcsec(&self) -> Option<f64> {
+        self.resolution_in_arcsec
+    }
+
+    /// Whether stars are classified as variable stars.
+    pub fn include_variability(&self) -> bool {
+        self.include_variability
+    }
+
+    /// Whether part of the young thin disc stars are placed in open clusters and OB associations.
+    pub fn include_clusters(&self) -> bool {
+        self.include_clusters
+    }
+
+    /// Whether part of the spheroid stars are placed in globular clusters.
+    pub fn include_globular_clusters(&self) -> bool {
+        self.include_globular_clusters
+    }
+}
+
+/// Sets the optional generation parameters one by one, and checks all of them at the end.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#generation-parameters>
+#[derive(Debug, Clone)]
+pub struct GenerationParametersBuilder {
+    parameters: GenerationParameters,
+}
+
+impl GenerationParametersBuilder {
+    /// Sets the conventions of the frame in which all positions are given. Defaults to a right-handed frame with the x-axis pointing towards the sun.
+    pub fn galactocentric_frame(mut self, frame: GalactocentricFrame) -> Self {
+        self.parameters.galactocentric_frame = frame;
+        self
+    }
+
+    /// Applies the apparent magnitude limit to the given band instead of the bolometric magnitude.
+    pub fn magnitude_limit_band(mut self, band: Band) -> Self {
+        self.parameters.magnitude_limit_band = Some(band);
+        self
+    }
+
+    /// Sets the seed of the random number generator. Defaults to 0.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.parameters.seed = seed;
+        self
+    }
+
+    /// Only samples stars that are heavy enough to possibly exceed the magnitude limit.
+    pub fn importance_sampling(mut self, enabled: bool) -> Self {
+        self.parameters.importance_sampling = enabled;
+        self
+    }
+
+    /// Reads the white dwarf cooling tracks from the given file instead of using the shipped ones.
+    pub fn white_dwarf_cooling_table(mut self, path: PathBuf) -> Self {
+        self.parameters.white_dwarf_cooling_table = Some(path);
+        self
+    }
+
+    /// Reads the bolometric corrections from the given file instead of using the shipped synthetic placeholder.
+    pub fn bolometric_correction_table(mut self, path: PathBuf) -> Self {
+        self.parameters.bolometric_correction_table = Some(path);
+        self
+    }
+
+    /// Dims stars along their line of sight with the given dust model.
+    pub fn extinction(mut self, model: ExtinctionModel) -> Self {
+        self.parameters.extinction = Some(model);
+        self
+    }
+
+    /// Attaches a display colour to every generated star.
+    pub fn include_colour(mut self, enabled: bool) -> Self {
+        self.parameters.include_colour = enabled;
+        self
+    }
+
+    /// Includes the sky coordinates of every star in the given celestial frames.
+    pub fn sky_frames(mut self, frames: Vec<SkyFrame>) -> Self {
+        self.parameters.sky_frames = frames;
+        self
+    }
+
+    /// Sets the dark matter halo of the galactic potential in which stars orbit.
+    pub fn dark_halo(mut self, halo: DarkHalo) -> Self {
+        self.parameters.dark_halo = halo;
+        self
+    }
+
+    /// Gives the positions of the stars at the given time instead of the present.
+    pub fn epoch(mut self, epoch: Epoch) -> Self {
+        self.parameters.epoch = epoch;
+        self
+    }
+
+    /// Gives stars companions, following the statistics of binary systems.
+    pub fn include_companions(mut self, enabled: bool) -> Self {
+        self.parameters.include_companions = enabled;
+        self
+    }
+
+    /// Merges companions closer to their primary than the given angular separation, in units of arcseconds, into one point source.
+    pub fn resolution_in_arcsec(mut self, resolution: f64) -> Self {
+        self.parameters.resolution_in_arcsec = Some(resolution);
+        self
+    }
+
+    /// Classifies stars in instability regions of the HR diagram and eclipsing binaries as variable stars.
+    pub fn include_variability(mut self, enabled: bool) -> Self {
+        self.parameters.include_variability = enabled;
+        self
+    }
+
+    /// Places part of the young thin disc stars in open clusters and OB associations.
+    pub fn include_clusters(mut self, enabled: bool) -> Self {
+        self.parameters.include_clusters = enabled;
+        self
+    }
+
+    /// Places part of the spheroid stars in globular clusters.
+    pub fn include_globular_clusters(mut self, enabled: bool) -> Self {
+        self.parameters.include_globular_clusters = enabled;
+        self
+    }
+
+    /// The parameters, if they describe a catalogue that can be generated.
+    pub fn build(self) -> Result<GenerationParameters, Box<dyn Error>> {
+        self.parameters.validate()?;
+        Ok(self.parameters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_sets_the_given_parameters_and_validates_them() {
+        let params = GenerationParameters::builder((8200., 0., 25.), 6., 100., 15.)
+            .seed(7)
+            .magnitude_limit_band(Band::GaiaG)
+            .include_companions(true)
+            .build()
+            .expect("The parameters should be valid");
+        assert_eq!(params.observer_position_in_pc(), (8200., 0., 25.));
+        assert_eq!(params.apparent_magnitude_limit(), 6.);
+        assert_eq!(params.max_distance_in_pc(), 100.);
+        assert_eq!(params.chunksize_in_pc(), 15.);
+        assert_eq!(params.seed(), 7);
+        assert_eq!(params.magnitude_limit_band(), Some(Band::GaiaG));
+        assert!(params.include_companions());
+        assert!(!params.include_variability());
+        assert!(params.extinction().is_none());
+
+        assert!(
+            GenerationParameters::builder((8200., 0., 25.), 6., 100., 0.)
+                .build()
+                .is_err()
+        );
+        assert!(
+            GenerationParameters::builder((8200., 0., 25.), 6., 100., 15.)
+                .resolution_in_arcsec(-1.)
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn observer_defaults_to_the_sun_of_the_configured_frame() {
+        let params: GenerationParameters = serde_json::from_str(
+            r#"{
+                "galactocentric_frame": { "solar_radius_in_pc": 8000, "x_axis": "AwayFromSun" },
+                "apparent_magnitude_limit": 6,
+                "max_distance_in_pc": 100,
+                "chunksize_in_pc": 15
+            }"#,
+        )
+        .expect("The parameters should be readable");
+        assert_eq!(
+            params.observer_position_in_pc(),
+            params.galactocentric_frame().solar_position_in_pc()
+        );
+        assert_eq!(params.observer_position_in_pc().0, -8000.);
+    }
 }
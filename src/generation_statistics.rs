@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// Counters describing how many stars were discarded at which stage of the generation.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#algorithm>
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationStatistics {
+    /// Number of chunks that were generated completely.
+    pub chunks_processed: usize,
+    /// Number of combinations of chunk and age bin that were skipped entirely, because not even their brightest possible star could reach the magnitude limit.
+    pub chunk_age_bins_culled: usize,
+    /// Expected number of stars in the skipped combinations. These stars are never sampled.
+    pub expected_stars_culled_with_chunks: f64,
+    /// Expected number of stars that were not sampled because their initial mass is too low to ever reach the magnitude limit.
+    ///
+    /// This is only non-zero when importance sampling is enabled.
+    pub expected_stars_below_visible_mass: f64,
+    /// Number of stars that were sampled, before any of them were culled.
+    pub stars_sampled: u64,
+    /// Number of sampled stars that turned out to be fainter than the magnitude limit.
+    pub stars_culled_individually: u64,
+    /// Number of sampled white dwarfs that were dropped, because no progenitor that has died by the sampled age was drawn within the allowed number of attempts.
+    #[serde(default)]
+    pub white_dwarf_progenitors_not_found: u64,
+    /// Number of stars that were written to the catalogue, not counting companions.
+    pub stars_kept: u64,
+    /// Number of companions of the kept stars.
+    #[serde(default)]
+    pub companions_kept: u64,
+    /// Number of sampled open clusters and OB associations.
+    #[serde(default)]
+    pub clusters_sampled: u64,
+    /// Number of sampled clusters that were skipped entirely, because neither their brightest possible member nor their integrated light could reach the magnitude limit.
+    #[serde(default)]
+    pub clusters_culled: u64,
+    /// Number of globular clusters that are too distant to be resolved, but bright enough to be kept as a single object.
+    #[serde(default)]
+    pub unresolved_clusters_kept: u64,
+}
@@ -0,0 +1,1940 @@
+use std::error::Error;
+
+use astro_units::time::gigayear;
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, Poisson};
+use uom::si::f64::Time;
+
+use crate::{
+    colour::{colour_from_xyz, Colour, ColourMatchingFunctions},
+    coordinates::SkyCoordinates,
+    epoch::Epoch,
+    extinction::{redden, Extinction},
+    galactic_chunk::{GalacticChunk, GalacticChunks},
+    galactic_potential::GalacticPotential,
+    galactocentric_frame::GalactocentricFrame,
+    generation_parameters::GenerationParameters,
+    generation_statistics::GenerationStatistics,
+    initial_mass_function::InitialMassFunction,
+    kinematics::{sample_velocity, solar_velocity_in_km_per_s, velocity_ellipsoid, Astrometry},
+    luminosity_limit::luminosity_limit,
+    mass_density::number_density,
+    metallicity::sample_metallicity,
+    multiplicity::{sample_companion, Orbit},
+    photometry::{distance_modulus, Band, BolometricCorrectionTable, Magnitudes},
+    population::{AgeBin, Population},
+    star::Star,
+    star_cluster::{
+        ClusterKind, StarCluster, UnresolvedCluster, CLUSTERED_FRACTION, GLOBULAR_CLUSTER_FRACTION,
+    },
+    stellar_evolution::{evolve, heaviest_living_mass, lifetime_in_gyr, max_luminosity},
+    variability::classify,
+    white_dwarf::{
+        luminosity_grid, mean_white_dwarf_mass, sample_progenitor, white_dwarf_mass, CoolingTable,
+    },
+};
+
+/// Number of bisection steps used to find the minimal visible mass.
+const MASS_BISECTION_STEPS: usize = 50;
+/// Number of logarithmic mass steps used to integrate the light of an unresolved cluster over the IMF.
+///
+/// The steps need to be fine enough to resolve the short giant phase just above the turnoff mass.
+const LIGHT_INTEGRATION_STEPS: usize = 2000;
+/// Number of logarithmic initial mass steps used to integrate the luminosity distribution of an age bin.
+const LUMINOSITY_MASS_STEPS: usize = 4000;
+/// Number of age steps used to integrate the luminosity distribution of an age bin.
+const LUMINOSITY_AGE_STEPS: usize = 20;
+
+/// The stars of a catalogue, generated lazily chunk by chunk in the same order as they are written to a catalogue.
+///
+/// Only one chunk is held in memory at a time. Unresolved globular clusters are not stars, and are collected separately instead.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#algorithm>
+pub struct Stars<'a> {
+    generator: Generator<'a>,
+    chunks: GalacticChunks,
+    total_chunks: usize,
+    next_chunk: usize,
+    current: std::vec::IntoIter<Star>,
+    unresolved_clusters: Vec<UnresolvedCluster>,
+    statistics: GenerationStatistics,
+}
+
+impl<'a> Stars<'a> {
+    /// Prepares the generation, which fails if the parameters are invalid or a table they refer to cannot be read.
+    pub fn new(generation_parameters: &'a GenerationParameters) -> Result<Self, Box<dyn Error>> {
+        Ok(Stars {
+            generator: Generator::new(generation_parameters)?,
+            chunks: GalacticChunk::generate_chunks(generation_parameters),
+            total_chunks: GalacticChunk::number_of_chunks(generation_parameters) as usize,
+            next_chunk: 0,
+            current: Vec::new().into_iter(),
+            unresolved_clusters: Vec::new(),
+            statistics: GenerationStatistics::default(),
+        })
+    }
+
+    /// The position of the observer at the epoch of the catalogue, from which magnitudes and sky coordinates are computed, in units of parsec.
+    pub fn observer_position_in_pc(&self) -> (f64, f64, f64) {
+        self.generator.observer_position_in_pc()
+    }
+
+    /// The statistics of the chunks generated so far.
+    pub fn statistics(&self) -> &GenerationStatistics {
+        &self.statistics
+    }
+
+    /// The globular clusters too distant to be resolved into stars in the chunks generated so far, in the order in which they are written to a catalogue.
+    ///
+    /// There are at most a few hundred of them in the whole galaxy, so they are kept rather than dropped after every chunk.
+    pub fn unresolved_clusters(&self) -> &[UnresolvedCluster] {
+        &self.unresolved_clusters
+    }
+
+    /// The number of chunks that have been generated completely.
+    pub fn chunks_completed(&self) -> usize {
+        self.next_chunk
+    }
+
+    /// The number of chunks the generated volume is split into.
+    pub fn total_chunks(&self) -> usize {
+        self.total_chunks
+    }
+}
+
+impl Iterator for Stars<'_> {
+    type Item = Star;
+
+    fn next(&mut self) -> Option<Star> {
+        loop {
+            if let Some(star) = self.current.next() {
+                return Some(star);
+            }
+            let chunk = self.chunks.next()?;
+            let generated =
+                self.generator
+                    .generate_chunk(self.next_chunk, &chunk, &mut self.statistics);
+            self.next_chunk += 1;
+            self.current = generated.stars.into_iter();
+            self.unresolved_clusters
+                .extend(generated.unresolved_clusters);
+        }
+    }
+}
+
+/// Generates the stars inside chunks of the galaxy.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#algorithm>
+pub(crate) struct Generator<'a> {
+    generation_parameters: &'a GenerationParameters,
+    cooling_table: CoolingTable,
+    bolometric_corrections: BolometricCorrectionTable,
+    colour_matching_functions: ColourMatchingFunctions,
+    extinction: Option<Extinction>,
+    /// The potential in which stars orbit when they are propagated to the epoch of the catalogue.
+    potential: GalacticPotential,
+    /// The position of the observer at the epoch of the catalogue, in units of parsec.
+    observer_position_in_pc: (f64, f64, f64),
+    /// The velocity of the observer in the model frame at the epoch of the catalogue, in units of km/s.
+    observer_velocity_in_km_per_s: (f64, f64, f64),
+    /// The bolometric magnitude limit used to cull chunks and to choose the minimal sampled mass.
+    ///
+    /// If the magnitude limit applies to a band, it is loosened by the largest bolometric correction of that band.
+    culling_magnitude_limit: f64,
+    components: Vec<Component>,
+}
+
+/// The objects generated inside one chunk.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct GeneratedChunk {
+    pub(crate) stars: Vec<Star>,
+    /// Globular clusters too distant to be resolved into stars, seen as single objects.
+    pub(crate) unresolved_clusters: Vec<UnresolvedCluster>,
+}
+
+/// The expected number of stars of one age bin in some volume, known without sampling them.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#dry-run>
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ExpectedStars {
+    pub(crate) population: Population,
+    /// All stars, before the magnitude cut.
+    pub(crate) all: f64,
+    /// The stars that are sampled, which are fewer than all stars if the age bin is culled or importance sampling is enabled.
+    pub(crate) sampled: f64,
+    /// The stars that are brighter than the magnitude limit, neglecting extinction and bolometric corrections.
+    pub(crate) visible: f64,
+}
+
+/// The fraction of the stars of an age bin that are brighter than a luminosity.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#dry-run>
+pub(crate) struct LuminosityDistribution {
+    /// Luminosities on a grid over initial mass and age, sorted in ascending order, in units of solar luminosities.
+    luminosities: Vec<f64>,
+    /// The fraction of the stars that are at least as bright as the luminosity with the same index.
+    fractions_above: Vec<f64>,
+}
+
+impl LuminosityDistribution {
+    /// Builds the distribution from luminosities in units of solar luminosities, each with the probability of its grid cell.
+    fn new(mut grid: Vec<(f64, f64)>) -> Self {
+        grid.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let total: f64 = grid.iter().map(|(_, weight)| weight).sum();
+        let mut above = total;
+        let mut fractions_above = Vec::with_capacity(grid.len());
+        for (_, weight) in grid.iter() {
+            fractions_above.push(if total > 0. { above / total } else { 0. });
+            above -= weight;
+        }
+        let luminosities = grid.into_iter().map(|(luminosity, _)| luminosity).collect();
+        Self {
+            luminosities,
+            fractions_above,
+        }
+    }
+
+    fn fraction_above(&self, luminosity: f64) -> f64 {
+        let index = self.luminosities.partition_point(|&l| l <= luminosity);
+        self.fractions_above.get(index).copied().unwrap_or(0.)
+    }
+}
+
+/// An age bin together with the quantities that are the same for all chunks.
+struct Component {
+    age_bin: AgeBin,
+    imf: InitialMassFunction,
+    youngest_in_gyr: f64,
+    oldest_in_gyr: f64,
+    /// The initial mass of the heaviest star of this component that is still alive, in units of solar masses.
+    heaviest_mass: f64,
+    /// The highest luminosity any star of this component can have, in units of solar luminosities.
+    brightest_luminosity: f64,
+    /// Whether the stars of this component are given companions.
+    has_companions: bool,
+    /// The expectation value of the current stellar mass, in units of solar masses.
+    mean_mass: f64,
+}
+
+impl Component {
+    fn new(age_bin: AgeBin, cooling_table: &CoolingTable, include_companions: bool) -> Self {
+        let imf = InitialMassFunction::new(age_bin.population);
+        let youngest_in_gyr = age_bin.youngest.get::<gigayear>();
+        let oldest_in_gyr = age_bin.oldest.get::<gigayear>();
+        let heaviest_mass = imf
+            .max_mass()
+            .min(heaviest_living_mass(youngest_in_gyr) * (1. - 1e-9));
+        let (brightest_luminosity, mean_mass) = if age_bin.is_white_dwarf() {
+            (
+                cooling_table.max_luminosity(),
+                mean_white_dwarf_mass(&imf, youngest_in_gyr, oldest_in_gyr),
+            )
+        } else {
+            (
+                max_luminosity(heaviest_mass, youngest_in_gyr, oldest_in_gyr),
+                imf.mean_mass(),
+            )
+        };
+        Self {
+            age_bin,
+            imf,
+            youngest_in_gyr,
+            oldest_in_gyr,
+            heaviest_mass,
+            brightest_luminosity,
+            has_companions: include_companions && !age_bin.is_white_dwarf(),
+            mean_mass,
+        }
+    }
+
+    /// The highest luminosity any system of this component can have, including its companion, in units of solar luminosities.
+    fn brightest_system_luminosity(&self) -> f64 {
+        self.max_system_luminosity(self.brightest_luminosity)
+    }
+
+    /// The highest luminosity a system can have whose primary reaches at most the given luminosity within the age bin.
+    ///
+    /// White dwarfs have no companions. A companion is lighter than its primary and of the same age, and the highest luminosity within the age bin does not decrease with initial mass for living stars.
+    /// So the companion is never brighter than the brightest star of the mass of its primary, even if the primary is currently fainter than it.
+    /// A living companion of a primary that has already died is still no brighter than the brightest living star of the age bin, and such primaries are heavier than any mass this bound is asked for.
+    fn max_system_luminosity(&self, primary_luminosity: f64) -> f64 {
+        if self.has_companions {
+            2. * primary_luminosity
+        } else {
+            primary_luminosity
+        }
+    }
+
+    /// The smallest initial mass for which a system of this component, with the star as its primary, can exceed the given luminosity, in units of solar masses.
+    ///
+    /// The highest luminosity reached within the age bin does not decrease with initial mass for living stars, so the mass can be found by bisection.
+    /// The result errs on the low side.
+    fn minimum_visible_mass(&self, luminosity_limit: f64) -> f64 {
+        let reaches_limit = |mass: f64| {
+            let primary = max_luminosity(mass, self.youngest_in_gyr, self.oldest_in_gyr);
+            self.max_system_luminosity(primary) > luminosity_limit
+        };
+        let mut lower = self.imf.min_mass();
+        let mut upper = self.heaviest_mass;
+        if reaches_limit(lower) {
+            return lower;
+        }
+        for _ in 0..MASS_BISECTION_STEPS {
+            let middle = (lower + upper) / 2.;
+            if reaches_limit(middle) {
+                upper = middle;
+            } else {
+                lower = middle;
+            }
+        }
+        lower
+    }
+
+    fn sample_age<R: Rng>(&self, rng: &mut R) -> f64 {
+        if self.oldest_in_gyr > self.youngest_in_gyr {
+            rng.gen_range(self.youngest_in_gyr..self.oldest_in_gyr)
+        } else {
+            self.youngest_in_gyr
+        }
+    }
+
+    fn expected_number_of_stars(
+        &self,
+        chunk: &GalacticChunk,
+        chunksize_in_pc: f64,
+        frame: &GalactocentricFrame,
+    ) -> f64 {
+        self.number_density(chunk.center(chunksize_in_pc), frame) * chunksize_in_pc.powi(3)
+    }
+
+    /// The distribution of the luminosities of all stars, integrated over the IMF and the age range on a grid.
+    ///
+    /// Stars heavier than the heaviest living one are sampled as well, so they count as remnants without luminosity.
+    fn luminosity_distribution(&self) -> LuminosityDistribution {
+        let ratio = self.heaviest_mass / self.imf.min_mass();
+        let mut grid = Vec::with_capacity(LUMINOSITY_MASS_STEPS * LUMINOSITY_AGE_STEPS + 1);
+        grid.push((0., self.imf.fraction_above(self.heaviest_mass)));
+        for mass_step in 0..LUMINOSITY_MASS_STEPS {
+            let lower =
+                self.imf.min_mass() * ratio.powf(mass_step as f64 / LUMINOSITY_MASS_STEPS as f64);
+            let upper = self.imf.min_mass()
+                * ratio.powf((mass_step + 1) as f64 / LUMINOSITY_MASS_STEPS as f64);
+            let probability = self.imf.fraction_above(lower) - self.imf.fraction_above(upper);
+            let mass = (lower * upper).sqrt();
+            for age_step in 0..LUMINOSITY_AGE_STEPS {
+                let fraction = (age_step as f64 + 0.5) / LUMINOSITY_AGE_STEPS as f64;
+                let age =
+                    self.youngest_in_gyr + fraction * (self.oldest_in_gyr - self.youngest_in_gyr);
+                let luminosity = evolve(mass, age).luminosity_in_solar_units;
+                grid.push((luminosity, probability / LUMINOSITY_AGE_STEPS as f64));
+            }
+        }
+        LuminosityDistribution::new(grid)
+    }
+
+    fn number_density(&self, position_in_pc: (f64, f64, f64), frame: &GalactocentricFrame) -> f64 {
+        number_density(
+            self.age_bin.population,
+            self.age_bin.representative_age(),
+            position_in_pc,
+            self.mean_mass,
+            frame,
+        )
+    }
+}
+
+impl<'a> Generator<'a> {
+    pub(crate) fn new(
+        generation_parameters: &'a GenerationParameters,
+    ) -> Result<Self, Box<dyn Error>> {
+        generation_parameters.validate()?;
+        let cooling_table =
+            CoolingTable::load(generation_parameters.white_dwarf_cooling_table.as_deref())?;
+        let components = AgeBin::all()
+            .into_iter()
+            .map(|age_bin| {
+                Component::new(
+                    age_bin,
+                    &cooling_table,
+                    generation_parameters.include_companions,
+                )
+            })
+            .collect();
+        let bolometric_corrections = BolometricCorrectionTable::load(
+            generation_parameters.bolometric_correction_table.as_deref(),
+        )?;
+        let extinction = generation_parameters
+            .extinction
+            .as_ref()
+            .map(|model| Extinction::load(model, &generation_parameters.galactocentric_frame))
+            .transpose()?;
+        let culling_magnitude_limit = match generation_parameters.magnitude_limit_band {
+            Some(band) => {
+                generation_parameters.apparent_magnitude_limit
+                    + bolometric_corrections.max_correction(band)
+            }
+            None => generation_parameters.apparent_magnitude_limit,
+        };
+        let frame = &generation_parameters.galactocentric_frame;
+        let potential = GalacticPotential::new(generation_parameters.dark_halo, frame);
+        let (observer_position, observer_velocity) = generation_parameters.epoch.propagate(
+            frame.model_frame_vector(generation_parameters.observer_position_in_pc()),
+            solar_velocity_in_km_per_s(frame, &potential),
+            &potential,
+        );
+        Ok(Self {
+            generation_parameters,
+            cooling_table,
+            bolometric_corrections,
+            colour_matching_functions: ColourMatchingFunctions::new(),
+            extinction,
+            potential,
+            observer_position_in_pc: frame.vector_from_model_frame(observer_position),
+            observer_velocity_in_km_per_s: observer_velocity,
+            culling_magnitude_limit,
+            components,
+        })
+    }
+
+    /// The position of the observer at the epoch of the catalogue, in units of parsec.
+    pub(crate) fn observer_position_in_pc(&self) -> (f64, f64, f64) {
+        self.observer_position_in_pc
+    }
+
+    /// The luminosity distributions of all age bins, in the order of [`Generator::expected_stars`].
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#dry-run>
+    pub(crate) fn luminosity_distributions(&self) -> Vec<LuminosityDistribution> {
+        self.components
+            .iter()
+            .map(|component| {
+                if component.age_bin.is_white_dwarf() {
+                    LuminosityDistribution::new(luminosity_grid(
+                        &component.imf,
+                        component.youngest_in_gyr,
+                        component.oldest_in_gyr,
+                        &self.cooling_table,
+                    ))
+                } else {
+                    component.luminosity_distribution()
+                }
+            })
+            .collect()
+    }
+
+    /// The expected number of stars of every age bin in a volume around a position, without sampling any of them.
+    ///
+    /// As in [`Generator::generate_chunk`], the density is the one at the center of the chunk containing the position, and the age bins are culled with the nearest distance of that chunk.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#dry-run>
+    pub(crate) fn expected_stars(
+        &self,
+        position_in_pc: (f64, f64, f64),
+        volume_in_pc3: f64,
+        luminosity_distributions: &[LuminosityDistribution],
+    ) -> Vec<ExpectedStars> {
+        let params = self.generation_parameters;
+        let chunksize = params.chunksize_in_pc;
+        let chunk = GalacticChunk::containing(params, position_in_pc);
+        let culling_limit = self.culling_luminosity_limit(
+            chunk.nearest_distance_in_pc(chunksize, params.observer_position_in_pc()),
+        );
+        let distance = distance_in_pc(position_in_pc, params.observer_position_in_pc());
+        let visible_limit = luminosity_limit(distance, params.apparent_magnitude_limit);
+        self.components
+            .iter()
+            .zip(luminosity_distributions)
+            .map(|(component, distribution)| {
+                let all = component.expected_number_of_stars(
+                    &chunk,
+                    chunksize,
+                    &params.galactocentric_frame,
+                ) * volume_in_pc3
+                    / chunksize.powi(3);
+                let sampled = if component.brightest_system_luminosity() <= culling_limit {
+                    0.
+                } else if params.importance_sampling && !component.age_bin.is_white_dwarf() {
+                    let minimum_mass = component.minimum_visible_mass(culling_limit);
+                    all * component.imf.fraction_above(minimum_mass)
+                } else {
+                    all
+                };
+                ExpectedStars {
+                    population: component.age_bin.population,
+                    all,
+                    sampled,
+                    visible: all * distribution.fraction_above(visible_limit),
+                }
+            })
+            .collect()
+    }
+
+    /// Generates all stars and unresolved clusters of the chunk with the given index that are brighter than the magnitude limit.
+    ///
+    /// Every chunk draws from its own random stream, so the result only depends on the seed and the index.
+    pub(crate) fn generate_chunk(
+        &self,
+        index: usize,
+        chunk: &GalacticChunk,
+        statistics: &mut GenerationStatistics,
+    ) -> GeneratedChunk {
+        let params = self.generation_parameters;
+        let mut rng = ChaCha8Rng::seed_from_u64(params.seed);
+        rng.set_stream(index as u64);
+
+        let chunk_limit = self.culling_luminosity_limit(
+            chunk.nearest_distance_in_pc(params.chunksize_in_pc, params.observer_position_in_pc()),
+        );
+
+        let mut generated = GeneratedChunk::default();
+        let mut next_cluster_id = (index as u64) << 32;
+        for component in self.components.iter() {
+            let mut expected = component.expected_number_of_stars(
+                chunk,
+                params.chunksize_in_pc,
+                &params.galactocentric_frame,
+            );
+            let clustered_fraction = self.clustered_fraction(&component.age_bin);
+            if clustered_fraction > 0. {
+                let clustered = expected * clustered_fraction;
+                expected -= clustered;
+                let expected_clusters = clustered * component.imf.mean_mass()
+                    / StarCluster::mean_mass(component.age_bin.population);
+                for _ in 0..sample_number_of_stars(expected_clusters, &mut rng) {
+                    let cluster = self.sample_cluster(component, next_cluster_id, chunk, &mut rng);
+                    next_cluster_id += 1;
+                    self.generate_cluster(
+                        component,
+                        &cluster,
+                        &mut rng,
+                        statistics,
+                        &mut generated,
+                    );
+                }
+            }
+            if component.brightest_system_luminosity() <= chunk_limit {
+                statistics.chunk_age_bins_culled += 1;
+                statistics.expected_stars_culled_with_chunks += expected;
+                continue;
+            }
+            let (minimum_mass, expected_sampled) =
+                if params.importance_sampling && !component.age_bin.is_white_dwarf() {
+                    let minimum_mass = component.minimum_visible_mass(chunk_limit);
+                    let fraction = component.imf.fraction_above(minimum_mass);
+                    statistics.expected_stars_below_visible_mass += expected * (1. - fraction);
+                    (minimum_mass, expected * fraction)
+                } else {
+                    (component.imf.min_mass(), expected)
+                };
+            let number = sample_number_of_stars(expected_sampled, &mut rng);
+            for _ in 0..number {
+                let star = self.sample_star(component, minimum_mass, chunk, &mut rng);
+                self.keep_if_visible(star, statistics, &mut generated.stars);
+            }
+        }
+        statistics.chunks_processed += 1;
+        generated
+    }
+
+    /// The fraction of the stars of an age bin that are placed in clusters instead of the field.
+    fn clustered_fraction(&self, age_bin: &AgeBin) -> f64 {
+        let params = self.generation_parameters;
+        if params.include_clusters && StarCluster::forms_in(age_bin) {
+            CLUSTERED_FRACTION
+        } else if params.include_globular_clusters && age_bin.population == Population::Spheroid {
+            GLOBULAR_CLUSTER_FRACTION
+        } else {
+            0.
+        }
+    }
+
+    /// The luminosity limit a system at the given distance has to exceed to possibly be visible, loosened for the epoch the stars are moved to.
+    fn culling_luminosity_limit(&self, nearest_distance_in_pc: f64) -> f64 {
+        let params = self.generation_parameters;
+        // Stars may approach the observer until the epoch of the catalogue.
+        let nearest_distance = (nearest_distance_in_pc - params.epoch.max_approach_in_pc()).max(0.);
+        luminosity_limit(nearest_distance, self.culling_magnitude_limit)
+    }
+
+    /// Moves a sampled star to the epoch of the catalogue and keeps it if the observer can see it.
+    fn keep_if_visible(
+        &self,
+        star: Option<Star>,
+        statistics: &mut GenerationStatistics,
+        stars: &mut Vec<Star>,
+    ) {
+        statistics.stars_sampled += 1;
+        let Some(star) = star else {
+            statistics.white_dwarf_progenitors_not_found += 1;
+            return;
+        };
+        let star = self.propagate(star, &self.generation_parameters.epoch);
+        if let Some(star) = self.observe(star) {
+            statistics.stars_kept += 1;
+            statistics.companions_kept += star.companions.len() as u64;
+            stars.push(star);
+        } else {
+            statistics.stars_culled_individually += 1;
+        }
+    }
+
+    /// Samples a cluster of the given component with its center inside the chunk.
+    fn sample_cluster<R: Rng>(
+        &self,
+        component: &Component,
+        id: u64,
+        chunk: &GalacticChunk,
+        rng: &mut R,
+    ) -> StarCluster {
+        let center_in_pc = self.sample_position(chunk, rng);
+        let age_in_gyr = component.sample_age(rng);
+        let metallicity_in_dex = self.sample_metallicity(component, center_in_pc, rng);
+        let velocity_in_km_per_s =
+            self.sample_velocity(component.age_bin.population, age_in_gyr, center_in_pc, rng);
+        StarCluster::sample(
+            id,
+            component.age_bin.population,
+            age_in_gyr,
+            metallicity_in_dex,
+            center_in_pc,
+            velocity_in_km_per_s,
+            rng,
+        )
+    }
+
+    /// Samples the members of a cluster that are bright enough to possibly be visible.
+    ///
+    /// A globular cluster none of whose members can reach the magnitude limit is instead observed as a whole.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#open-clusters-and-ob-associations>
+    fn generate_cluster<R: Rng>(
+        &self,
+        component: &Component,
+        cluster: &StarCluster,
+        rng: &mut R,
+        statistics: &mut GenerationStatistics,
+        generated: &mut GeneratedChunk,
+    ) {
+        let params = self.generation_parameters;
+        let nearest_distance =
+            distance_in_pc(cluster.center_in_pc, params.observer_position_in_pc())
+                - cluster.extent_in_pc();
+        let limit = self.culling_luminosity_limit(nearest_distance);
+        let expected = cluster.mass_in_solar_masses / component.imf.mean_mass();
+        statistics.clusters_sampled += 1;
+        if component.brightest_system_luminosity() <= limit {
+            statistics.expected_stars_culled_with_chunks += expected;
+            let unresolved = if cluster.kind == ClusterKind::GlobularCluster {
+                self.observe_unresolved(component, *cluster)
+            } else {
+                None
+            };
+            match unresolved {
+                Some(unresolved) => {
+                    statistics.unresolved_clusters_kept += 1;
+                    generated.unresolved_clusters.push(unresolved);
+                }
+                None => statistics.clusters_culled += 1,
+            }
+            return;
+        }
+        let minimum_mass = if params.importance_sampling {
+            let minimum_mass = component.minimum_visible_mass(limit);
+            let fraction = component.imf.fraction_above(minimum_mass);
+            statistics.expected_stars_below_visible_mass += expected * (1. - fraction);
+            minimum_mass
+        } else {
+            component.imf.min_mass()
+        };
+        let number =
+            sample_number_of_stars(expected * component.imf.fraction_above(minimum_mass), rng);
+        for _ in 0..number {
+            let initial_mass = component.imf.sample_above(minimum_mass, rng);
+            let position_in_pc = cluster.sample_member_position(rng);
+            let velocity_in_km_per_s = cluster.sample_member_velocity(rng);
+            let mut star = evolved_star(
+                component.age_bin.population,
+                initial_mass,
+                cluster.age_in_gyr,
+                cluster.metallicity_in_dex,
+                position_in_pc,
+                velocity_in_km_per_s,
+            );
+            star.cluster_id = Some(cluster.id);
+            self.add_companions_and_variability(&mut star, component, rng);
+            self.keep_if_visible(Some(star), statistics, &mut generated.stars);
+        }
+    }
+
+    /// Moves an unresolved cluster to the epoch of the catalogue and assigns its integrated light as seen by the observer, or discards it if it is fainter than the magnitude limit.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#globular-clusters>
+    fn observe_unresolved(
+        &self,
+        component: &Component,
+        mut cluster: StarCluster,
+    ) -> Option<UnresolvedCluster> {
+        let params = self.generation_parameters;
+        let frame = &params.galactocentric_frame;
+        let (position, velocity) = params.epoch.propagate(
+            frame.model_frame_vector(cluster.center_in_pc),
+            frame.model_frame_vector(cluster.velocity_in_km_per_s),
+            &self.potential,
+        );
+        cluster.center_in_pc = frame.vector_from_model_frame(position);
+        cluster.velocity_in_km_per_s = frame.vector_from_model_frame(velocity);
+
+        let (absolute_magnitudes, xyz) = self.integrated_light(component, &cluster);
+        let distance = distance_in_pc(cluster.center_in_pc, self.observer_position_in_pc);
+        let visual_extinction_in_mag = self.extinction.as_ref().map_or(0., |extinction| {
+            extinction.visual_extinction(self.observer_position_in_pc, cluster.center_in_pc)
+        });
+        let apparent_magnitudes = redden(
+            &absolute_magnitudes.shifted(distance_modulus(distance)),
+            visual_extinction_in_mag,
+        );
+        let apparent_magnitude = apparent_magnitudes.get(params.magnitude_limit_band);
+        if apparent_magnitude >= params.apparent_magnitude_limit {
+            return None;
+        }
+        let colour = params
+            .include_colour
+            .then(|| colour_from_xyz(xyz, apparent_magnitude, params.apparent_magnitude_limit));
+        Some(UnresolvedCluster {
+            cluster,
+            absolute_magnitudes,
+            apparent_magnitudes,
+            visual_extinction_in_mag,
+            // One parsec seen from a distance of one parsec subtends one radian.
+            angular_radius_in_arcsec: (cluster.half_mass_radius_in_pc() / distance)
+                .atan()
+                .to_degrees()
+                * 3600.,
+            sky_coordinates: SkyCoordinates::new(
+                cluster.center_in_pc,
+                self.observer_position_in_pc,
+                frame,
+                &params.sky_frames,
+            ),
+            astrometry: self.astrometry(cluster.center_in_pc, cluster.velocity_in_km_per_s),
+            colour,
+        })
+    }
+
+    /// The integrated absolute magnitudes of all members of a cluster, together with the CIE XYZ tristimulus values of their combined light.
+    ///
+    /// The light of the members is integrated over the IMF, approximating each member by a blackbody for the colour.
+    fn integrated_light(
+        &self,
+        component: &Component,
+        cluster: &StarCluster,
+    ) -> (Magnitudes, (f64, f64, f64)) {
+        let imf = &component.imf;
+        let number_of_members = cluster.mass_in_solar_masses / imf.mean_mass();
+        let ratio = imf.max_mass() / imf.min_mass();
+        let mut contributions = Vec::new();
+        let mut xyz = (0., 0., 0.);
+        for step in 0..LIGHT_INTEGRATION_STEPS {
+            let lower = imf.min_mass() * ratio.powf(step as f64 / LIGHT_INTEGRATION_STEPS as f64);
+            let upper =
+                imf.min_mass() * ratio.powf((step + 1) as f64 / LIGHT_INTEGRATION_STEPS as f64);
+            let number =
+                number_of_members * (imf.fraction_above(lower) - imf.fraction_above(upper));
+            let state = evolve((lower * upper).sqrt(), cluster.age_in_gyr);
+            if number <= 0. || state.luminosity_in_solar_units <= 0. {
+                continue;
+            }
+            let magnitudes = self
+                .bolometric_corrections
+                .absolute_magnitudes(
+                    state.luminosity_in_solar_units,
+                    state.effective_temperature_in_k,
+                    state.mass_in_solar_masses,
+                    cluster.metallicity_in_dex,
+                )
+                .shifted(-2.5 * number.log10());
+            let (x, y, z) = self
+                .colour_matching_functions
+                .blackbody_xyz(state.effective_temperature_in_k);
+            let luminance = 10_f64.powf(-0.4 * magnitudes.johnson_v);
+            xyz = (
+                xyz.0 + x * luminance,
+                xyz.1 + y * luminance,
+                xyz.2 + z * luminance,
+            );
+            contributions.push(magnitudes);
+        }
+        (Magnitudes::combined(contributions.iter()), xyz)
+    }
+
+    /// A uniformly distributed position inside the chunk.
+    ///
+    /// The offset from the center is drawn in the model frame, so the conventions of the galactocentric frame only relabel the axes.
+    fn sample_position<R: Rng>(&self, chunk: &GalacticChunk, rng: &mut R) -> (f64, f64, f64) {
+        let size = self.generation_parameters.chunksize_in_pc;
+        let (x0, y0, z0) = chunk.center(size);
+        let (dx, dy, dz) = self
+            .generation_parameters
+            .galactocentric_frame
+            .vector_from_model_frame((
+                (rng.gen::<f64>() - 0.5) * size,
+                (rng.gen::<f64>() - 0.5) * size,
+                (rng.gen::<f64>() - 0.5) * size,
+            ));
+        (x0 + dx, y0 + dy, z0 + dz)
+    }
+
+    fn sample_metallicity<R: Rng>(
+        &self,
+        component: &Component,
+        position_in_pc: (f64, f64, f64),
+        rng: &mut R,
+    ) -> f64 {
+        let radius =
+            (position_in_pc.0 * position_in_pc.0 + position_in_pc.1 * position_in_pc.1).sqrt();
+        sample_metallicity(
+            component.age_bin.population,
+            component.age_bin.representative_age(),
+            radius,
+            self.generation_parameters
+                .galactocentric_frame
+                .solar_radius_in_pc,
+            rng,
+        )
+    }
+
+    fn sample_star<R: Rng>(
+        &self,
+        component: &Component,
+        minimum_mass: f64,
+        chunk: &GalacticChunk,
+        rng: &mut R,
+    ) -> Option<Star> {
+        let position_in_pc = self.sample_position(chunk, rng);
+        if component.age_bin.is_white_dwarf() {
+            return self.sample_white_dwarf(component, position_in_pc, rng);
+        }
+        let initial_mass = component.imf.sample_above(minimum_mass, rng);
+        let age_in_gyr = component.sample_age(rng);
+        let metallicity_in_dex = self.sample_metallicity(component, position_in_pc, rng);
+        let velocity_in_km_per_s = self.sample_velocity(
+            component.age_bin.population,
+            age_in_gyr,
+            position_in_pc,
+            rng,
+        );
+        let mut star = evolved_star(
+            component.age_bin.population,
+            initial_mass,
+            age_in_gyr,
+            metallicity_in_dex,
+            position_in_pc,
+            velocity_in_km_per_s,
+        );
+        self.add_companions_and_variability(&mut star, component, rng);
+        Some(star)
+    }
+
+    /// Gives a star a companion and a variability class, if requested.
+    fn add_companions_and_variability<R: Rng>(
+        &self,
+        star: &mut Star,
+        component: &Component,
+        rng: &mut R,
+    ) {
+        if self.generation_parameters.include_companions {
+            let primary_mass = star.initial_mass_in_solar_masses;
+            if let Some(orbit) = sample_companion(primary_mass, component.imf.min_mass(), rng) {
+                let companion = companion(star, orbit);
+                star.companions.push(companion);
+            }
+        }
+        if self.generation_parameters.include_variability {
+            for companion in star.companions.iter_mut() {
+                companion.variability = classify(companion, rng);
+            }
+            star.variability = classify(star, rng);
+        }
+    }
+
+    /// Samples a white dwarf from its progenitor, cooling it down for the time since the progenitor died.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#white-dwarfs>
+    fn sample_white_dwarf<R: Rng>(
+        &self,
+        component: &Component,
+        position_in_pc: (f64, f64, f64),
+        rng: &mut R,
+    ) -> Option<Star> {
+        let (age_in_gyr, progenitor_mass) = sample_progenitor(
+            &component.imf,
+            component.youngest_in_gyr,
+            component.oldest_in_gyr,
+            rng,
+        )?;
+        let metallicity_in_dex = self.sample_metallicity(component, position_in_pc, rng);
+        let mass = white_dwarf_mass(progenitor_mass);
+        let cooling_age_in_gyr = age_in_gyr - lifetime_in_gyr(progenitor_mass);
+        let (luminosity, temperature) = self.cooling_table.interpolate(mass, cooling_age_in_gyr);
+        let velocity_in_km_per_s = self.sample_velocity(
+            component.age_bin.population,
+            age_in_gyr,
+            position_in_pc,
+            rng,
+        );
+        Some(Star {
+            position_in_pc,
+            velocity_in_km_per_s,
+            initial_mass_in_solar_masses: progenitor_mass,
+            mass_in_solar_masses: mass,
+            age_in_gyr,
+            metallicity_in_dex,
+            luminosity_in_solar_units: luminosity,
+            effective_temperature_in_k: temperature,
+            population: component.age_bin.population,
+            cooling_age_in_gyr: Some(cooling_age_in_gyr),
+            absolute_magnitudes: Magnitudes::default(),
+            apparent_magnitudes: Magnitudes::default(),
+            system_apparent_magnitudes: None,
+            visual_extinction_in_mag: 0.,
+            sky_coordinates: SkyCoordinates::default(),
+            astrometry: Astrometry::default(),
+            colour: None,
+            orbit: None,
+            companions: Vec::new(),
+            variability: None,
+            cluster_id: None,
+        })
+    }
+
+    /// Samples the velocity of a star from the velocity ellipsoid of its population, in units of km/s.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#kinematics>
+    fn sample_velocity<R: Rng>(
+        &self,
+        population: Population,
+        age_in_gyr: f64,
+        position_in_pc: (f64, f64, f64),
+        rng: &mut R,
+    ) -> (f64, f64, f64) {
+        let frame = &self.generation_parameters.galactocentric_frame;
+        let ellipsoid = velocity_ellipsoid(population, Time::new::<gigayear>(age_in_gyr));
+        let velocity = sample_velocity(
+            &ellipsoid,
+            frame.model_frame_vector(position_in_pc),
+            &self.potential,
+            rng,
+        );
+        frame.vector_from_model_frame(velocity)
+    }
+
+    /// Moves a star from the present to the given epoch.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#epoch>
+    fn propagate(&self, mut star: Star, epoch: &Epoch) -> Star {
+        let frame = &self.generation_parameters.galactocentric_frame;
+        let (position, velocity) = epoch.propagate(
+            frame.model_frame_vector(star.position_in_pc),
+            frame.model_frame_vector(star.velocity_in_km_per_s),
+            &self.potential,
+        );
+        star.position_in_pc = frame.vector_from_model_frame(position);
+        star.velocity_in_km_per_s = frame.vector_from_model_frame(velocity);
+        for companion in star.companions.iter_mut() {
+            companion.position_in_pc = star.position_in_pc;
+            companion.velocity_in_km_per_s = star.velocity_in_km_per_s;
+        }
+        star
+    }
+
+    /// Assigns the magnitudes, the astrometry and, if requested, the colour and sky coordinates as seen by the observer to a star and its companions, or discards them if all of their point sources are fainter than the magnitude limit.
+    ///
+    /// For a bolometric limit, systems are first compared on their total luminosity, so no logarithms or extinction integrals are needed for most discarded stars.
+    /// Extinction only dims stars, so this comparison never discards a star that would be visible.
+    fn observe(&self, star: Star) -> Option<Star> {
+        let params = self.generation_parameters;
+        let distance = distance_in_pc(star.position_in_pc, self.observer_position_in_pc);
+        let mut star = without_dark_primary(star)?;
+        let system_luminosity = star.luminosity_in_solar_units
+            + star
+                .companions
+                .iter()
+                .map(|companion| companion.luminosity_in_solar_units.max(0.))
+                .sum::<f64>();
+        if params.magnitude_limit_band.is_none()
+            && system_luminosity <= luminosity_limit(distance, params.apparent_magnitude_limit)
+        {
+            return None;
+        }
+        if let Some(extinction) = &self.extinction {
+            star.visual_extinction_in_mag =
+                extinction.visual_extinction(self.observer_position_in_pc, star.position_in_pc);
+        }
+        self.assign_magnitudes(&mut star, distance);
+        for companion in star.companions.iter_mut() {
+            companion.visual_extinction_in_mag = star.visual_extinction_in_mag;
+            self.assign_magnitudes(companion, distance);
+            if let Some(orbit) = companion.orbit.as_mut() {
+                orbit.angular_separation_in_arcsec = orbit.angular_separation_at(distance);
+                orbit.resolved = params
+                    .resolution_in_arcsec
+                    .is_none_or(|resolution| orbit.angular_separation_in_arcsec >= resolution);
+            }
+        }
+        if !star.companions.is_empty() {
+            star.system_apparent_magnitudes = Some(Magnitudes::combined(
+                std::iter::once(&star.apparent_magnitudes).chain(
+                    star.companions
+                        .iter()
+                        .map(|companion| &companion.apparent_magnitudes),
+                ),
+            ));
+        }
+        if brightest_point_source(&star, params.magnitude_limit_band)
+            >= params.apparent_magnitude_limit
+        {
+            return None;
+        }
+        star.sky_coordinates = SkyCoordinates::new(
+            star.position_in_pc,
+            self.observer_position_in_pc,
+            &params.galactocentric_frame,
+            &params.sky_frames,
+        );
+        star.astrometry = self.astrometry(star.position_in_pc, star.velocity_in_km_per_s);
+        star.colour = self.colour(&star);
+        for companion in star.companions.iter_mut() {
+            companion.sky_coordinates = star.sky_coordinates;
+            companion.astrometry = star.astrometry;
+            companion.colour = self.colour(companion);
+        }
+        Some(star)
+    }
+
+    /// Assigns the absolute magnitudes and the apparent magnitudes at the given distance, after extinction.
+    fn assign_magnitudes(&self, star: &mut Star, distance_in_pc: f64) {
+        star.absolute_magnitudes = self.bolometric_corrections.absolute_magnitudes(
+            star.luminosity_in_solar_units,
+            star.effective_temperature_in_k,
+            star.mass_in_solar_masses,
+            star.metallicity_in_dex,
+        );
+        star.apparent_magnitudes = redden(
+            &star
+                .absolute_magnitudes
+                .shifted(distance_modulus(distance_in_pc)),
+            star.visual_extinction_in_mag,
+        );
+    }
+
+    /// The display colour of a star, if requested.
+    fn colour(&self, star: &Star) -> Option<Colour> {
+        let params = self.generation_parameters;
+        if !params.include_colour {
+            return None;
+        }
+        Some(self.colour_matching_functions.colour(
+            star.effective_temperature_in_k,
+            star.apparent_magnitudes.get(params.magnitude_limit_band),
+            params.apparent_magnitude_limit,
+        ))
+    }
+
+    /// The motion of an object with the given galactocentric position and velocity relative to the observer.
+    fn astrometry(
+        &self,
+        position_in_pc: (f64, f64, f64),
+        velocity_in_km_per_s: (f64, f64, f64),
+    ) -> Astrometry {
+        let params = self.generation_parameters;
+        let frame = &params.galactocentric_frame;
+        let position = frame.model_frame_vector(position_in_pc);
+        let observer = frame.model_frame_vector(self.observer_position_in_pc);
+        let velocity = frame.model_frame_vector(velocity_in_km_per_s);
+        let observer_velocity = self.observer_velocity_in_km_per_s;
+        Astrometry::new(
+            (
+                position.0 - observer.0,
+                position.1 - observer.1,
+                position.2 - observer.2,
+            ),
+            (
+                velocity.0 - observer_velocity.0,
+                velocity.1 - observer_velocity.1,
+                velocity.2 - observer_velocity.2,
+            ),
+            frame,
+        )
+    }
+}
+
+/// A single star with the given properties, evolved to its age.
+fn evolved_star(
+    population: Population,
+    initial_mass: f64,
+    age_in_gyr: f64,
+    metallicity_in_dex: f64,
+    position_in_pc: (f64, f64, f64),
+    velocity_in_km_per_s: (f64, f64, f64),
+) -> Star {
+    let state = evolve(initial_mass, age_in_gyr);
+    Star {
+        position_in_pc,
+        velocity_in_km_per_s,
+        initial_mass_in_solar_masses: initial_mass,
+        mass_in_solar_masses: state.mass_in_solar_masses,
+        age_in_gyr,
+        metallicity_in_dex,
+        luminosity_in_solar_units: state.luminosity_in_solar_units,
+        effective_temperature_in_k: state.effective_temperature_in_k,
+        population,
+        cooling_age_in_gyr: None,
+        absolute_magnitudes: Magnitudes::default(),
+        apparent_magnitudes: Magnitudes::default(),
+        system_apparent_magnitudes: None,
+        visual_extinction_in_mag: 0.,
+        sky_coordinates: SkyCoordinates::default(),
+        astrometry: Astrometry::default(),
+        colour: None,
+        orbit: None,
+        companions: Vec::new(),
+        variability: None,
+        cluster_id: None,
+    }
+}
+
+/// A companion on the given orbit around a star, of the same age and composition.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#multiple-systems>
+fn companion(primary: &Star, orbit: Orbit) -> Star {
+    let initial_mass = orbit.mass_ratio * primary.initial_mass_in_solar_masses;
+    let state = evolve(initial_mass, primary.age_in_gyr);
+    Star {
+        initial_mass_in_solar_masses: initial_mass,
+        mass_in_solar_masses: state.mass_in_solar_masses,
+        luminosity_in_solar_units: state.luminosity_in_solar_units,
+        effective_temperature_in_k: state.effective_temperature_in_k,
+        orbit: Some(orbit),
+        companions: Vec::new(),
+        ..primary.clone()
+    }
+}
+
+/// The system with its brightest living companion in place of a primary that has already become a dark remnant, or none if no component shines.
+///
+/// The remnant cannot be seen, so it is not listed. The orbits of any further companions still refer to it.
+fn without_dark_primary(mut star: Star) -> Option<Star> {
+    if star.luminosity_in_solar_units > 0. {
+        return Some(star);
+    }
+    let brightest = star
+        .companions
+        .iter()
+        .enumerate()
+        .filter(|(_, companion)| companion.luminosity_in_solar_units > 0.)
+        .max_by(|(_, a), (_, b)| {
+            a.luminosity_in_solar_units
+                .total_cmp(&b.luminosity_in_solar_units)
+        })
+        .map(|(index, _)| index)?;
+    let mut primary = star.companions.remove(brightest);
+    primary.orbit = None;
+    primary.companions = star.companions;
+    Some(primary)
+}
+
+/// The apparent magnitude of the brightest point source of a system.
+///
+/// Companions that are not resolved merge with their primary into one point source.
+fn brightest_point_source(star: &Star, band: Option<Band>) -> f64 {
+    let is_resolved =
+        |companion: &&Star| companion.orbit.as_ref().is_none_or(|orbit| orbit.resolved);
+    let primary = Magnitudes::combined(
+        std::iter::once(&star.apparent_magnitudes).chain(
+            star.companions
+                .iter()
+                .filter(|companion| !is_resolved(companion))
+                .map(|companion| &companion.apparent_magnitudes),
+        ),
+    );
+    star.companions
+        .iter()
+        .filter(is_resolved)
+        .map(|companion| companion.apparent_magnitudes.get(band))
+        .fold(primary.get(band), f64::min)
+}
+
+fn sample_number_of_stars<R: Rng>(expected: f64, rng: &mut R) -> u64 {
+    match Poisson::new(expected) {
+        Ok(poisson) => poisson.sample(rng) as u64,
+        Err(_) => 0,
+    }
+}
+
+pub(crate) fn distance_in_pc(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+    let (dx, dy, dz) = (a.0 - b.0, a.1 - b.1, a.2 - b.2);
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assert_diff, coordinates::SkyFrame, epoch::Propagation, extinction::ExtinctionModel,
+        galactocentric_frame::XAxisDirection, luminosity_limit::SOLAR_ABSOLUTE_MAGNITUDE,
+        photometry::Band, population::Subpopulation,
+    };
+
+    use super::*;
+
+    fn solar_neighbourhood(apparent_magnitude_limit: f64) -> GenerationParameters {
+        GenerationParameters {
+            observer_position_in_pc: Some((8200., 0., 0.)),
+            apparent_magnitude_limit,
+            max_distance_in_pc: 100.,
+            chunksize_in_pc: 15.,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn all_kept_stars_are_brighter_than_the_limit() {
+        let params = solar_neighbourhood(6.);
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let mut statistics = GenerationStatistics::default();
+        for (index, chunk) in GalacticChunk::generate_chunks(&params).enumerate() {
+            for star in generator
+                .generate_chunk(index, &chunk, &mut statistics)
+                .stars
+            {
+                let distance =
+                    distance_in_pc(star.position_in_pc, params.observer_position_in_pc());
+                let limit = luminosity_limit(distance, params.apparent_magnitude_limit);
+                assert!(star.luminosity_in_solar_units > limit);
+            }
+        }
+        assert_eq!(
+            statistics.stars_sampled,
+            statistics.stars_kept
+                + statistics.stars_culled_individually
+                + statistics.white_dwarf_progenitors_not_found
+        );
+    }
+
+    #[test]
+    fn distant_chunks_are_culled_for_faint_populations() {
+        let params = solar_neighbourhood(-10.);
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let mut statistics = GenerationStatistics::default();
+        let chunk = GalacticChunk {
+            corner: (8300., 0., 0.),
+        };
+        let stars = generator.generate_chunk(1, &chunk, &mut statistics).stars;
+        assert!(stars.is_empty());
+        assert_eq!(statistics.chunk_age_bins_culled, generator.components.len());
+        assert!(statistics.expected_stars_culled_with_chunks > 0.);
+        assert_eq!(statistics.stars_sampled, 0);
+    }
+
+    #[test]
+    fn minimum_visible_mass_is_a_lower_bound() {
+        let cooling_table = CoolingTable::load(None).expect("The cooling table should be valid");
+        for age_bin in AgeBin::all_alive() {
+            for include_companions in [false, true] {
+                let component = Component::new(age_bin, &cooling_table, include_companions);
+                for limit in [1e-3, 0.1, 1., 100., 1e4] {
+                    let minimum_mass = component.minimum_visible_mass(limit);
+                    if minimum_mass <= component.imf.min_mass() {
+                        continue;
+                    }
+                    let just_below = minimum_mass * (1. - 1e-6);
+                    let luminosity = max_luminosity(
+                        just_below,
+                        component.youngest_in_gyr,
+                        component.oldest_in_gyr,
+                    );
+                    let system_luminosity = component.max_system_luminosity(luminosity);
+                    assert!(system_luminosity <= limit, "{:?} {}", age_bin, limit);
+                }
+            }
+        }
+    }
+
+    /// Counts the kept stars and sums up their initial masses over many independent chunks.
+    fn kept_star_moments(params: &GenerationParameters, chunk: &GalacticChunk) -> (f64, f64) {
+        const REPETITIONS: usize = 300;
+        let generator = Generator::new(params).expect("The generator should be valid");
+        let mut statistics = GenerationStatistics::default();
+        let mut count = 0.;
+        let mut total_mass = 0.;
+        for index in 0..REPETITIONS {
+            for star in generator
+                .generate_chunk(index, chunk, &mut statistics)
+                .stars
+            {
+                count += 1.;
+                total_mass += star.initial_mass_in_solar_masses;
+            }
+        }
+        (count, total_mass)
+    }
+
+    #[test]
+    fn importance_sampling_is_statistically_identical_to_full_sampling() {
+        let full = solar_neighbourhood(8.);
+        let importance = GenerationParameters {
+            importance_sampling: true,
+            seed: 1,
+            ..full.clone()
+        };
+        let chunk = GalacticChunk {
+            corner: (8240., 0., 0.),
+        };
+
+        let (full_count, full_mass) = kept_star_moments(&full, &chunk);
+        let (importance_count, importance_mass) = kept_star_moments(&importance, &chunk);
+
+        let count_tolerance = 5. * (full_count + importance_count).sqrt();
+        assert!(full_count > 1000.);
+        assert!(
+            (full_count - importance_count).abs() < count_tolerance,
+            "{} vs {}",
+            full_count,
+            importance_count
+        );
+        let full_mean = full_mass / full_count;
+        let importance_mean = importance_mass / importance_count;
+        assert!(
+            (full_mean - importance_mean).abs() / full_mean < 0.1,
+            "{} vs {}",
+            full_mean,
+            importance_mean
+        );
+    }
+
+    #[test]
+    fn importance_sampling_samples_fewer_stars() {
+        let full = solar_neighbourhood(6.);
+        let importance = GenerationParameters {
+            importance_sampling: true,
+            ..full.clone()
+        };
+        let chunk = GalacticChunk {
+            corner: (8240., 0., 0.),
+        };
+        let mut full_statistics = GenerationStatistics::default();
+        let mut importance_statistics = GenerationStatistics::default();
+        Generator::new(&full)
+            .expect("The generator should be valid")
+            .generate_chunk(0, &chunk, &mut full_statistics);
+        Generator::new(&importance)
+            .expect("The generator should be valid")
+            .generate_chunk(0, &chunk, &mut importance_statistics);
+        assert!(importance_statistics.stars_sampled < full_statistics.stars_sampled);
+        assert!(importance_statistics.expected_stars_below_visible_mass > 0.);
+    }
+
+    #[test]
+    fn nearby_white_dwarfs_are_generated() {
+        let params = solar_neighbourhood(16.);
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let mut statistics = GenerationStatistics::default();
+        let chunk = GalacticChunk::generate_chunks(&params)
+            .next()
+            .expect("The chunk should exist");
+        let white_dwarfs: Vec<Star> = (0..20)
+            .flat_map(|index| {
+                generator
+                    .generate_chunk(index, &chunk, &mut statistics)
+                    .stars
+            })
+            .filter(|star| star.cooling_age_in_gyr.is_some())
+            .collect();
+        assert!(!white_dwarfs.is_empty());
+        let faint = white_dwarfs
+            .iter()
+            .filter(|star| star.luminosity_in_solar_units < 1e-2)
+            .count();
+        assert!(2 * faint > white_dwarfs.len());
+        for star in white_dwarfs {
+            assert!(star.luminosity_in_solar_units <= generator.cooling_table.max_luminosity());
+            assert!(star.mass_in_solar_masses > 0.4 && star.mass_in_solar_masses < 1.4);
+        }
+    }
+
+    #[test]
+    fn band_limit_keeps_only_stars_brighter_in_that_band() {
+        let params = GenerationParameters {
+            magnitude_limit_band: Some(Band::JohnsonV),
+            ..solar_neighbourhood(6.)
+        };
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let mut statistics = GenerationStatistics::default();
+        let mut kept = 0;
+        for (index, chunk) in GalacticChunk::generate_chunks(&params).enumerate() {
+            for star in generator
+                .generate_chunk(index, &chunk, &mut statistics)
+                .stars
+            {
+                assert!(star.apparent_magnitudes.johnson_v < params.apparent_magnitude_limit);
+                kept += 1;
+            }
+        }
+        assert!(kept > 0);
+    }
+
+    #[test]
+    fn apparent_magnitudes_follow_distance_modulus() {
+        let params = solar_neighbourhood(6.);
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let chunk = GalacticChunk::generate_chunks(&params)
+            .nth(3)
+            .expect("The chunk should exist");
+        for star in generator
+            .generate_chunk(3, &chunk, &mut GenerationStatistics::default())
+            .stars
+        {
+            let distance = distance_in_pc(star.position_in_pc, params.observer_position_in_pc());
+            let modulus = star.apparent_magnitudes.johnson_v - star.absolute_magnitudes.johnson_v;
+            assert!((modulus - distance_modulus(distance)).abs() < 1e-10);
+            assert!(star.apparent_magnitudes.bolometric < params.apparent_magnitude_limit);
+        }
+    }
+
+    #[test]
+    fn colours_are_only_attached_on_request() {
+        let params = solar_neighbourhood(6.);
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let chunk = GalacticChunk::generate_chunks(&params)
+            .nth(3)
+            .expect("The chunk should exist");
+        for star in generator
+            .generate_chunk(3, &chunk, &mut GenerationStatistics::default())
+            .stars
+        {
+            assert!(star.colour.is_none());
+        }
+
+        let params = GenerationParameters {
+            include_colour: true,
+            ..params
+        };
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        for star in generator
+            .generate_chunk(3, &chunk, &mut GenerationStatistics::default())
+            .stars
+        {
+            let colour = star.colour.expect("Every kept star should have a colour");
+            assert!(colour.relative_brightness > 1.);
+        }
+    }
+
+    #[test]
+    fn extinction_dims_kept_stars() {
+        let params = GenerationParameters {
+            extinction: Some(ExtinctionModel::ExponentialDisc),
+            ..solar_neighbourhood(6.)
+        };
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let mut kept = 0;
+        for (index, chunk) in GalacticChunk::generate_chunks(&params).enumerate() {
+            for star in generator
+                .generate_chunk(index, &chunk, &mut GenerationStatistics::default())
+                .stars
+            {
+                let distance =
+                    distance_in_pc(star.position_in_pc, params.observer_position_in_pc());
+                let dimming = star.apparent_magnitudes.johnson_v
+                    - star.absolute_magnitudes.johnson_v
+                    - distance_modulus(distance);
+                assert!(star.visual_extinction_in_mag > 0.);
+                assert!((dimming - star.visual_extinction_in_mag).abs() < 1e-10);
+                assert!(star.apparent_magnitudes.bolometric < params.apparent_magnitude_limit);
+                kept += 1;
+            }
+        }
+        assert!(kept > 0);
+    }
+
+    #[test]
+    fn nearby_disc_stars_move_with_the_sun() {
+        let params = solar_neighbourhood(6.);
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let chunk = GalacticChunk::generate_chunks(&params)
+            .nth(3)
+            .expect("The chunk should exist");
+        let stars = generator
+            .generate_chunk(3, &chunk, &mut GenerationStatistics::default())
+            .stars;
+        let disc_stars: Vec<_> = stars
+            .iter()
+            .filter(|star| matches!(star.population, Population::ThinDisc(_)))
+            .collect();
+        assert!(!disc_stars.is_empty());
+        let mean_radial_velocity = disc_stars
+            .iter()
+            .map(|star| star.astrometry.radial_velocity_in_km_per_s.abs())
+            .sum::<f64>()
+            / disc_stars.len() as f64;
+        assert!(mean_radial_velocity < 50.);
+        for star in disc_stars {
+            let (_, vy, _) = star.velocity_in_km_per_s;
+            // The default frame is right-handed, so the disc rotates towards negative y.
+            assert!(vy < 0.);
+        }
+    }
+
+    #[test]
+    fn stars_at_another_epoch_have_moved_along_their_velocity() {
+        let present = solar_neighbourhood(6.);
+        let future = GenerationParameters {
+            epoch: Epoch {
+                offset_in_kyr: 100.,
+                propagation: Propagation::Linear,
+            },
+            ..solar_neighbourhood(6.)
+        };
+        let chunk = GalacticChunk::generate_chunks(&present)
+            .nth(3)
+            .expect("The chunk should exist");
+        let generate = |params: &GenerationParameters| {
+            Generator::new(params)
+                .expect("The generator should be valid")
+                .generate_chunk(3, &chunk, &mut GenerationStatistics::default())
+                .stars
+        };
+        let now = generate(&present);
+        let then = generate(&future);
+        let mut matched = 0;
+        for star in then.iter() {
+            let Some(original) = now
+                .iter()
+                .find(|s| s.initial_mass_in_solar_masses == star.initial_mass_in_solar_masses)
+            else {
+                continue;
+            };
+            matched += 1;
+            let travelled = distance_in_pc(star.position_in_pc, original.position_in_pc);
+            let (vx, vy, vz) = original.velocity_in_km_per_s;
+            let speed = (vx * vx + vy * vy + vz * vz).sqrt();
+            assert!((travelled - 0.1022712 * speed).abs() < 1e-3);
+        }
+        assert!(matched > 0);
+    }
+
+    #[test]
+    fn companions_are_only_sampled_on_request() {
+        let params = solar_neighbourhood(6.);
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let chunk = GalacticChunk::generate_chunks(&params)
+            .nth(3)
+            .expect("The chunk should exist");
+        for star in generator
+            .generate_chunk(3, &chunk, &mut GenerationStatistics::default())
+            .stars
+        {
+            assert!(star.companions.is_empty());
+            assert!(star.system_apparent_magnitudes.is_none());
+        }
+    }
+
+    #[test]
+    fn systems_shine_with_the_combined_light_of_their_components() {
+        let params = GenerationParameters {
+            include_companions: true,
+            ..solar_neighbourhood(6.)
+        };
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let mut statistics = GenerationStatistics::default();
+        let mut systems = 0;
+        for (index, chunk) in GalacticChunk::generate_chunks(&params).enumerate() {
+            for star in generator
+                .generate_chunk(index, &chunk, &mut statistics)
+                .stars
+            {
+                let Some(system) = star.system_apparent_magnitudes else {
+                    continue;
+                };
+                systems += 1;
+                assert!(system.bolometric < star.apparent_magnitudes.bolometric);
+                for companion in star.companions.iter() {
+                    let orbit = companion.orbit.expect("Companions have an orbit");
+                    assert!(orbit.resolved);
+                    assert!(
+                        companion.initial_mass_in_solar_masses <= star.initial_mass_in_solar_masses
+                    );
+                    assert_eq!(star.position_in_pc, companion.position_in_pc);
+                }
+            }
+        }
+        assert!(systems > 0);
+        assert!(statistics.companions_kept > 0);
+    }
+
+    #[test]
+    fn living_companions_of_dark_primaries_are_kept() {
+        let params = GenerationParameters {
+            include_companions: true,
+            ..solar_neighbourhood(6.)
+        };
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let age_in_gyr = 0.01;
+        let primary_mass = 40.;
+        assert!(lifetime_in_gyr(primary_mass) < age_in_gyr);
+        let mut primary = evolved_star(
+            Population::ThinDisc(Subpopulation::Alive),
+            primary_mass,
+            age_in_gyr,
+            0.,
+            (8210., 0., 0.),
+            (0., 0., 0.),
+        );
+        assert_eq!(primary.luminosity_in_solar_units, 0.);
+        let orbit = Orbit {
+            mass_ratio: 0.2,
+            period_in_days: 1000.,
+            eccentricity: 0.,
+            semi_major_axis_in_au: 5.,
+            angular_separation_in_arcsec: 0.,
+            resolved: true,
+        };
+        let companion = companion(&primary, orbit);
+        assert!(companion.luminosity_in_solar_units > 0.);
+        primary.companions.push(companion.clone());
+
+        let kept = generator
+            .observe(primary)
+            .expect("The living companion should be visible");
+        assert_eq!(
+            kept.initial_mass_in_solar_masses,
+            companion.initial_mass_in_solar_masses
+        );
+        assert!(kept.orbit.is_none());
+        assert!(kept.companions.is_empty());
+        assert!(kept.apparent_magnitudes.bolometric < params.apparent_magnitude_limit);
+    }
+
+    #[test]
+    fn close_companions_merge_below_the_resolution() {
+        let params = GenerationParameters {
+            include_companions: true,
+            resolution_in_arcsec: Some(1.),
+            ..solar_neighbourhood(6.)
+        };
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let mut statistics = GenerationStatistics::default();
+        for (index, chunk) in GalacticChunk::generate_chunks(&params).enumerate() {
+            for star in generator
+                .generate_chunk(index, &chunk, &mut statistics)
+                .stars
+            {
+                for companion in star.companions.iter() {
+                    let orbit = companion.orbit.expect("Companions have an orbit");
+                    assert_eq!(orbit.angular_separation_in_arcsec >= 1., orbit.resolved);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn variable_stars_are_only_classified_on_request() {
+        let mut classified = 0;
+        for include_variability in [false, true] {
+            let params = GenerationParameters {
+                include_variability,
+                ..solar_neighbourhood(6.)
+            };
+            let generator = Generator::new(&params).expect("The generator should be valid");
+            let mut statistics = GenerationStatistics::default();
+            for (index, chunk) in GalacticChunk::generate_chunks(&params).enumerate() {
+                for star in generator
+                    .generate_chunk(index, &chunk, &mut statistics)
+                    .stars
+                {
+                    if let Some(variability) = star.variability {
+                        assert!(include_variability);
+                        classified += 1;
+                        let mean =
+                            star.apparent_magnitude_at(None, 0.) - variability.magnitude_offset(0.);
+                        assert!((mean - star.apparent_magnitudes.bolometric).abs() < 1e-10);
+                    }
+                }
+            }
+        }
+        assert!(classified > 0);
+    }
+
+    #[test]
+    fn cluster_members_share_age_and_metallicity() {
+        let mut members: Vec<Star> = Vec::new();
+        for include_clusters in [false, true] {
+            let params = GenerationParameters {
+                include_clusters,
+                ..solar_neighbourhood(9.)
+            };
+            let generator = Generator::new(&params).expect("The generator should be valid");
+            let mut statistics = GenerationStatistics::default();
+            for (index, chunk) in GalacticChunk::generate_chunks(&params).enumerate() {
+                for star in generator
+                    .generate_chunk(index, &chunk, &mut statistics)
+                    .stars
+                {
+                    if star.cluster_id.is_some() {
+                        assert!(include_clusters);
+                        members.push(star);
+                    }
+                }
+            }
+        }
+        assert!(!members.is_empty());
+        for member in members.iter() {
+            for other in members
+                .iter()
+                .filter(|other| other.cluster_id == member.cluster_id)
+            {
+                assert_eq!(member.age_in_gyr, other.age_in_gyr);
+                assert_eq!(member.metallicity_in_dex, other.metallicity_in_dex);
+                assert_eq!(member.population, other.population);
+            }
+        }
+    }
+
+    fn globular_cluster(distance_in_pc: f64) -> StarCluster {
+        StarCluster {
+            id: 7,
+            kind: ClusterKind::GlobularCluster,
+            age_in_gyr: 14.,
+            metallicity_in_dex: -1.5,
+            center_in_pc: (8200., 0., distance_in_pc),
+            velocity_in_km_per_s: (0., 0., 0.),
+            mass_in_solar_masses: 2e5,
+            scale_radius_in_pc: 2.,
+        }
+    }
+
+    fn generate_globular_cluster(
+        params: &GenerationParameters,
+        cluster: &StarCluster,
+    ) -> GeneratedChunk {
+        let generator = Generator::new(params).expect("The generator should be valid");
+        let spheroid = generator
+            .components
+            .iter()
+            .find(|component| component.age_bin.population == Population::Spheroid)
+            .expect("There should be a spheroid component");
+        let mut generated = GeneratedChunk::default();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        generator.generate_cluster(
+            spheroid,
+            cluster,
+            &mut rng,
+            &mut GenerationStatistics::default(),
+            &mut generated,
+        );
+        generated
+    }
+
+    #[test]
+    fn distant_globular_clusters_are_seen_as_single_objects() {
+        let params = GenerationParameters {
+            magnitude_limit_band: Some(Band::JohnsonV),
+            ..solar_neighbourhood(10.)
+        };
+        let cluster = globular_cluster(10_000.);
+        let generated = generate_globular_cluster(&params, &cluster);
+        assert!(generated.stars.is_empty());
+        assert_eq!(1, generated.unresolved_clusters.len());
+        let unresolved = &generated.unresolved_clusters[0];
+        assert_eq!(cluster, unresolved.cluster);
+        assert!(
+            unresolved.absolute_magnitudes.johnson_b > unresolved.absolute_magnitudes.johnson_v
+        );
+        assert_diff!(
+            unresolved.absolute_magnitudes.johnson_v + distance_modulus(10_000.),
+            unresolved.apparent_magnitudes.johnson_v,
+            1e-10
+        );
+    }
+
+    #[test]
+    fn integrated_light_matches_the_sum_over_members() {
+        let params = solar_neighbourhood(10.);
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let spheroid = generator
+            .components
+            .iter()
+            .find(|component| component.age_bin.population == Population::Spheroid)
+            .expect("There should be a spheroid component");
+        let cluster = globular_cluster(10_000.);
+        let (magnitudes, _) = generator.integrated_light(spheroid, &cluster);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let number = (cluster.mass_in_solar_masses / spheroid.imf.mean_mass()) as usize;
+        let luminosity: f64 = (0..number)
+            .map(|_| {
+                let mass = spheroid.imf.sample_above(spheroid.imf.min_mass(), &mut rng);
+                evolve(mass, cluster.age_in_gyr).luminosity_in_solar_units
+            })
+            .sum();
+        assert_diff!(
+            SOLAR_ABSOLUTE_MAGNITUDE - 2.5 * luminosity.log10(),
+            magnitudes.bolometric,
+            0.05
+        );
+    }
+
+    #[test]
+    fn faint_unresolved_globular_clusters_are_discarded() {
+        let params = solar_neighbourhood(3.);
+        let generated = generate_globular_cluster(&params, &globular_cluster(10_000.));
+        assert!(generated.stars.is_empty());
+        assert!(generated.unresolved_clusters.is_empty());
+    }
+
+    #[test]
+    fn nearby_globular_clusters_are_resolved_into_stars() {
+        let params = solar_neighbourhood(14.);
+        let generated = generate_globular_cluster(&params, &globular_cluster(3_000.));
+        assert!(generated.unresolved_clusters.is_empty());
+        assert!(!generated.stars.is_empty());
+        for star in generated.stars.iter() {
+            assert_eq!(Some(7), star.cluster_id);
+            assert_eq!(-1.5, star.metallicity_in_dex);
+        }
+    }
+
+    #[test]
+    fn sky_coordinates_agree_with_distance() {
+        let params = GenerationParameters {
+            sky_frames: vec![SkyFrame::Galactic, SkyFrame::Equatorial],
+            ..solar_neighbourhood(6.)
+        };
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let chunk = GalacticChunk::generate_chunks(&params)
+            .nth(3)
+            .expect("The chunk should exist");
+        for star in generator
+            .generate_chunk(3, &chunk, &mut GenerationStatistics::default())
+            .stars
+        {
+            let galactic = star
+                .sky_coordinates
+                .galactic
+                .expect("Galactic coordinates were requested");
+            let distance = distance_in_pc(star.position_in_pc, params.observer_position_in_pc());
+            assert!((galactic.distance_in_pc - distance).abs() < 1e-10);
+            assert!(star.sky_coordinates.equatorial.is_some());
+            assert!(star.sky_coordinates.ecliptic.is_none());
+        }
+    }
+
+    #[test]
+    fn flipping_the_x_axis_mirrors_the_stars() {
+        let generate = |x_axis| {
+            let params = GenerationParameters {
+                galactocentric_frame: GalactocentricFrame {
+                    x_axis,
+                    ..Default::default()
+                },
+                observer_position_in_pc: None,
+                ..solar_neighbourhood(6.)
+            };
+            let stars: Vec<Star> = Stars::new(&params)
+                .expect("The parameters should be valid")
+                .collect();
+            (params.galactocentric_frame, stars)
+        };
+        let (towards_frame, towards) = generate(XAxisDirection::TowardsSun);
+        let (away_frame, away) = generate(XAxisDirection::AwayFromSun);
+
+        assert!(!towards.is_empty());
+        assert_eq!(towards.len(), away.len());
+        for (star, mirrored) in towards.iter().zip(away.iter()) {
+            let position = towards_frame.model_frame_vector(star.position_in_pc);
+            let mirrored_position = away_frame.model_frame_vector(mirrored.position_in_pc);
+            assert_diff!(position.0, mirrored_position.0, 1e-6);
+            assert_diff!(position.1, mirrored_position.1, 1e-6);
+            assert_diff!(position.2, mirrored_position.2, 1e-6);
+            assert_diff!(
+                star.apparent_magnitudes.bolometric,
+                mirrored.apparent_magnitudes.bolometric,
+                1e-6
+            );
+            assert_eq!(
+                star.initial_mass_in_solar_masses,
+                mirrored.initial_mass_in_solar_masses
+            );
+        }
+    }
+
+    #[test]
+    fn generation_is_reproducible() {
+        let params = solar_neighbourhood(6.);
+        let generator = Generator::new(&params).expect("The generator should be valid");
+        let chunk = GalacticChunk::generate_chunks(&params)
+            .next()
+            .expect("The chunk should exist");
+        let first = generator
+            .generate_chunk(0, &chunk, &mut GenerationStatistics::default())
+            .stars;
+        let second = generator
+            .generate_chunk(0, &chunk, &mut GenerationStatistics::default())
+            .stars;
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn stars_are_yielded_chunk_by_chunk_in_catalogue_order() {
+        let params = solar_neighbourhood(6.);
+        let generator = Generator::new(&params).expect("The parameters should be valid");
+        let mut statistics = GenerationStatistics::default();
+        let expected: Vec<Star> = GalacticChunk::generate_chunks(&params)
+            .enumerate()
+            .flat_map(|(index, chunk)| {
+                generator
+                    .generate_chunk(index, &chunk, &mut statistics)
+                    .stars
+            })
+            .collect();
+
+        let mut stars = Stars::new(&params).expect("The parameters should be valid");
+        let first = stars.next().expect("There should be stars");
+        assert!(stars.chunks_completed() < stars.total_chunks());
+        let yielded: Vec<Star> = std::iter::once(first).chain(&mut stars).collect();
+        assert!(yielded == expected);
+        assert_eq!(stars.chunks_completed(), stars.total_chunks());
+        assert_eq!(stars.statistics(), &statistics);
+    }
+
+    #[test]
+    fn unresolved_clusters_are_collected_while_iterating() {
+        // Seen from far above the disc, the globular clusters of the inner halo are too faint to be resolved.
+        let params = GenerationParameters {
+            observer_position_in_pc: Some((0., 0., 30_000.)),
+            apparent_magnitude_limit: 10.,
+            magnitude_limit_band: Some(Band::JohnsonV),
+            max_distance_in_pc: 30_000.,
+            chunksize_in_pc: 2_000.,
+            importance_sampling: true,
+            include_globular_clusters: true,
+            ..Default::default()
+        };
+        let generator = Generator::new(&params).expect("The parameters should be valid");
+        let mut statistics = GenerationStatistics::default();
+        let expected: Vec<UnresolvedCluster> = GalacticChunk::generate_chunks(&params)
+            .enumerate()
+            .flat_map(|(index, chunk)| {
+                generator
+                    .generate_chunk(index, &chunk, &mut statistics)
+                    .unresolved_clusters
+            })
+            .collect();
+        assert!(!expected.is_empty());
+
+        let mut stars = Stars::new(&params).expect("The parameters should be valid");
+        assert!(stars.unresolved_clusters().is_empty());
+        stars.by_ref().for_each(drop);
+        assert!(stars.unresolved_clusters() == expected.as_slice());
+    }
+}
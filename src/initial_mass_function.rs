@@ -0,0 +1,269 @@
+use rand::Rng;
+
+use crate::population::Population;
+
+/// The minimal mass required to fuse hydrogen, in units of solar masses.
+const HYDROGEN_BURNING_LIMIT: f64 = 0.07;
+/// The most massive stars considered, in units of solar masses.
+const MAX_STELLAR_MASS: f64 = 120.;
+/// Upper mass limit for the old thick disc and spheroid, in units of solar masses.
+///
+/// Heavier stars have long since died in these populations, and their shallow IMF would otherwise be dominated by them.
+const OLD_POPULATION_MAX_MASS: f64 = 1.1;
+/// Lower mass limit of the bulge IMF, in units of solar masses.
+const BULGE_MIN_MASS: f64 = 0.7;
+
+/// A piecewise power law distribution of initial stellar masses.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#initial-mass-functions>
+#[derive(Debug, Clone)]
+pub struct InitialMassFunction {
+    segments: Vec<Segment>,
+}
+
+/// A section of the IMF where it follows `A m^(-alpha)`.
+#[derive(Debug, Clone)]
+struct Segment {
+    lower: f64,
+    upper: f64,
+    alpha: f64,
+    /// The probability of a star falling into this segment.
+    probability: f64,
+}
+
+impl InitialMassFunction {
+    /// The initial mass function of the given population.
+    pub fn new(population: Population) -> Self {
+        match population {
+            Population::ThinDisc(_) => Self::from_power_laws(&[
+                (HYDROGEN_BURNING_LIMIT, 0.6, 1.1),
+                (0.6, 1., 1.6),
+                (1., MAX_STELLAR_MASS, 3.0),
+            ]),
+            Population::ThickDisc(_) | Population::Spheroid => {
+                Self::from_power_laws(&[(HYDROGEN_BURNING_LIMIT, OLD_POPULATION_MAX_MASS, 0.5)])
+            }
+            Population::Bulge => Self::from_power_laws(&[(BULGE_MIN_MASS, MAX_STELLAR_MASS, 2.35)]),
+        }
+    }
+
+    /// Builds a continuous, normalised IMF from `(lower, upper, alpha)` triples.
+    fn from_power_laws(power_laws: &[(f64, f64, f64)]) -> Self {
+        let mut segments = Vec::with_capacity(power_laws.len());
+        let mut prefactor = 1.;
+        let mut total = 0.;
+        for (i, &(lower, upper, alpha)) in power_laws.iter().enumerate() {
+            if i > 0 {
+                let previous_alpha = power_laws[i - 1].2;
+                prefactor *= lower.powf(alpha - previous_alpha);
+            }
+            let probability = prefactor * integral_of_power(lower, upper, -alpha);
+            total += probability;
+            segments.push(Segment {
+                lower,
+                upper,
+                alpha,
+                probability,
+            });
+        }
+        for segment in segments.iter_mut() {
+            segment.probability /= total;
+        }
+        Self { segments }
+    }
+
+    /// The lowest initial mass, in units of solar masses.
+    pub fn min_mass(&self) -> f64 {
+        self.segments.first().map_or(0., |s| s.lower)
+    }
+
+    /// The highest initial mass, in units of solar masses.
+    pub fn max_mass(&self) -> f64 {
+        self.segments.last().map_or(0., |s| s.upper)
+    }
+
+    /// The expectation value of the initial mass, in units of solar masses.
+    pub fn mean_mass(&self) -> f64 {
+        self.segments
+            .iter()
+            .map(|s| {
+                let norm = integral_of_power(s.lower, s.upper, -s.alpha);
+                s.probability * integral_of_power(s.lower, s.upper, 1. - s.alpha) / norm
+            })
+            .sum()
+    }
+
+    /// The probability density of the initial mass at the given mass, in units of inverse solar masses.
+    pub fn probability_density(&self, mass: f64) -> f64 {
+        self.segments
+            .iter()
+            .find(|s| s.lower <= mass && mass <= s.upper)
+            .map_or(0., |s| {
+                s.probability * mass.powf(-s.alpha) / integral_of_power(s.lower, s.upper, -s.alpha)
+            })
+    }
+
+    /// The probability of a star having an initial mass above the given one.
+    pub fn fraction_above(&self, mass: f64) -> f64 {
+        1. - self.cumulative_probability(mass)
+    }
+
+    /// Samples an initial mass in units of solar masses from the part of the IMF above the given mass.
+    ///
+    /// This uses the inverse transform method. Passing the lower end of the IMF samples the full distribution.
+    pub(crate) fn sample_above<R: Rng>(&self, mass: f64, rng: &mut R) -> f64 {
+        let lower = self.cumulative_probability(mass);
+        let u: f64 = rng.gen();
+        self.inverse_cumulative_probability(lower + u * (1. - lower))
+            .max(mass)
+    }
+
+    fn cumulative_probability(&self, mass: f64) -> f64 {
+        let mut total = 0.;
+        for segment in self.segments.iter() {
+            if mass >= segment.upper {
+                total += segment.probability;
+            } else if mass > segment.lower {
+                total += segment.probability * segment.cumulative_probability(mass);
+            }
+        }
+        total.clamp(0., 1.)
+    }
+
+    fn inverse_cumulative_probability(&self, mut u: f64) -> f64 {
+        for segment in self.segments.iter() {
+            if u < segment.probability {
+                return segment.sample(u / segment.probability);
+            }
+            u -= segment.probability;
+        }
+        self.max_mass()
+    }
+}
+
+impl Segment {
+    /// The probability of a star in this segment having a mass below the given one.
+    fn cumulative_probability(&self, mass: f64) -> f64 {
+        integral_of_power(self.lower, mass, -self.alpha)
+            / integral_of_power(self.lower, self.upper, -self.alpha)
+    }
+
+    /// Maps a uniform number in [0,1) onto the mass range of this segment.
+    fn sample(&self, u: f64) -> f64 {
+        let exponent = 1. - self.alpha;
+        if exponent.abs() < f64::EPSILON {
+            return self.lower * (self.upper / self.lower).powf(u);
+        }
+        let lo = self.lower.powf(exponent);
+        let hi = self.upper.powf(exponent);
+        (lo + u * (hi - lo)).powf(1. / exponent)
+    }
+}
+
+/// The integral of `m^exponent` from `lower` to `upper`.
+fn integral_of_power(lower: f64, upper: f64, exponent: f64) -> f64 {
+    let e = exponent + 1.;
+    if e.abs() < f64::EPSILON {
+        (upper / lower).ln()
+    } else {
+        (upper.powf(e) - lower.powf(e)) / e
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{assert_ratio, population::Subpopulation};
+
+    use super::*;
+
+    #[test]
+    fn segment_probabilities_are_normalised() {
+        let imf = InitialMassFunction::new(Population::ThinDisc(Subpopulation::Alive));
+        let total: f64 = imf.segments.iter().map(|s| s.probability).sum();
+        assert_ratio!(1., total, 1e-10);
+    }
+
+    #[test]
+    fn thin_disc_imf_is_continuous() {
+        let imf = InitialMassFunction::new(Population::ThinDisc(Subpopulation::Alive));
+        let density = |segment: &Segment, m: f64| {
+            let norm = integral_of_power(segment.lower, segment.upper, -segment.alpha);
+            segment.probability / norm * m.powf(-segment.alpha)
+        };
+        for pair in imf.segments.windows(2) {
+            let boundary = pair[0].upper;
+            assert_ratio!(
+                density(&pair[0], boundary),
+                density(&pair[1], boundary),
+                1e-10
+            );
+        }
+    }
+
+    #[test]
+    fn probability_density_integrates_to_the_fraction_in_between() {
+        const STEPS: usize = 10_000;
+        let imf = InitialMassFunction::new(Population::ThinDisc(Subpopulation::Alive));
+        let (lower, upper) = (0.3_f64, 3_f64);
+        let ratio = upper / lower;
+        let integral: f64 = (0..STEPS)
+            .map(|i| {
+                let m0 = lower * ratio.powf(i as f64 / STEPS as f64);
+                let m1 = lower * ratio.powf((i + 1) as f64 / STEPS as f64);
+                imf.probability_density((m0 * m1).sqrt()) * (m1 - m0)
+            })
+            .sum();
+        assert_ratio!(
+            integral,
+            imf.fraction_above(lower) - imf.fraction_above(upper),
+            1e-4
+        );
+        assert_eq!(imf.probability_density(imf.max_mass() * 2.), 0.);
+    }
+
+    #[test]
+    fn fraction_above_matches_sampling() {
+        const N: usize = 200_000;
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let imf = InitialMassFunction::new(Population::ThinDisc(Subpopulation::Alive));
+        for threshold in [0.1, 0.6, 0.8, 2.] {
+            let above = (0..N)
+                .filter(|_| imf.sample_above(imf.min_mass(), &mut rng) > threshold)
+                .count();
+            assert_ratio!(imf.fraction_above(threshold), above as f64 / N as f64, 0.05);
+        }
+    }
+
+    #[test]
+    fn samples_above_threshold_stay_above_it() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        let imf = InitialMassFunction::new(Population::ThinDisc(Subpopulation::Alive));
+        for _ in 0..10_000 {
+            let mass = imf.sample_above(0.8, &mut rng);
+            assert!((0.8..=imf.max_mass()).contains(&mass));
+        }
+    }
+
+    #[test]
+    fn sampled_mean_matches_mean_mass() {
+        const N: usize = 200_000;
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for population in [
+            Population::ThinDisc(Subpopulation::Alive),
+            Population::ThickDisc(Subpopulation::Alive),
+            Population::Bulge,
+        ] {
+            let imf = InitialMassFunction::new(population);
+            let mut total = 0.;
+            for _ in 0..N {
+                let mass = imf.sample_above(imf.min_mass(), &mut rng);
+                assert!(mass >= imf.min_mass() && mass <= imf.max_mass());
+                total += mass;
+            }
+            assert_ratio!(imf.mean_mass(), total / N as f64, 0.05);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+/// Finds the index of the grid point below the value and the relative position between it and the next one.
+///
+/// The grid must be sorted in ascending order and contain at least two points. Values outside the grid are clamped to its edges.
+pub(crate) fn bracket(grid: &[f64], value: f64) -> (usize, f64) {
+    let last = grid.len() - 2;
+    let i = grid
+        .partition_point(|&point| point <= value)
+        .saturating_sub(1)
+        .min(last);
+    let t = ((value - grid[i]) / (grid[i + 1] - grid[i])).clamp(0., 1.);
+    (i, t)
+}
+
+/// Collects the distinct values of a column, sorted in ascending order.
+pub(crate) fn grid_axis(rows: &[Vec<f64>], column: usize) -> Vec<f64> {
+    let mut axis: Vec<f64> = rows.iter().map(|row| row[column]).collect();
+    axis.sort_by(f64::total_cmp);
+    axis.dedup();
+    axis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_inside_the_grid_are_bracketed() {
+        let grid = [0., 1., 3.];
+        assert_eq!(bracket(&grid, 0.5), (0, 0.5));
+        assert_eq!(bracket(&grid, 2.), (1, 0.5));
+        assert_eq!(bracket(&grid, 1.), (1, 0.));
+    }
+
+    #[test]
+    fn values_outside_the_grid_are_clamped() {
+        let grid = [0., 1., 3.];
+        assert_eq!(bracket(&grid, -1.), (0, 0.));
+        assert_eq!(bracket(&grid, 5.), (1, 1.));
+    }
+}
@@ -0,0 +1,334 @@
+use astro_units::time::gigayear;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+use uom::si::f64::Time;
+
+use crate::{
+    coordinates::equatorial_vector, galactic_potential::GalacticPotential,
+    galactocentric_frame::GalactocentricFrame, population::Population,
+};
+
+/// The constant `k` in the Strömberg relation `v_a = σ_U^2 / k` for the thin disc, in units of km/s (Dehnen & Binney 1998).
+const STROMBERG_CONSTANT_IN_KM_PER_S: f64 = 80.;
+/// Asymmetric drift of the thick disc, in units of km/s.
+const THICK_DISC_ASYMMETRIC_DRIFT_IN_KM_PER_S: f64 = 53.;
+/// Velocity of the sun relative to the local standard of rest as (U, V, W), in units of km/s (Schönrich, Binney & Dehnen 2010).
+///
+/// U points towards the galactic center, V in the direction of rotation and W towards the north galactic pole.
+const SOLAR_PECULIAR_VELOCITY_IN_KM_PER_S: (f64, f64, f64) = (11.1, 12.24, 7.25);
+/// One astronomical unit per year, in units of km/s.
+const KM_PER_S_PER_AU_PER_YR: f64 = 4.740_470_464;
+
+/// The distribution of space velocities of a population.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#kinematics>
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct VelocityEllipsoid {
+    /// Dispersion towards the galactic center, in units of km/s.
+    pub(crate) sigma_u: f64,
+    /// Dispersion in the direction of rotation, in units of km/s.
+    pub(crate) sigma_v: f64,
+    /// Dispersion perpendicular to the galactic plane, in units of km/s.
+    pub(crate) sigma_w: f64,
+    /// How much the mean rotation lags behind the rotation curve, in units of km/s. `None` for populations without net rotation.
+    pub(crate) asymmetric_drift: Option<f64>,
+}
+
+impl VelocityEllipsoid {
+    /// The mean velocity in the direction of rotation at the given galactocentric radius, in units of km/s.
+    ///
+    /// The rotation curve is the circular velocity of the potential, so it depends on the dark matter halo.
+    pub(crate) fn mean_rotation_velocity(
+        &self,
+        radius_in_pc: f64,
+        potential: &GalacticPotential,
+    ) -> f64 {
+        match self.asymmetric_drift {
+            Some(drift) => potential.circular_velocity_in_km_per_s(radius_in_pc) - drift,
+            None => 0.,
+        }
+    }
+}
+
+/// The velocity ellipsoid of a population with stars of the given age.
+///
+/// The dispersions are those of Table 4 of Robin2003. Thin disc white dwarfs follow the thin disc stars of the same age.
+pub(crate) fn velocity_ellipsoid(population: Population, age: Time) -> VelocityEllipsoid {
+    let (sigma_u, sigma_v, sigma_w) = match population {
+        Population::ThinDisc(_) => {
+            if age < Time::new::<gigayear>(0.15) {
+                (16.7, 10.8, 6.0)
+            } else if age < Time::new::<gigayear>(1.) {
+                (19.8, 12.8, 8.0)
+            } else if age < Time::new::<gigayear>(2.) {
+                (27.2, 17.6, 10.0)
+            } else if age < Time::new::<gigayear>(3.) {
+                (30.2, 19.5, 13.2)
+            } else if age < Time::new::<gigayear>(5.) {
+                (36.7, 23.7, 15.8)
+            } else if age < Time::new::<gigayear>(7.) {
+                (43.1, 27.8, 17.4)
+            } else {
+                (43.1, 27.8, 17.5)
+            }
+        }
+        Population::ThickDisc(_) => (67., 51., 42.),
+        Population::Spheroid => (131., 106., 85.),
+        Population::Bulge => (113., 115., 100.),
+    };
+    let asymmetric_drift = match population {
+        Population::ThinDisc(_) => Some(sigma_u * sigma_u / STROMBERG_CONSTANT_IN_KM_PER_S),
+        Population::ThickDisc(_) => Some(THICK_DISC_ASYMMETRIC_DRIFT_IN_KM_PER_S),
+        Population::Spheroid | Population::Bulge => None,
+    };
+    VelocityEllipsoid {
+        sigma_u,
+        sigma_v,
+        sigma_w,
+        asymmetric_drift,
+    }
+}
+
+/// Samples the space velocity of a star in units of km/s, with position and velocity given in the model frame.
+pub(crate) fn sample_velocity<R: Rng>(
+    ellipsoid: &VelocityEllipsoid,
+    model_position_in_pc: (f64, f64, f64),
+    potential: &GalacticPotential,
+    rng: &mut R,
+) -> (f64, f64, f64) {
+    let (x, y, _) = model_position_in_pc;
+    let radius = x.hypot(y);
+    let u = sample_normal(0., ellipsoid.sigma_u, rng);
+    let v = sample_normal(
+        ellipsoid.mean_rotation_velocity(radius, potential),
+        ellipsoid.sigma_v,
+        rng,
+    );
+    let w = sample_normal(0., ellipsoid.sigma_w, rng);
+    from_cylindrical(model_position_in_pc, (u, v, w))
+}
+
+/// The velocity of the sun in the model frame, in units of km/s.
+///
+/// The local standard of rest moves with the circular velocity of the potential at the solar radius.
+pub(crate) fn solar_velocity_in_km_per_s(
+    frame: &GalactocentricFrame,
+    potential: &GalacticPotential,
+) -> (f64, f64, f64) {
+    let (u, v, w) = SOLAR_PECULIAR_VELOCITY_IN_KM_PER_S;
+    let circular = potential.circular_velocity_in_km_per_s(frame.solar_radius_in_pc);
+    (-u, circular + v, w)
+}
+
+/// Converts a velocity (U, V, W) at the given position into the model frame.
+fn from_cylindrical(
+    model_position_in_pc: (f64, f64, f64),
+    velocity: (f64, f64, f64),
+) -> (f64, f64, f64) {
+    let (x, y, _) = model_position_in_pc;
+    let radius = x.hypot(y);
+    let (cos, sin) = if radius > 0. {
+        (x / radius, y / radius)
+    } else {
+        (1., 0.)
+    };
+    let (u, v, w) = velocity;
+    // U points inwards, V along the direction of rotation, which is +y on the positive x-axis.
+    (-u * cos - v * sin, -u * sin + v * cos, w)
+}
+
+fn sample_normal<R: Rng>(mean: f64, dispersion: f64, rng: &mut R) -> f64 {
+    match Normal::new(mean, dispersion) {
+        Ok(normal) => normal.sample(rng),
+        Err(_) => mean,
+    }
+}
+
+/// The motion of a star as seen by the observer.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#kinematics>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Astrometry {
+    /// Proper motion in right ascension, `μ_α* = μ_α cos δ`, in units of milliarcseconds per year.
+    pub proper_motion_ra_in_mas_per_yr: f64,
+    /// Proper motion in declination, in units of milliarcseconds per year.
+    pub proper_motion_dec_in_mas_per_yr: f64,
+    /// Velocity along the line of sight, positive when receding, in units of km/s.
+    pub radial_velocity_in_km_per_s: f64,
+    /// Velocity perpendicular to the line of sight, in units of km/s.
+    pub tangential_velocity_in_km_per_s: f64,
+}
+
+impl Astrometry {
+    /// The astrometry of a star with the given position and velocity relative to the observer, both in the model frame.
+    pub(crate) fn new(
+        relative_position_in_pc: (f64, f64, f64),
+        relative_velocity_in_km_per_s: (f64, f64, f64),
+        frame: &GalactocentricFrame,
+    ) -> Self {
+        let (px, py, pz) = relative_position_in_pc;
+        let (vx, vy, vz) = relative_velocity_in_km_per_s;
+        let distance = (px * px + py * py + pz * pz).sqrt();
+        if distance <= 0. {
+            return Self::default();
+        }
+        let [x, y, z] = equatorial_vector([px, py, pz], frame);
+        let velocity = equatorial_vector([vx, vy, vz], frame);
+        let right_ascension = y.atan2(x);
+        let declination = (z / distance).clamp(-1., 1.).asin();
+        let (sin_ra, cos_ra) = right_ascension.sin_cos();
+        let (sin_dec, cos_dec) = declination.sin_cos();
+        let dot = |axis: [f64; 3]| axis.iter().zip(velocity.iter()).map(|(a, v)| a * v).sum();
+
+        let radial: f64 = dot([cos_dec * cos_ra, cos_dec * sin_ra, sin_dec]);
+        let along_ra: f64 = dot([-sin_ra, cos_ra, 0.]);
+        let along_dec: f64 = dot([-sin_dec * cos_ra, -sin_dec * sin_ra, cos_dec]);
+        let mas_per_yr = 1000. / (KM_PER_S_PER_AU_PER_YR * distance);
+        Self {
+            proper_motion_ra_in_mas_per_yr: along_ra * mas_per_yr,
+            proper_motion_dec_in_mas_per_yr: along_dec * mas_per_yr,
+            radial_velocity_in_km_per_s: radial,
+            tangential_velocity_in_km_per_s: along_ra.hypot(along_dec),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        assert_diff, assert_ratio, galactic_potential::DarkHalo,
+        galactocentric_frame::DEFAULT_SOLAR_RADIUS_IN_PC, population::Subpopulation,
+    };
+
+    use super::*;
+
+    const SUN: (f64, f64, f64) = (DEFAULT_SOLAR_RADIUS_IN_PC, 0., 0.);
+
+    #[test]
+    fn dispersion_grows_with_age_in_the_thin_disc() {
+        let mut previous = 0.;
+        for age in [0.1, 0.5, 1.5, 2.5, 4., 6., 8.5] {
+            let ellipsoid = velocity_ellipsoid(
+                Population::ThinDisc(Subpopulation::Alive),
+                Time::new::<gigayear>(age),
+            );
+            assert!(ellipsoid.sigma_w > previous);
+            previous = ellipsoid.sigma_w;
+        }
+    }
+
+    fn potential(dark_halo: DarkHalo) -> GalacticPotential {
+        GalacticPotential::new(dark_halo, &GalactocentricFrame::default())
+    }
+
+    #[test]
+    fn rotation_follows_the_circular_velocity_of_the_dark_halo() {
+        let ellipsoid = velocity_ellipsoid(
+            Population::ThinDisc(Subpopulation::Alive),
+            Time::new::<gigayear>(4.),
+        );
+        let frame = GalactocentricFrame::default();
+        let nfw = potential(DarkHalo::Nfw);
+        let isothermal = potential(DarkHalo::PseudoIsothermal);
+        for potential in [&nfw, &isothermal] {
+            for radius in [2000., DEFAULT_SOLAR_RADIUS_IN_PC, 30_000.] {
+                assert_diff!(
+                    potential.circular_velocity_in_km_per_s(radius)
+                        - ellipsoid.asymmetric_drift.expect("The thin disc rotates"),
+                    ellipsoid.mean_rotation_velocity(radius, potential),
+                    1e-10
+                );
+            }
+            let (_, v, _) = solar_velocity_in_km_per_s(&frame, potential);
+            assert_diff!(
+                potential.circular_velocity_in_km_per_s(DEFAULT_SOLAR_RADIUS_IN_PC)
+                    + SOLAR_PECULIAR_VELOCITY_IN_KM_PER_S.1,
+                v,
+                1e-10
+            );
+        }
+        // Both halos are normalised to the same circular velocity at the sun, but the cored halo rises more slowly.
+        for radius in [2000., 30_000.] {
+            let difference = ellipsoid.mean_rotation_velocity(radius, &nfw)
+                - ellipsoid.mean_rotation_velocity(radius, &isothermal);
+            assert!(difference.abs() > 4., "{}", difference);
+        }
+    }
+
+    #[test]
+    fn sampled_velocities_follow_the_ellipsoid() {
+        const N: usize = 50_000;
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let ellipsoid = velocity_ellipsoid(
+            Population::ThinDisc(Subpopulation::Alive),
+            Time::new::<gigayear>(4.),
+        );
+        let velocities: Vec<_> = (0..N)
+            .map(|_| sample_velocity(&ellipsoid, SUN, &potential(DarkHalo::Nfw), &mut rng))
+            .collect();
+        let mean =
+            |f: &dyn Fn(&(f64, f64, f64)) -> f64| velocities.iter().map(f).sum::<f64>() / N as f64;
+        let mean_u = mean(&|v| -v.0);
+        let mean_v = mean(&|v| v.1);
+        let sigma_u = mean(&|v| (v.0 + mean_u).powi(2)).sqrt();
+        let sigma_w = mean(&|v| v.2 * v.2).sqrt();
+        assert_diff!(0., mean_u, 1.);
+        assert_diff!(
+            ellipsoid.mean_rotation_velocity(DEFAULT_SOLAR_RADIUS_IN_PC, &potential(DarkHalo::Nfw)),
+            mean_v,
+            1.
+        );
+        assert_ratio!(ellipsoid.sigma_u, sigma_u, 0.02);
+        assert_ratio!(ellipsoid.sigma_w, sigma_w, 0.02);
+    }
+
+    #[test]
+    fn velocities_rotate_with_the_position() {
+        let ellipsoid = VelocityEllipsoid {
+            sigma_u: 0.,
+            sigma_v: 0.,
+            sigma_w: 0.,
+            asymmetric_drift: Some(0.),
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let (vx, vy, _) = sample_velocity(
+            &ellipsoid,
+            (0., DEFAULT_SOLAR_RADIUS_IN_PC, 0.),
+            &potential(DarkHalo::Nfw),
+            &mut rng,
+        );
+        assert!(vx < 0.);
+        assert_diff!(0., vy, 1e-10);
+    }
+
+    #[test]
+    fn purely_radial_motion_has_no_proper_motion() {
+        let frame = GalactocentricFrame::default();
+        let astrometry = Astrometry::new((30., -40., 120.), (3., -4., 12.), &frame);
+        assert_diff!(13., astrometry.radial_velocity_in_km_per_s, 1e-10);
+        assert_diff!(0., astrometry.tangential_velocity_in_km_per_s, 1e-10);
+        assert_diff!(0., astrometry.proper_motion_ra_in_mas_per_yr, 1e-10);
+        assert_diff!(0., astrometry.proper_motion_dec_in_mas_per_yr, 1e-10);
+    }
+
+    #[test]
+    fn proper_motion_follows_tangential_velocity() {
+        let frame = GalactocentricFrame::default();
+        let distance = 10.;
+        let astrometry = Astrometry::new((0., 0., distance), (30., 40., 0.), &frame);
+        assert_diff!(0., astrometry.radial_velocity_in_km_per_s, 1e-10);
+        assert_diff!(50., astrometry.tangential_velocity_in_km_per_s, 1e-10);
+        let total = astrometry
+            .proper_motion_ra_in_mas_per_yr
+            .hypot(astrometry.proper_motion_dec_in_mas_per_yr);
+        assert_ratio!(
+            1000. * 50. / (KM_PER_S_PER_AU_PER_YR * distance),
+            total,
+            1e-10
+        );
+    }
+}
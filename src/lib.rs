@@ -0,0 +1,116 @@
+//! Generates realistic catalogues of the stars of the Milky Way as seen by an observer anywhere in it.
+//!
+//! The stars are generated lazily by [`Stars`], chunk by chunk, from [`GenerationParameters`]:
+//! ```
+//! use ursa_lumi::{Band, GenerationParameters, Stars};
+//!
+//! let params = GenerationParameters::builder((8200., 0., 25.), 3., 20., 15.)
+//!     .magnitude_limit_band(Band::JohnsonV)
+//!     .seed(42)
+//!     .build()?;
+//! for star in Stars::new(&params)? {
+//!     let magnitude = star.apparent_magnitudes.get(Some(Band::JohnsonV));
+//!     assert!(magnitude <= 3.);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//! Catalogues written by the `ursa_lumi` binary in the `ndjson` or `binary` format can be read again record by record with [`CatalogueReader`],
+//! and complete `binary` catalogues can be searched for the stars in a region of space with [`query_catalogue`].
+//!
+//! The density laws, initial mass functions and the galactic potential the generation is based on are available as well.
+//!
+//! Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md>
+#![deny(missing_docs)]
+#![warn(clippy::unwrap_used)]
+
+mod catalogue;
+mod colour;
+mod coordinates;
+mod dry_run;
+mod epoch;
+mod extinction;
+mod galactic_chunk;
+mod galactic_potential;
+mod galactocentric_frame;
+mod generation_parameters;
+mod generation_statistics;
+mod generator;
+mod initial_mass_function;
+mod interpolation;
+mod kinematics;
+mod luminosity_limit;
+mod mass_density;
+mod metallicity;
+mod multiplicity;
+mod photometry;
+mod population;
+mod progress;
+mod star;
+mod star_cluster;
+mod stellar_evolution;
+mod variability;
+mod white_dwarf;
+
+/// The command line interface of the `ursa_lumi` binary, which is not part of the stable interface.
+#[doc(hidden)]
+pub mod cli;
+
+pub use crate::{
+    catalogue::{
+        index::{query_catalogue, MatchCallback, Query},
+        CatalogueHeader, CatalogueReader, Record,
+    },
+    colour::Colour,
+    coordinates::{
+        EclipticCoordinates, EquatorialCoordinates, GalacticCoordinates, SkyCoordinates, SkyFrame,
+    },
+    epoch::{Epoch, Propagation},
+    extinction::ExtinctionModel,
+    galactic_chunk::{GalacticChunk, GalacticChunks},
+    galactic_potential::{DarkHalo, GalacticPotential},
+    galactocentric_frame::{GalactocentricFrame, Handedness, XAxisDirection},
+    generation_parameters::{GenerationParameters, GenerationParametersBuilder},
+    generation_statistics::GenerationStatistics,
+    generator::Stars,
+    initial_mass_function::InitialMassFunction,
+    kinematics::Astrometry,
+    mass_density::{bulge_number_density_per_pc3, mass_density_in_solar_masses_per_pc3},
+    multiplicity::Orbit,
+    photometry::{Band, Magnitudes},
+    population::{Population, Subpopulation},
+    star::Star,
+    star_cluster::{ClusterKind, StarCluster, UnresolvedCluster},
+    variability::{Eclipse, Variability, VariabilityClass},
+};
+
+#[cfg(test)]
+mod tests {
+    /// Asserts that two numbers differ by less than the given amount.
+    #[macro_export]
+    macro_rules! assert_diff {
+        ($x:expr, $y:expr, $d:expr $(, $($arg:tt)+)?) => {
+            let x_f64: f64 = $x as f64;
+            let y_f64: f64 = $y as f64;
+            let d_f64: f64 = $d as f64;
+
+            assert!((x_f64 - y_f64).abs() < d_f64, $($($arg)+)?);
+        }
+    }
+
+    /// Asserts that the ratio of two numbers deviates from one by no more than the given amount.
+    #[macro_export]
+    macro_rules! assert_ratio {
+        ($x:expr, $y:expr, $max_dev:expr $(, $($arg:tt)+)?) => {
+            let x_f64: f64 = $x as f64;
+            let y_f64: f64 = $y as f64;
+            let dev_f64: f64 = $max_dev as f64;
+
+            let ratio = if x_f64.abs() > y_f64.abs() {
+                x_f64 / y_f64
+            } else {
+                y_f64 / x_f64
+            };
+            assert!((ratio.abs()-1.).abs() <= dev_f64, $($($arg)+)?);
+        }
+    }
+}
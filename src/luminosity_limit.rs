@@ -0,0 +1,47 @@
+/// Absolute bolometric magnitude of the sun.
+pub(crate) const SOLAR_ABSOLUTE_MAGNITUDE: f64 = 4.8;
+
+/// The luminosity in units of the solar luminosity that a star at the given distance must exceed to be brighter than the apparent magnitude limit.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#luminous-intensity>
+pub(crate) fn luminosity_limit(distance_in_pc: f64, apparent_magnitude_limit: f64) -> f64 {
+    let exponent = (SOLAR_ABSOLUTE_MAGNITUDE - 5. - apparent_magnitude_limit) / 2.5;
+    distance_in_pc * distance_in_pc * 10_f64.powf(exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_diff, assert_ratio};
+
+    use super::*;
+
+    fn apparent_magnitude(luminosity_in_solar_units: f64, distance_in_pc: f64) -> f64 {
+        SOLAR_ABSOLUTE_MAGNITUDE
+            - 5.
+            - 2.5 * (luminosity_in_solar_units / (distance_in_pc * distance_in_pc)).log10()
+    }
+
+    #[test]
+    fn sun_at_ten_parsec_is_at_limit_of_its_absolute_magnitude() {
+        assert_ratio!(1., luminosity_limit(10., SOLAR_ABSOLUTE_MAGNITUDE), 1e-10);
+    }
+
+    #[test]
+    fn star_at_luminosity_limit_has_limiting_magnitude() {
+        for (distance, magnitude_limit) in [(1., 0.), (10., 6.5), (1234., 12.)] {
+            let luminosity = luminosity_limit(distance, magnitude_limit);
+            assert_diff!(
+                magnitude_limit,
+                apparent_magnitude(luminosity, distance),
+                1e-10
+            );
+        }
+    }
+
+    #[test]
+    fn luminosity_limit_scales_with_distance_squared() {
+        let near = luminosity_limit(10., 6.);
+        let far = luminosity_limit(100., 6.);
+        assert_ratio!(100. * near, far, 1e-10);
+    }
+}
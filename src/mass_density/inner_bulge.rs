@@ -1,9 +1,39 @@
+use super::{
+    BULGE_CENTRAL_NUMBER_DENSITY_PER_PC3, BULGE_MAJOR_SCALE_LENGTH_IN_PC,
+    BULGE_MINOR_SCALE_LENGTH_IN_PC,
+};
+
+/// Number density of bulge stars inside the cutoff radius, in units of stars per cubic parsec.
+///
+/// The coordinates are given in the frame of reference of the bulge.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#inner-bulge-sqrtx_b2y_b2-leq-r_c>
+pub(super) fn density(x_b: f64, y_b: f64, z_b: f64) -> f64 {
+    BULGE_CENTRAL_NUMBER_DENSITY_PER_PC3 * (-0.5 * r_s_squared(x_b, y_b, z_b)).exp()
+}
+
+pub(super) fn r_s_squared(x_b: f64, y_b: f64, z_b: f64) -> f64 {
+    let x = x_b / BULGE_MAJOR_SCALE_LENGTH_IN_PC;
+    let y = y_b / BULGE_MINOR_SCALE_LENGTH_IN_PC;
+    let z = z_b / BULGE_MINOR_SCALE_LENGTH_IN_PC;
+    let planar = x * x + y * y;
+    (planar * planar + z * z * z * z).sqrt()
+}
+
 #[cfg(test)]
 mod tests {
     use serial_test::serial;
 
+    use crate::assert_ratio;
+
     use super::*;
 
+    #[test]
+    fn density_at_bulge_center_is_central_density() {
+        let rho = density(0., 0., 0.);
+        assert_ratio!(BULGE_CENTRAL_NUMBER_DENSITY_PER_PC3, rho, 1e-10);
+    }
+
     #[test]
     #[ignore]
     #[serial]
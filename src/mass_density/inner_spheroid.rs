@@ -1,9 +1,34 @@
+use super::{SPHEROID_CORE_RADIUS_IN_PC, SPHEROID_POWER_LAW_EXPONENT};
+
+/// Mass density of spheroid stars inside the spheroid core, in units of solar masses per cubic parsec.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#inner-spheroid-a-leq-a_c>
+pub(super) fn density(rho0: f64, solar_radius_in_pc: f64) -> f64 {
+    rho0 * (SPHEROID_CORE_RADIUS_IN_PC / solar_radius_in_pc).powf(SPHEROID_POWER_LAW_EXPONENT)
+}
+
 #[cfg(test)]
 mod tests {
     use serial_test::serial;
 
+    use crate::{
+        assert_ratio, galactocentric_frame::DEFAULT_SOLAR_RADIUS_IN_PC,
+        mass_density::outer_spheroid,
+    };
+
     use super::*;
 
+    #[test]
+    fn density_is_continuous_at_core_radius() {
+        let inner = density(9.32e-6, DEFAULT_SOLAR_RADIUS_IN_PC);
+        let outer = outer_spheroid::density(
+            9.32e-6,
+            SPHEROID_CORE_RADIUS_IN_PC,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+        );
+        assert_ratio!(inner, outer, 1e-10);
+    }
+
     #[test]
     #[ignore]
     #[serial]
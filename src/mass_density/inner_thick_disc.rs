@@ -1,9 +1,39 @@
+use super::{
+    flare_factor, THICK_DISC_HEIGHT_THRESHOLD_IN_PC, THICK_DISC_RADIAL_DECAY_LENGTH_IN_PC,
+    THICK_DISC_SCALE_HEIGHT_IN_PC,
+};
+
+/// Mass density of thick disc stars close to the galactic plane, in units of solar masses per cubic parsec.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#inner-thick-disc-stars-z-leq-x_l>
+pub(super) fn density(rho0: f64, radius: f64, z: f64, solar_radius_in_pc: f64) -> f64 {
+    let k_flare = flare_factor(radius);
+    let x_l = THICK_DISC_HEIGHT_THRESHOLD_IN_PC;
+    let h_z = k_flare * THICK_DISC_SCALE_HEIGHT_IN_PC;
+    let radial = (-(radius - solar_radius_in_pc) / THICK_DISC_RADIAL_DECAY_LENGTH_IN_PC).exp();
+    let vertical = 1. - z * z / (h_z * x_l * (2. + x_l / h_z));
+    rho0 / k_flare * radial * vertical
+}
+
 #[cfg(test)]
 mod tests {
     use serial_test::serial;
 
+    use crate::{assert_ratio, galactocentric_frame::DEFAULT_SOLAR_RADIUS_IN_PC};
+
     use super::*;
 
+    #[test]
+    fn density_at_solar_position_is_rho0() {
+        let rho = density(
+            1.34e-3,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+            0.,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+        );
+        assert_ratio!(1.34e-3, rho, 1e-10);
+    }
+
     #[test]
     #[ignore]
     #[serial]
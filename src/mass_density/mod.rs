@@ -1,7 +1,10 @@
 use astro_units::time::gigayear;
 use uom::si::f64::Time;
 
-use crate::population::{Population, Subpopulation};
+use crate::{
+    galactocentric_frame::GalactocentricFrame,
+    population::{Population, Subpopulation},
+};
 
 mod inner_bulge;
 mod inner_spheroid;
@@ -12,6 +15,196 @@ mod outer_spheroid;
 mod outer_thick_disc;
 mod young_thin_disc;
 
+/// Radius beyond which the disc thickens, in units of parsec.
+const FLARE_RADIUS_IN_PC: f64 = 9500.;
+/// Increase of the flare factor per parsec beyond the flare radius.
+const FLARE_GRADIENT_PER_PC: f64 = 0.545e-6;
+
+/// Height above the plane separating the inner and outer thick disc laws, in units of parsec.
+const THICK_DISC_HEIGHT_THRESHOLD_IN_PC: f64 = 72.;
+/// Radial decay length of the thick disc, in units of parsec.
+const THICK_DISC_RADIAL_DECAY_LENGTH_IN_PC: f64 = 4000.;
+/// Decay height of the thick disc before applying the flare factor, in units of parsec.
+const THICK_DISC_SCALE_HEIGHT_IN_PC: f64 = 1200.;
+
+/// Boundary of the spheroid core, in units of parsec.
+pub(crate) const SPHEROID_CORE_RADIUS_IN_PC: f64 = 500.;
+pub(crate) const SPHEROID_POWER_LAW_EXPONENT: f64 = -2.44;
+
+/// Angle between the bulge major axis and the line perpendicular to the sun - galactic center line, in degrees.
+const BULGE_ALPHA_IN_DEG: f64 = 78.9;
+/// Tilt angle between the bulge plane and the galactic plane, in degrees.
+const BULGE_BETA_IN_DEG: f64 = 3.5;
+/// Roll angle around the bulge major axis, in degrees.
+const BULGE_GAMMA_IN_DEG: f64 = 91.3;
+const BULGE_MAJOR_SCALE_LENGTH_IN_PC: f64 = 1590.;
+const BULGE_MINOR_SCALE_LENGTH_IN_PC: f64 = 424.;
+const BULGE_CUTOFF_RADIUS_IN_PC: f64 = 2540.;
+const BULGE_CENTRAL_NUMBER_DENSITY_PER_PC3: f64 = 13.70;
+
+/// The age used to look up the density parameters of white dwarfs, which follow the oldest thin disc stars.
+const WHITE_DWARF_REFERENCE_AGE_IN_GYR: f64 = 8.5;
+
+/// The mass density of the stars of a population with the given age at a galactocentric position, in units of solar masses per cubic parsec.
+///
+/// Dividing it by [`InitialMassFunction::mean_mass`](crate::InitialMassFunction::mean_mass) gives the number density of stars. The bulge is described by [`bulge_number_density_per_pc3`] instead, so its mass density is zero.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#density-laws>
+pub fn mass_density_in_solar_masses_per_pc3(
+    population: Population,
+    age_in_gyr: f64,
+    position_in_pc: (f64, f64, f64),
+    frame: &GalactocentricFrame,
+) -> f64 {
+    mass_density(
+        population,
+        Time::new::<gigayear>(age_in_gyr),
+        position_in_pc,
+        frame,
+    )
+}
+
+/// The number density of bulge stars at a galactocentric position, in units of stars per cubic parsec.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#density-laws>
+pub fn bulge_number_density_per_pc3(
+    position_in_pc: (f64, f64, f64),
+    frame: &GalactocentricFrame,
+) -> f64 {
+    bulge_number_density(position_in_pc, frame)
+}
+
+/// The expected number of stars per cubic parsec of a population at a position given in parsec in the galactocentric frame.
+///
+/// All populations but the bulge are described by a mass density, which is converted using the mean stellar mass of their initial mass function.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#density-laws>
+pub(super) fn number_density(
+    population: Population,
+    age: Time,
+    position: (f64, f64, f64),
+    mean_mass_in_solar_masses: f64,
+    frame: &GalactocentricFrame,
+) -> f64 {
+    match population {
+        Population::Bulge => bulge_number_density(position, frame),
+        _ => mass_density(population, age, position, frame) / mean_mass_in_solar_masses,
+    }
+}
+
+/// The mass density of a population at a position given in parsec in the galactocentric frame, in units of solar masses per cubic parsec.
+///
+/// The density laws are normalised such that the density at the position of the sun, including its height above the midplane, is the local density `rho0`.
+/// The bulge is described by a number density instead, so its mass density is zero.
+pub(super) fn mass_density(
+    population: Population,
+    age: Time,
+    position: (f64, f64, f64),
+    frame: &GalactocentricFrame,
+) -> f64 {
+    if population == Population::Bulge {
+        return 0.;
+    }
+    let solar_radius = frame.solar_radius_in_pc;
+    let sun = (solar_radius, 0., frame.solar_height_in_pc);
+    let at_sun = density_law(population, age, sun, solar_radius);
+    rho0(population, age) / at_sun * density_law(population, age, position, solar_radius)
+}
+
+/// The density law of a population, normalised to `rho0` in the midplane at the solar radius.
+fn density_law(
+    population: Population,
+    age: Time,
+    position: (f64, f64, f64),
+    solar_radius: f64,
+) -> f64 {
+    let (x, y, z) = position;
+    let radius = (x * x + y * y).sqrt();
+    match population {
+        Population::ThinDisc(Subpopulation::Alive) => {
+            let rho0 = rho0(population, age);
+            let epsilon = epsilon(population, age);
+            if age <= Time::new::<gigayear>(0.15) {
+                young_thin_disc::density(rho0, epsilon, radius, z, solar_radius)
+            } else {
+                older_thin_disc::density(rho0, epsilon, radius, z, solar_radius)
+            }
+        }
+        Population::ThinDisc(Subpopulation::WhiteDwarf) => {
+            let reference_age = Time::new::<gigayear>(WHITE_DWARF_REFERENCE_AGE_IN_GYR);
+            let epsilon = epsilon(Population::ThinDisc(Subpopulation::Alive), reference_age);
+            older_thin_disc::density(rho0(population, age), epsilon, radius, z, solar_radius)
+        }
+        Population::ThickDisc(_) => {
+            let rho0 = rho0(population, age);
+            if z.abs() <= THICK_DISC_HEIGHT_THRESHOLD_IN_PC {
+                inner_thick_disc::density(rho0, radius, z, solar_radius)
+            } else {
+                outer_thick_disc::density(rho0, radius, z, solar_radius)
+            }
+        }
+        Population::Spheroid => {
+            let rho0 = rho0(population, age);
+            let epsilon = epsilon(population, age);
+            let a = (radius * radius + z * z / (epsilon * epsilon)).sqrt();
+            if a <= SPHEROID_CORE_RADIUS_IN_PC {
+                inner_spheroid::density(rho0, solar_radius)
+            } else {
+                outer_spheroid::density(rho0, a, solar_radius)
+            }
+        }
+        Population::Bulge => 0.,
+    }
+}
+
+/// The number density of bulge stars at a position given in parsec in the galactocentric frame, in units of stars per cubic parsec.
+pub(super) fn bulge_number_density(position: (f64, f64, f64), frame: &GalactocentricFrame) -> f64 {
+    let (x_b, y_b, z_b) = to_bulge_frame(frame.model_frame_vector(position));
+    if (x_b * x_b + y_b * y_b).sqrt() <= BULGE_CUTOFF_RADIUS_IN_PC {
+        inner_bulge::density(x_b, y_b, z_b)
+    } else {
+        outer_bulge::density(x_b, y_b, z_b)
+    }
+}
+
+/// Accounts for the increase of the thickness of the disc with galactocentric distance.
+fn flare_factor(radius: f64) -> f64 {
+    if radius >= FLARE_RADIUS_IN_PC {
+        1. + (radius - FLARE_RADIUS_IN_PC) * FLARE_GRADIENT_PER_PC
+    } else {
+        1.
+    }
+}
+
+/// Rotates a position in the model frame into the frame of reference of the bulge.
+///
+/// The sun - galactic center line is the x-axis of the model frame, and the near end of the bulge lies at positive galactic longitude.
+fn to_bulge_frame(position: (f64, f64, f64)) -> (f64, f64, f64) {
+    let (x, y, z) = position;
+
+    let phi = (90. - BULGE_ALPHA_IN_DEG).to_radians();
+    let (x, y) = (
+        x * phi.cos() + y * phi.sin(),
+        -x * phi.sin() + y * phi.cos(),
+    );
+
+    let beta = BULGE_BETA_IN_DEG.to_radians();
+    let (x, z) = (
+        x * beta.cos() + z * beta.sin(),
+        -x * beta.sin() + z * beta.cos(),
+    );
+
+    let gamma = BULGE_GAMMA_IN_DEG.to_radians();
+    let (y, z) = (
+        y * gamma.cos() + z * gamma.sin(),
+        -y * gamma.sin() + z * gamma.cos(),
+    );
+
+    (x, y, z)
+}
+
+// The thin disc age bins between 2 and 5 Gyr share their density, but are kept apart as in the published table.
+#[allow(clippy::if_same_then_else)]
 pub(super) fn rho0(population: Population, age: Time) -> f64 {
     match population {
         Population::ThinDisc(Subpopulation::Alive) => {
@@ -68,12 +261,55 @@ pub(super) fn epsilon(population: Population, age: Time) -> f64 {
 
 #[cfg(test)]
 mod tests {
-    use crate::{assert_diff, assert_ratio};
+    use crate::{
+        assert_diff, assert_ratio,
+        galactocentric_frame::{Handedness, XAxisDirection},
+    };
 
     use super::*;
 
     const ACC: f64 = 1.0e-3;
 
+    #[test]
+    fn near_end_of_bulge_lies_at_positive_longitude() {
+        for x_axis in [XAxisDirection::TowardsSun, XAxisDirection::AwayFromSun] {
+            for handedness in [Handedness::Right, Handedness::Left] {
+                let frame = GalactocentricFrame {
+                    x_axis,
+                    handedness,
+                    ..Default::default()
+                };
+                let positive_longitude = frame.vector_from_model_frame((1000., 500., 0.));
+                let negative_longitude = frame.vector_from_model_frame((1000., -500., 0.));
+                assert!(
+                    bulge_number_density(positive_longitude, &frame)
+                        > bulge_number_density(negative_longitude, &frame)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn density_at_the_sun_is_the_local_density() {
+        let frame = GalactocentricFrame {
+            solar_height_in_pc: 100.,
+            ..Default::default()
+        };
+        let sun = frame.solar_position_in_pc();
+        let populations = [
+            (Population::ThinDisc(Subpopulation::Alive), 0.1),
+            (Population::ThinDisc(Subpopulation::Alive), 5.5),
+            (Population::ThinDisc(Subpopulation::WhiteDwarf), 0.),
+            (Population::ThickDisc(Subpopulation::Alive), 0.),
+            (Population::Spheroid, 0.),
+        ];
+        for (population, age_in_gyr) in populations {
+            let age = Time::new::<gigayear>(age_in_gyr);
+            let rho = mass_density(population, age, sun, &frame);
+            assert_ratio!(rho0(population, age), rho, 1e-10);
+        }
+    }
+
     #[test]
     fn rho0_is_correct() {
         let rho = rho0(
@@ -1,9 +1,71 @@
+use super::flare_factor;
+
+/// Radial decay length dominating at larger distances, in units of parsec.
+const H_PLUS_IN_PC: f64 = 2530.;
+/// Radial decay length dominating at smaller distances, in units of parsec.
+const H_MINUS_IN_PC: f64 = 1320.;
+
+/// Mass density of older thin disc stars, in units of solar masses per cubic parsec.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#older-thin-disc-stars-age--015-gyr>
+pub(super) fn density(
+    rho0: f64,
+    epsilon: f64,
+    radius: f64,
+    z: f64,
+    solar_radius_in_pc: f64,
+) -> f64 {
+    let d0 = shape(epsilon, solar_radius_in_pc, 0.);
+    rho0 / (d0 * flare_factor(radius)) * shape(epsilon, radius, z)
+}
+
+fn shape(epsilon: f64, radius: f64, z: f64) -> f64 {
+    let k_flare = flare_factor(radius);
+    let z_scaled = z / epsilon / k_flare;
+    let a_squared = radius * radius + z_scaled * z_scaled;
+    (-(0.25 + a_squared / (H_PLUS_IN_PC * H_PLUS_IN_PC)).sqrt()).exp()
+        - (-(0.25 + a_squared / (H_MINUS_IN_PC * H_MINUS_IN_PC)).sqrt()).exp()
+}
+
 #[cfg(test)]
 mod tests {
     use serial_test::serial;
 
+    use crate::{assert_ratio, galactocentric_frame::DEFAULT_SOLAR_RADIUS_IN_PC};
+
     use super::*;
 
+    #[test]
+    fn density_at_solar_position_is_rho0() {
+        let rho = density(
+            6.6e-3,
+            0.0791,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+            0.,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+        );
+        assert_ratio!(6.6e-3, rho, 1e-10);
+    }
+
+    #[test]
+    fn density_decreases_away_from_the_plane() {
+        let in_plane = density(
+            6.6e-3,
+            0.0791,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+            0.,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+        );
+        let above_plane = density(
+            6.6e-3,
+            0.0791,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+            500.,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+        );
+        assert!(above_plane < in_plane);
+    }
+
     #[test]
     #[ignore]
     #[serial]
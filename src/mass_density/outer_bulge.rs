@@ -1,9 +1,33 @@
+use super::{
+    inner_bulge::r_s_squared, BULGE_CENTRAL_NUMBER_DENSITY_PER_PC3, BULGE_CUTOFF_RADIUS_IN_PC,
+};
+
+/// Number density of bulge stars outside the cutoff radius, in units of stars per cubic parsec.
+///
+/// The coordinates are given in the frame of reference of the bulge. The cutoff term is evaluated in units of kiloparsec.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#outer-bulge-sqrtx_b2y_b2--r_c>
+pub(super) fn density(x_b: f64, y_b: f64, z_b: f64) -> f64 {
+    let excess_in_kpc = ((x_b * x_b + y_b * y_b).sqrt() - BULGE_CUTOFF_RADIUS_IN_PC) / 1000.;
+    let exponent = -0.5 * r_s_squared(x_b, y_b, z_b) - 0.2 * excess_in_kpc * excess_in_kpc;
+    BULGE_CENTRAL_NUMBER_DENSITY_PER_PC3 * exponent.exp()
+}
+
 #[cfg(test)]
 mod tests {
     use serial_test::serial;
 
+    use crate::{assert_ratio, mass_density::inner_bulge};
+
     use super::*;
 
+    #[test]
+    fn density_is_continuous_at_cutoff_radius() {
+        let inner = inner_bulge::density(BULGE_CUTOFF_RADIUS_IN_PC, 0., 0.);
+        let outer = density(BULGE_CUTOFF_RADIUS_IN_PC, 0., 0.);
+        assert_ratio!(inner, outer, 1e-10);
+    }
+
     #[test]
     #[ignore]
     #[serial]
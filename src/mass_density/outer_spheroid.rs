@@ -1,9 +1,30 @@
+use super::SPHEROID_POWER_LAW_EXPONENT;
+
+/// Mass density of spheroid stars outside the spheroid core, in units of solar masses per cubic parsec.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#outer-spheroid-a--a_c>
+pub(super) fn density(rho0: f64, a: f64, solar_radius_in_pc: f64) -> f64 {
+    rho0 * (a / solar_radius_in_pc).powf(SPHEROID_POWER_LAW_EXPONENT)
+}
+
 #[cfg(test)]
 mod tests {
     use serial_test::serial;
 
+    use crate::{assert_ratio, galactocentric_frame::DEFAULT_SOLAR_RADIUS_IN_PC};
+
     use super::*;
 
+    #[test]
+    fn density_at_solar_position_is_rho0() {
+        let rho = density(
+            9.32e-6,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+        );
+        assert_ratio!(9.32e-6, rho, 1e-10);
+    }
+
     #[test]
     #[ignore]
     #[serial]
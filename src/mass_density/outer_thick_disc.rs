@@ -1,9 +1,50 @@
+use super::{
+    flare_factor, THICK_DISC_HEIGHT_THRESHOLD_IN_PC, THICK_DISC_RADIAL_DECAY_LENGTH_IN_PC,
+    THICK_DISC_SCALE_HEIGHT_IN_PC,
+};
+
+/// Mass density of thick disc stars far from the galactic plane, in units of solar masses per cubic parsec.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#outer-thick-disc-stars-z--x_l>
+pub(super) fn density(rho0: f64, radius: f64, z: f64, solar_radius_in_pc: f64) -> f64 {
+    let k_flare = flare_factor(radius);
+    let x_l = THICK_DISC_HEIGHT_THRESHOLD_IN_PC;
+    let h_z = k_flare * THICK_DISC_SCALE_HEIGHT_IN_PC;
+    let exponent =
+        -(radius - solar_radius_in_pc) / THICK_DISC_RADIAL_DECAY_LENGTH_IN_PC - z.abs() / h_z;
+    let matching = (x_l / h_z).exp() / (1. + x_l / (2. * h_z));
+    rho0 / k_flare * exponent.exp() * matching
+}
+
 #[cfg(test)]
 mod tests {
     use serial_test::serial;
 
+    use crate::{
+        assert_ratio, galactocentric_frame::DEFAULT_SOLAR_RADIUS_IN_PC,
+        mass_density::inner_thick_disc,
+    };
+
     use super::*;
 
+    #[test]
+    fn density_is_continuous_at_height_threshold() {
+        let z = THICK_DISC_HEIGHT_THRESHOLD_IN_PC;
+        let inner = inner_thick_disc::density(
+            1.34e-3,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+            z,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+        );
+        let outer = density(
+            1.34e-3,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+            z,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+        );
+        assert_ratio!(inner, outer, 1e-10);
+    }
+
     #[test]
     #[ignore]
     #[serial]
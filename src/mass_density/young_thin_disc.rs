@@ -1,9 +1,52 @@
+use super::flare_factor;
+
+/// Radial decay length dominating at larger distances, in units of parsec.
+const H_PLUS_IN_PC: f64 = 5000.;
+/// Radial decay length dominating at smaller distances, in units of parsec.
+const H_MINUS_IN_PC: f64 = 3000.;
+
+/// Mass density of young thin disc stars, in units of solar masses per cubic parsec.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#young-thin-disc-stars-age-leq-015-gyr>
+pub(super) fn density(
+    rho0: f64,
+    epsilon: f64,
+    radius: f64,
+    z: f64,
+    solar_radius_in_pc: f64,
+) -> f64 {
+    let d0 = shape(epsilon, solar_radius_in_pc, 0.);
+    rho0 / (d0 * flare_factor(radius)) * shape(epsilon, radius, z)
+}
+
+fn shape(epsilon: f64, radius: f64, z: f64) -> f64 {
+    let k_flare = flare_factor(radius);
+    let z_scaled = z / epsilon / k_flare;
+    let a_squared = radius * radius + z_scaled * z_scaled;
+    (-a_squared / (H_PLUS_IN_PC * H_PLUS_IN_PC)).exp()
+        - (-a_squared / (H_MINUS_IN_PC * H_MINUS_IN_PC)).exp()
+}
+
 #[cfg(test)]
 mod tests {
     use serial_test::serial;
 
+    use crate::{assert_ratio, galactocentric_frame::DEFAULT_SOLAR_RADIUS_IN_PC};
+
     use super::*;
 
+    #[test]
+    fn density_at_solar_position_is_rho0() {
+        let rho = density(
+            4.0e-3,
+            0.0140,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+            0.,
+            DEFAULT_SOLAR_RADIUS_IN_PC,
+        );
+        assert_ratio!(4.0e-3, rho, 1e-10);
+    }
+
     #[test]
     #[ignore]
     #[serial]
@@ -0,0 +1,146 @@
+use astro_units::time::gigayear;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use uom::si::f64::Time;
+
+use crate::population::{Population, Subpopulation};
+
+/// Radial metallicity gradient of the thin disc, in units of dex per parsec.
+const THIN_DISC_RADIAL_GRADIENT_IN_DEX_PER_PC: f64 = -0.07e-3;
+
+/// The mean metallicity [Fe/H] of a population in dex.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#metallicity>
+pub(super) fn mean_metallicity(population: Population, age: Time) -> f64 {
+    match population {
+        Population::ThinDisc(Subpopulation::Alive) => {
+            if age < Time::new::<gigayear>(0.15) {
+                0.01
+            } else if age < Time::new::<gigayear>(1.) {
+                0.00
+            } else if age < Time::new::<gigayear>(2.) {
+                -0.02
+            } else if age < Time::new::<gigayear>(3.) {
+                -0.03
+            } else if age < Time::new::<gigayear>(5.) {
+                -0.05
+            } else if age < Time::new::<gigayear>(7.) {
+                -0.09
+            } else {
+                -0.12
+            }
+        }
+        Population::ThinDisc(Subpopulation::WhiteDwarf) => -0.12,
+        Population::ThickDisc(_) => -0.50,
+        Population::Spheroid => -1.5,
+        Population::Bulge => 0.00,
+    }
+}
+
+/// The dispersion of the metallicity [Fe/H] of a population in dex.
+pub(super) fn metallicity_dispersion(population: Population, age: Time) -> f64 {
+    match population {
+        Population::ThinDisc(Subpopulation::Alive) => {
+            if age < Time::new::<gigayear>(0.15) {
+                0.010
+            } else if age < Time::new::<gigayear>(1.) {
+                0.11
+            } else if age < Time::new::<gigayear>(2.) {
+                0.12
+            } else if age < Time::new::<gigayear>(3.) {
+                0.125
+            } else if age < Time::new::<gigayear>(5.) {
+                0.135
+            } else if age < Time::new::<gigayear>(7.) {
+                0.16
+            } else {
+                0.18
+            }
+        }
+        Population::ThinDisc(Subpopulation::WhiteDwarf) => 0.18,
+        Population::ThickDisc(_) => 0.30,
+        Population::Spheroid => 0.50,
+        Population::Bulge => 0.20,
+    }
+}
+
+/// Samples a metallicity [Fe/H] in dex for a star at the given galactocentric radius in parsec.
+///
+/// The radial gradient of the thin disc is taken relative to the galactocentric radius of the sun.
+pub(super) fn sample_metallicity<R: Rng>(
+    population: Population,
+    age: Time,
+    radius_in_pc: f64,
+    solar_radius_in_pc: f64,
+    rng: &mut R,
+) -> f64 {
+    let mut mean = mean_metallicity(population, age);
+    if let Population::ThinDisc(_) = population {
+        mean += THIN_DISC_RADIAL_GRADIENT_IN_DEX_PER_PC * (radius_in_pc - solar_radius_in_pc);
+    }
+    let dispersion = metallicity_dispersion(population, age);
+    match Normal::new(mean, dispersion) {
+        Ok(normal) => normal.sample(rng),
+        Err(_) => mean,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_diff, assert_ratio};
+
+    use super::*;
+
+    const ACC: f64 = 1.0e-3;
+
+    #[test]
+    fn mean_metallicity_is_correct() {
+        let thin_disc = Population::ThinDisc(Subpopulation::Alive);
+        let expected = [
+            (0.1, 0.01),
+            (0.5, 0.00),
+            (1.5, -0.02),
+            (2.5, -0.03),
+            (3.5, -0.05),
+            (5.5, -0.09),
+            (7.5, -0.12),
+        ];
+        for (age, feh) in expected {
+            let mean = mean_metallicity(thin_disc, Time::new::<gigayear>(age));
+            assert_diff!(feh, mean, ACC);
+        }
+
+        let mean = mean_metallicity(
+            Population::ThickDisc(Subpopulation::Alive),
+            Time::new::<gigayear>(11.),
+        );
+        assert_diff!(-0.50, mean, ACC);
+
+        let mean = mean_metallicity(Population::Spheroid, Time::new::<gigayear>(14.));
+        assert_diff!(-1.5, mean, ACC);
+
+        let mean = mean_metallicity(Population::Bulge, Time::new::<gigayear>(10.));
+        assert_diff!(0., mean, ACC);
+    }
+
+    #[test]
+    fn metallicity_dispersion_is_correct() {
+        let thin_disc = Population::ThinDisc(Subpopulation::Alive);
+        let expected = [
+            (0.1, 0.010),
+            (0.5, 0.11),
+            (1.5, 0.12),
+            (2.5, 0.125),
+            (3.5, 0.135),
+            (5.5, 0.16),
+            (7.5, 0.18),
+        ];
+        for (age, sigma) in expected {
+            let dispersion = metallicity_dispersion(thin_disc, Time::new::<gigayear>(age));
+            assert_ratio!(sigma, dispersion, ACC);
+        }
+
+        let dispersion = metallicity_dispersion(Population::Spheroid, Time::new::<gigayear>(14.));
+        assert_ratio!(0.50, dispersion, ACC);
+    }
+}
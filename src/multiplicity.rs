@@ -0,0 +1,212 @@
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+/// Mean of the log-normal period distribution, with the period in units of days (Duquennoy & Mayor 1991).
+const MEAN_LOG_PERIOD_IN_DAYS: f64 = 4.8;
+/// Standard deviation of the log-normal period distribution, in units of dex.
+const LOG_PERIOD_DISPERSION: f64 = 2.3;
+/// Range of periods that are sampled, as the decadic logarithm of the period in days.
+const LOG_PERIOD_RANGE_IN_DAYS: (f64, f64) = (-0.5, 10.);
+/// Orbits with shorter periods are circularised by tides, in units of days.
+const CIRCULARISATION_PERIOD_IN_DAYS: f64 = 11.6;
+/// Orbits with longer periods follow a thermal eccentricity distribution, in units of days.
+const THERMAL_ECCENTRICITY_PERIOD_IN_DAYS: f64 = 1000.;
+/// Mean and standard deviation of the eccentricity of intermediate period orbits.
+const INTERMEDIATE_ECCENTRICITY: (f64, f64) = (0.31, 0.17);
+/// The largest sampled eccentricity.
+const MAX_ECCENTRICITY: f64 = 0.95;
+const DAYS_PER_YEAR: f64 = 365.25;
+
+/// The orbit of a companion around its primary.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#multiple-systems>
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Orbit {
+    /// Initial mass of the companion divided by that of the primary.
+    pub mass_ratio: f64,
+    /// Orbital period, in units of days.
+    pub period_in_days: f64,
+    /// Orbital eccentricity, between 0 and 1.
+    pub eccentricity: f64,
+    /// Semi-major axis of the relative orbit, in units of astronomical units.
+    pub semi_major_axis_in_au: f64,
+    /// The semi-major axis as seen by the observer, in units of arcseconds.
+    pub angular_separation_in_arcsec: f64,
+    /// Whether the companion is a point source of its own, or merges with its primary.
+    pub resolved: bool,
+}
+
+impl Orbit {
+    /// The angular separation under which the observer sees the orbit from the given distance, in units of arcseconds.
+    pub(crate) fn angular_separation_at(&self, distance_in_pc: f64) -> f64 {
+        if distance_in_pc <= 0. {
+            return f64::INFINITY;
+        }
+        // One astronomical unit seen from one parsec subtends one arcsecond.
+        self.semi_major_axis_in_au / distance_in_pc
+    }
+}
+
+/// The probability of a star of the given mass to have a companion (Arenou 2011).
+pub(crate) fn multiplicity_fraction(primary_mass_in_solar_masses: f64) -> f64 {
+    0.8388 * (0.688 * primary_mass_in_solar_masses + 0.079).tanh()
+}
+
+/// Decides whether a star with the given initial mass has a companion and samples its orbit.
+///
+/// The mass ratio is drawn uniformly, such that the companion is at least as heavy as the given minimal mass.
+pub(crate) fn sample_companion<R: Rng>(
+    primary_mass_in_solar_masses: f64,
+    minimal_mass_in_solar_masses: f64,
+    rng: &mut R,
+) -> Option<Orbit> {
+    let minimal_ratio = minimal_mass_in_solar_masses / primary_mass_in_solar_masses;
+    if minimal_ratio >= 1.
+        || rng.gen::<f64>() >= multiplicity_fraction(primary_mass_in_solar_masses)
+    {
+        return None;
+    }
+    let mass_ratio = rng.gen_range(minimal_ratio..=1.);
+    let period_in_days = 10_f64.powf(sample_truncated_normal(
+        MEAN_LOG_PERIOD_IN_DAYS,
+        LOG_PERIOD_DISPERSION,
+        LOG_PERIOD_RANGE_IN_DAYS,
+        rng,
+    ));
+    let eccentricity = sample_eccentricity(period_in_days, rng);
+    let total_mass = primary_mass_in_solar_masses * (1. + mass_ratio);
+    let period_in_years = period_in_days / DAYS_PER_YEAR;
+    // Kepler's third law in units of astronomical units, years and solar masses.
+    let semi_major_axis_in_au = (total_mass * period_in_years * period_in_years).cbrt();
+    Some(Orbit {
+        mass_ratio,
+        period_in_days,
+        eccentricity,
+        semi_major_axis_in_au,
+        angular_separation_in_arcsec: 0.,
+        resolved: true,
+    })
+}
+
+/// Samples the eccentricity of an orbit following Duquennoy & Mayor 1991.
+fn sample_eccentricity<R: Rng>(period_in_days: f64, rng: &mut R) -> f64 {
+    if period_in_days < CIRCULARISATION_PERIOD_IN_DAYS {
+        0.
+    } else if period_in_days < THERMAL_ECCENTRICITY_PERIOD_IN_DAYS {
+        let (mean, dispersion) = INTERMEDIATE_ECCENTRICITY;
+        sample_truncated_normal(mean, dispersion, (0., MAX_ECCENTRICITY), rng)
+    } else {
+        // The thermal distribution f(e) = 2e has the cumulative distribution e^2.
+        (rng.gen::<f64>().sqrt()).min(MAX_ECCENTRICITY)
+    }
+}
+
+/// Samples a normal distribution restricted to the given range.
+pub(crate) fn sample_truncated_normal<R: Rng>(
+    mean: f64,
+    dispersion: f64,
+    range: (f64, f64),
+    rng: &mut R,
+) -> f64 {
+    let (lower, upper) = range;
+    match Normal::new(mean, dispersion) {
+        Ok(normal) => {
+            for _ in 0..100 {
+                let value = normal.sample(rng);
+                if value >= lower && value <= upper {
+                    return value;
+                }
+            }
+            mean.clamp(lower, upper)
+        }
+        Err(_) => mean.clamp(lower, upper),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{assert_diff, assert_ratio};
+
+    use super::*;
+
+    #[test]
+    fn heavy_stars_are_more_often_multiple() {
+        assert!(multiplicity_fraction(0.1) < 0.15);
+        assert_diff!(0.5, multiplicity_fraction(1.), 0.05);
+        assert!(multiplicity_fraction(10.) > 0.8);
+        assert!(multiplicity_fraction(10.) < 0.84);
+    }
+
+    #[test]
+    fn companion_frequency_follows_multiplicity_fraction() {
+        const N: usize = 20_000;
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let companions = (0..N)
+            .filter_map(|_| sample_companion(1., 0.08, &mut rng))
+            .count();
+        assert_ratio!(
+            multiplicity_fraction(1.),
+            companions as f64 / N as f64,
+            0.03
+        );
+    }
+
+    #[test]
+    fn companions_are_lighter_than_primary_and_above_minimal_mass() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for _ in 0..1000 {
+            if let Some(orbit) = sample_companion(2., 0.1, &mut rng) {
+                assert!(orbit.mass_ratio <= 1.);
+                assert!(orbit.mass_ratio * 2. >= 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn stars_at_the_minimal_mass_are_single() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        for _ in 0..100 {
+            assert!(sample_companion(0.08, 0.08, &mut rng).is_none());
+        }
+    }
+
+    #[test]
+    fn orbits_obey_keplers_third_law() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let mut sampled = 0;
+        while sampled < 100 {
+            if let Some(orbit) = sample_companion(1., 0.08, &mut rng) {
+                let period_in_years = orbit.period_in_days / DAYS_PER_YEAR;
+                let total_mass = 1. + orbit.mass_ratio;
+                assert_ratio!(
+                    orbit.semi_major_axis_in_au.powi(3),
+                    total_mass * period_in_years.powi(2),
+                    1e-8
+                );
+                assert!(orbit.eccentricity >= 0. && orbit.eccentricity <= MAX_ECCENTRICITY);
+                if orbit.period_in_days < CIRCULARISATION_PERIOD_IN_DAYS {
+                    assert_eq!(0., orbit.eccentricity);
+                }
+                sampled += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn earth_orbit_subtends_one_arcsecond_from_one_parsec() {
+        let orbit = Orbit {
+            mass_ratio: 3e-6,
+            period_in_days: DAYS_PER_YEAR,
+            eccentricity: 0.,
+            semi_major_axis_in_au: 1.,
+            angular_separation_in_arcsec: 0.,
+            resolved: true,
+        };
+        assert_diff!(1., orbit.angular_separation_at(1.), 1e-12);
+        assert_diff!(0.1, orbit.angular_separation_at(10.), 1e-12);
+    }
+}
@@ -0,0 +1,484 @@
+//! Magnitudes in photometric bands, derived from the bolometric magnitude via bolometric corrections.
+//!
+//! The band magnitudes are experimental unless a published table of bolometric corrections is provided.
+//! The bolometric corrections shipped with the crate in `data/bolometric_corrections.csv` are a synthetic placeholder, not a published grid of model atmospheres.
+//! They are computed from blackbody spectra whose temperature is shifted to mimic the colour changes caused by surface gravity and metallicity, and they are least reliable for cool and hot stars.
+//! Tabulated corrections, e.g. from MIST, can be provided with the same columns via the `bolometric_correction_table` parameter.
+//!
+//! Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#photometric-bands>
+
+use std::{error::Error, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    generation_parameters::GenerationParameters,
+    interpolation::{bracket, grid_axis},
+    luminosity_limit::SOLAR_ABSOLUTE_MAGNITUDE,
+    stellar_evolution::radius_in_solar_radii,
+};
+
+/// The synthetic placeholder bolometric corrections shipped with the crate.
+const DEFAULT_BOLOMETRIC_CORRECTIONS: &str = include_str!("../data/bolometric_corrections.csv");
+
+/// The names of the columns of a bolometric correction table, in order.
+const COLUMNS: [&str; 3 + Band::ALL.len()] = [
+    "effective_temperature_in_k",
+    "log_g",
+    "metallicity_in_dex",
+    "bc_johnson_b",
+    "bc_johnson_v",
+    "bc_gaia_g",
+    "bc_gaia_bp",
+    "bc_gaia_rp",
+];
+
+/// Shown whenever band magnitudes are computed from the shipped synthetic placeholder.
+const PLACEHOLDER_WARNING: &str = "The band magnitudes are experimental, because the shipped bolometric corrections are a blackbody placeholder rather than Johnson and Gaia photometry. Provide a published table via bolometric_correction_table for realistic band magnitudes.";
+
+/// Surface gravity of the sun, `log10(g / (cm s^-2))`.
+const SOLAR_LOG_G: f64 = 4.438;
+
+/// A photometric passband.
+///
+/// With the shipped synthetic bolometric corrections, the magnitudes in these bands are experimental approximations.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#photometric-bands>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Band {
+    /// The Johnson B band.
+    JohnsonB,
+    /// The Johnson V band.
+    JohnsonV,
+    /// The Gaia G band.
+    GaiaG,
+    /// The Gaia BP band.
+    GaiaBp,
+    /// The Gaia RP band.
+    GaiaRp,
+}
+
+impl Band {
+    pub(crate) const ALL: [Band; 5] = [
+        Band::JohnsonB,
+        Band::JohnsonV,
+        Band::GaiaG,
+        Band::GaiaBp,
+        Band::GaiaRp,
+    ];
+
+    fn index(self) -> usize {
+        match self {
+            Band::JohnsonB => 0,
+            Band::JohnsonV => 1,
+            Band::GaiaG => 2,
+            Band::GaiaBp => 3,
+            Band::GaiaRp => 4,
+        }
+    }
+}
+
+/// Magnitudes of a star, bolometric and in every supported band.
+///
+/// The band magnitudes are experimental unless a published table of bolometric corrections is provided via the generation parameters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Magnitudes {
+    /// The bolometric magnitude.
+    pub bolometric: f64,
+    /// The magnitude in the Johnson B band.
+    pub johnson_b: f64,
+    /// The magnitude in the Johnson V band.
+    pub johnson_v: f64,
+    /// The magnitude in the Gaia G band.
+    pub gaia_g: f64,
+    /// The magnitude in the Gaia BP band.
+    pub gaia_bp: f64,
+    /// The magnitude in the Gaia RP band.
+    pub gaia_rp: f64,
+}
+
+impl Magnitudes {
+    /// The magnitude in the given band, or the bolometric magnitude if no band is given.
+    pub fn get(&self, band: Option<Band>) -> f64 {
+        match band {
+            None => self.bolometric,
+            Some(Band::JohnsonB) => self.johnson_b,
+            Some(Band::JohnsonV) => self.johnson_v,
+            Some(Band::GaiaG) => self.gaia_g,
+            Some(Band::GaiaBp) => self.gaia_bp,
+            Some(Band::GaiaRp) => self.gaia_rp,
+        }
+    }
+
+    /// The magnitudes of the combined light of several stars.
+    pub(crate) fn combined<'a>(magnitudes: impl IntoIterator<Item = &'a Magnitudes>) -> Self {
+        let mut fluxes = [0.; 6];
+        for m in magnitudes {
+            let values = [
+                m.bolometric,
+                m.johnson_b,
+                m.johnson_v,
+                m.gaia_g,
+                m.gaia_bp,
+                m.gaia_rp,
+            ];
+            for (flux, value) in fluxes.iter_mut().zip(values) {
+                *flux += 10_f64.powf(-0.4 * value);
+            }
+        }
+        let [bolometric, johnson_b, johnson_v, gaia_g, gaia_bp, gaia_rp] =
+            fluxes.map(|flux| -2.5 * flux.log10());
+        Self {
+            bolometric,
+            johnson_b,
+            johnson_v,
+            gaia_g,
+            gaia_bp,
+            gaia_rp,
+        }
+    }
+
+    /// Shifts all magnitudes by the same amount, e.g. a distance modulus.
+    pub(crate) fn shifted(&self, offset: f64) -> Self {
+        Self {
+            bolometric: self.bolometric + offset,
+            johnson_b: self.johnson_b + offset,
+            johnson_v: self.johnson_v + offset,
+            gaia_g: self.gaia_g + offset,
+            gaia_bp: self.gaia_bp + offset,
+            gaia_rp: self.gaia_rp + offset,
+        }
+    }
+}
+
+/// Bolometric corrections `BC = M_bol - M_band` as a function of effective temperature, surface gravity and metallicity.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#photometric-bands>
+#[derive(Debug, Clone)]
+pub(crate) struct BolometricCorrectionTable {
+    log_temperatures: Vec<f64>,
+    log_gs: Vec<f64>,
+    metallicities: Vec<f64>,
+    /// Corrections for all bands, indexed by temperature, log g and metallicity in this order.
+    corrections: Vec<[f64; 5]>,
+}
+
+impl BolometricCorrectionTable {
+    /// Loads the table from the given file, or the one shipped with the crate if none is given.
+    pub(crate) fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        match path {
+            Some(path) => Self::parse(&fs::read_to_string(path)?),
+            None => Self::parse(DEFAULT_BOLOMETRIC_CORRECTIONS),
+        }
+    }
+
+    /// Parses a comma separated table with the columns effective temperature, log g, metallicity and the corrections for B, V, G, BP and RP.
+    ///
+    /// Lines starting with `#` and the header line are ignored.
+    fn parse(content: &str) -> Result<Self, Box<dyn Error>> {
+        let mut rows = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("effective") {
+                continue;
+            }
+            let values = line
+                .split(',')
+                .map(|value| value.trim().parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()?;
+            if values.len() != COLUMNS.len() {
+                return Err(format!(
+                    "Expected {} columns in bolometric correction line '{}'",
+                    COLUMNS.len(),
+                    line
+                )
+                .into());
+            }
+            if let Some((_, column)) = values.iter().zip(COLUMNS).find(|(v, _)| !v.is_finite()) {
+                return Err(format!(
+                    "The bolometric correction line '{}' has an invalid value in column {}",
+                    line, column
+                )
+                .into());
+            }
+            rows.push(values);
+        }
+
+        let temperatures = grid_axis(&rows, 0);
+        let log_gs = grid_axis(&rows, 1);
+        let metallicities = grid_axis(&rows, 2);
+        if temperatures.len() < 2 || log_gs.len() < 2 || metallicities.len() < 2 {
+            return Err(
+                "The bolometric correction table needs at least two values per axis".into(),
+            );
+        }
+        if temperatures[0] <= 0. {
+            return Err("The bolometric correction table needs positive temperatures".into());
+        }
+
+        let size = temperatures.len() * log_gs.len() * metallicities.len();
+        let mut corrections = vec![[0.; 5]; size];
+        let mut filled = vec![false; size];
+        for row in rows.iter() {
+            let position = |axis: &[f64], column: usize| {
+                axis.iter()
+                    .position(|&v| v == row[column])
+                    .unwrap_or_default()
+            };
+            let i = position(&temperatures, 0);
+            let j = position(&log_gs, 1);
+            let k = position(&metallicities, 2);
+            let index = (i * log_gs.len() + j) * metallicities.len() + k;
+            if filled[index] {
+                return Err(format!(
+                    "The bolometric correction table contains more than one entry for T_eff = {}, log g = {}, [Fe/H] = {}",
+                    row[0], row[1], row[2]
+                )
+                .into());
+            }
+            corrections[index].copy_from_slice(&row[3..]);
+            filled[index] = true;
+        }
+        if let Some(index) = filled.iter().position(|&f| !f) {
+            let k = index % metallicities.len();
+            let j = index / metallicities.len() % log_gs.len();
+            let i = index / metallicities.len() / log_gs.len();
+            return Err(format!(
+                "The bolometric correction table has no entry for T_eff = {}, log g = {}, [Fe/H] = {}",
+                temperatures[i], log_gs[j], metallicities[k]
+            )
+            .into());
+        }
+
+        Ok(Self {
+            log_temperatures: temperatures.iter().map(|t| t.log10()).collect(),
+            log_gs,
+            metallicities,
+            corrections,
+        })
+    }
+
+    /// The bolometric corrections in all bands, interpolated linearly in `log10(T_eff)`, log g and metallicity.
+    ///
+    /// Values outside the table are clamped to its edges.
+    pub(crate) fn corrections(
+        &self,
+        effective_temperature_in_k: f64,
+        log_g: f64,
+        metallicity_in_dex: f64,
+    ) -> [f64; 5] {
+        let (i, u) = bracket(&self.log_temperatures, effective_temperature_in_k.log10());
+        let (j, v) = bracket(&self.log_gs, log_g);
+        let (k, w) = bracket(&self.metallicities, metallicity_in_dex);
+        let mut result = [0.; 5];
+        for (di, wi) in [(0, 1. - u), (1, u)] {
+            for (dj, wj) in [(0, 1. - v), (1, v)] {
+                for (dk, wk) in [(0, 1. - w), (1, w)] {
+                    let index =
+                        ((i + di) * self.log_gs.len() + j + dj) * self.metallicities.len() + k + dk;
+                    for (band, value) in result.iter_mut().enumerate() {
+                        *value += wi * wj * wk * self.corrections[index][band];
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// The largest correction anywhere in the table for the given band.
+    ///
+    /// A star can be at most this much brighter in the band than bolometrically.
+    pub(crate) fn max_correction(&self, band: Band) -> f64 {
+        self.corrections
+            .iter()
+            .map(|corrections| corrections[band.index()])
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    /// The absolute magnitudes of a star.
+    pub(crate) fn absolute_magnitudes(
+        &self,
+        luminosity_in_solar_units: f64,
+        effective_temperature_in_k: f64,
+        mass_in_solar_masses: f64,
+        metallicity_in_dex: f64,
+    ) -> Magnitudes {
+        let bolometric = SOLAR_ABSOLUTE_MAGNITUDE - 2.5 * luminosity_in_solar_units.log10();
+        let log_g = log_g(
+            luminosity_in_solar_units,
+            effective_temperature_in_k,
+            mass_in_solar_masses,
+        );
+        let bc = self.corrections(effective_temperature_in_k, log_g, metallicity_in_dex);
+        Magnitudes {
+            bolometric,
+            johnson_b: bolometric - bc[Band::JohnsonB.index()],
+            johnson_v: bolometric - bc[Band::JohnsonV.index()],
+            gaia_g: bolometric - bc[Band::GaiaG.index()],
+            gaia_bp: bolometric - bc[Band::GaiaBp.index()],
+            gaia_rp: bolometric - bc[Band::GaiaRp.index()],
+        }
+    }
+}
+
+/// A warning that the band magnitudes are experimental, if the parameters use the shipped synthetic bolometric corrections.
+pub(crate) fn placeholder_warning(params: &GenerationParameters) -> Option<&'static str> {
+    params
+        .bolometric_correction_table
+        .is_none()
+        .then_some(PLACEHOLDER_WARNING)
+}
+
+/// The surface gravity `log10(g / (cm s^-2))` of a star, with its radius derived from luminosity and effective temperature.
+pub(crate) fn log_g(
+    luminosity_in_solar_units: f64,
+    effective_temperature_in_k: f64,
+    mass_in_solar_masses: f64,
+) -> f64 {
+    let radius = radius_in_solar_radii(luminosity_in_solar_units, effective_temperature_in_k);
+    SOLAR_LOG_G + mass_in_solar_masses.log10() - 2. * radius.log10()
+}
+
+/// The difference between apparent and absolute magnitude for an object at the given distance.
+pub(crate) fn distance_modulus(distance_in_pc: f64) -> f64 {
+    5. * distance_in_pc.log10() - 5.
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_diff, stellar_evolution::SOLAR_EFFECTIVE_TEMPERATURE_IN_K};
+
+    use super::*;
+
+    fn default_table() -> BolometricCorrectionTable {
+        BolometricCorrectionTable::load(None).expect("The shipped table should be valid")
+    }
+
+    #[test]
+    fn sun_has_its_known_absolute_magnitudes() {
+        let sun = default_table().absolute_magnitudes(1., SOLAR_EFFECTIVE_TEMPERATURE_IN_K, 1., 0.);
+        assert_diff!(4.8, sun.bolometric, 1e-10);
+        assert_diff!(4.81, sun.johnson_v, 0.01);
+        assert_diff!(0.63, sun.johnson_b - sun.johnson_v, 0.05);
+        assert_diff!(4.67, sun.gaia_g, 0.01);
+        assert_diff!(0.83, sun.gaia_bp - sun.gaia_rp, 0.05);
+    }
+
+    #[test]
+    fn hot_stars_are_bluer_than_cool_stars() {
+        let table = default_table();
+        let hot = table.absolute_magnitudes(100., 10000., 2.5, 0.);
+        let cool = table.absolute_magnitudes(0.01, 3500., 0.4, 0.);
+        assert!(hot.johnson_b - hot.johnson_v < cool.johnson_b - cool.johnson_v);
+        assert!(hot.gaia_bp - hot.gaia_rp < cool.gaia_bp - cool.gaia_rp);
+    }
+
+    #[test]
+    fn corrections_depend_on_surface_gravity_and_metallicity() {
+        let table = default_table();
+        let b_minus_v = |log_g, metallicity| {
+            let bc = table.corrections(5000., log_g, metallicity);
+            bc[Band::JohnsonV.index()] - bc[Band::JohnsonB.index()]
+        };
+        let dwarf = b_minus_v(SOLAR_LOG_G, 0.);
+        let giant = b_minus_v(2.5, 0.);
+        let metal_poor = b_minus_v(SOLAR_LOG_G, -2.);
+        assert_diff!(0.06, giant - dwarf, 0.01);
+        assert!(dwarf - metal_poor > 0.1);
+
+        let bc = |log_g, metallicity| table.corrections(5000., log_g, metallicity);
+        for band in [Band::JohnsonB, Band::JohnsonV] {
+            let solar = bc(SOLAR_LOG_G, 0.)[band.index()];
+            assert!((bc(2.5, 0.)[band.index()] - solar).abs() > 0.01);
+            assert!((bc(SOLAR_LOG_G, -2.)[band.index()] - solar).abs() > 0.01);
+        }
+    }
+
+    #[test]
+    fn sun_has_solar_surface_gravity() {
+        assert_diff!(
+            SOLAR_LOG_G,
+            log_g(1., SOLAR_EFFECTIVE_TEMPERATURE_IN_K, 1.),
+            1e-10
+        );
+    }
+
+    #[test]
+    fn two_equal_stars_are_brighter_by_0_75_magnitudes() {
+        let star = Magnitudes::default().shifted(5.);
+        let pair = Magnitudes::combined([&star, &star]);
+        assert_diff!(5. - 2.5 * 2_f64.log10(), pair.bolometric, 1e-10);
+        assert_diff!(pair.bolometric, pair.gaia_rp, 1e-10);
+    }
+
+    #[test]
+    fn distance_modulus_vanishes_at_ten_parsec() {
+        assert_diff!(0., distance_modulus(10.), 1e-10);
+        assert_diff!(5., distance_modulus(100.), 1e-10);
+    }
+
+    const HEADER: &str = "effective_temperature_in_k,log_g,metallicity_in_dex,bc_johnson_b,bc_johnson_v,bc_gaia_g,bc_gaia_bp,bc_gaia_rp";
+
+    fn parse_error(content: &str) -> String {
+        BolometricCorrectionTable::parse(content)
+            .expect_err("The table should be rejected")
+            .to_string()
+    }
+
+    #[test]
+    fn incomplete_tables_are_rejected() {
+        let content = format!(
+            "{HEADER}
+5000,4,0,0,0,0,0,0
+6000,4,0,0,0,0,0,0"
+        );
+        assert!(BolometricCorrectionTable::parse(&content).is_err());
+    }
+
+    #[test]
+    fn missing_grid_points_are_reported() {
+        let content = format!(
+            "{HEADER}
+5000,4,0,0,0,0,0,0
+5000,4,1,0,0,0,0,0
+5000,5,0,0,0,0,0,0
+5000,5,1,0,0,0,0,0
+6000,4,0,0,0,0,0,0
+6000,4,1,0,0,0,0,0
+6000,5,0,0,0,0,0,0"
+        );
+        let error = parse_error(&content);
+        assert!(
+            error.contains("no entry for T_eff = 6000, log g = 5, [Fe/H] = 1"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn duplicate_grid_points_are_reported() {
+        let content = format!(
+            "{HEADER}
+5000,4,0,0,0,0,0,0
+5000,4,0,1,1,1,1,1
+5000,4,1,0,0,0,0,0
+5000,5,0,0,0,0,0,0
+5000,5,1,0,0,0,0,0
+6000,4,0,0,0,0,0,0
+6000,4,1,0,0,0,0,0
+6000,5,0,0,0,0,0,0
+6000,5,1,0,0,0,0,0"
+        );
+        let error = parse_error(&content);
+        assert!(
+            error.contains("more than one entry for T_eff = 5000, log g = 4, [Fe/H] = 0"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_reported_with_their_column() {
+        let content = format!("{HEADER}\n5000,4,0,0,nan,0,0,0");
+        let error = parse_error(&content);
+        assert!(error.contains("column bc_johnson_v"), "{error}");
+    }
+}
@@ -1,15 +1,138 @@
+use astro_units::time::gigayear;
 use serde::{Deserialize, Serialize};
+use uom::si::f64::Time;
 
+/// Boundaries of the thin disc age bins, in units of gigayears.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#adding-stars>
+const THIN_DISC_AGE_BIN_EDGES_IN_GYR: [f64; 8] = [0., 0.15, 1., 2., 3., 5., 7., 10.];
+const THICK_DISC_AGE_IN_GYR: f64 = 11.;
+const SPHEROID_AGE_IN_GYR: f64 = 14.;
+const BULGE_AGE_IN_GYR: f64 = 10.;
+
+/// A stellar population of the milky way, which determines the density law, age, metallicity and kinematics of its stars.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) enum Population {
+pub enum Population {
+    /// The thin disc, split into age bins.
     ThinDisc(Subpopulation),
+    /// The thick disc, which formed in a single burst.
     ThickDisc(Subpopulation),
+    /// The old and metal poor stellar halo.
     Spheroid,
+    /// The bar shaped bulge at the galactic center.
     Bulge,
 }
 
+/// The stars of a disc population that are sampled separately.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) enum Subpopulation {
+pub enum Subpopulation {
+    /// Stars that have not yet become white dwarfs.
     Alive,
+    /// White dwarfs, whose progenitors have died.
     WhiteDwarf,
 }
+
+/// A population together with the range of ages its stars are drawn from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct AgeBin {
+    pub(crate) population: Population,
+    pub(crate) youngest: Time,
+    pub(crate) oldest: Time,
+}
+
+impl AgeBin {
+    fn new(population: Population, youngest_in_gyr: f64, oldest_in_gyr: f64) -> Self {
+        Self {
+            population,
+            youngest: Time::new::<gigayear>(youngest_in_gyr),
+            oldest: Time::new::<gigayear>(oldest_in_gyr),
+        }
+    }
+
+    /// All age bins of stars that are still alive.
+    pub(crate) fn all_alive() -> Vec<Self> {
+        let mut bins: Vec<Self> = THIN_DISC_AGE_BIN_EDGES_IN_GYR
+            .windows(2)
+            .map(|edges| {
+                AgeBin::new(
+                    Population::ThinDisc(Subpopulation::Alive),
+                    edges[0],
+                    edges[1],
+                )
+            })
+            .collect();
+        bins.push(AgeBin::new(
+            Population::ThickDisc(Subpopulation::Alive),
+            THICK_DISC_AGE_IN_GYR,
+            THICK_DISC_AGE_IN_GYR,
+        ));
+        bins.push(AgeBin::new(
+            Population::Spheroid,
+            SPHEROID_AGE_IN_GYR,
+            SPHEROID_AGE_IN_GYR,
+        ));
+        bins.push(AgeBin::new(
+            Population::Bulge,
+            BULGE_AGE_IN_GYR,
+            BULGE_AGE_IN_GYR,
+        ));
+        bins
+    }
+
+    /// All age bins of white dwarfs.
+    ///
+    /// Their age is the time since the formation of their progenitor.
+    pub(crate) fn all_white_dwarfs() -> Vec<Self> {
+        let oldest_thin_disc =
+            THIN_DISC_AGE_BIN_EDGES_IN_GYR[THIN_DISC_AGE_BIN_EDGES_IN_GYR.len() - 1];
+        vec![
+            AgeBin::new(
+                Population::ThinDisc(Subpopulation::WhiteDwarf),
+                THIN_DISC_AGE_BIN_EDGES_IN_GYR[0],
+                oldest_thin_disc,
+            ),
+            AgeBin::new(
+                Population::ThickDisc(Subpopulation::WhiteDwarf),
+                THICK_DISC_AGE_IN_GYR,
+                THICK_DISC_AGE_IN_GYR,
+            ),
+        ]
+    }
+
+    /// All age bins of all populations.
+    pub(crate) fn all() -> Vec<Self> {
+        let mut bins = Self::all_alive();
+        bins.extend(Self::all_white_dwarfs());
+        bins
+    }
+
+    pub(crate) fn is_white_dwarf(&self) -> bool {
+        matches!(
+            self.population,
+            Population::ThinDisc(Subpopulation::WhiteDwarf)
+                | Population::ThickDisc(Subpopulation::WhiteDwarf)
+        )
+    }
+
+    /// The age in the middle of the bin, used to look up the parameters of the population.
+    pub(crate) fn representative_age(&self) -> Time {
+        (self.youngest + self.oldest) / 2.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thin_disc_age_bins_are_contiguous() {
+        let bins: Vec<AgeBin> = AgeBin::all_alive()
+            .into_iter()
+            .filter(|bin| bin.population == Population::ThinDisc(Subpopulation::Alive))
+            .collect();
+        assert_eq!(bins.len(), 7);
+        for pair in bins.windows(2) {
+            assert_eq!(pair[0].oldest, pair[1].youngest);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    error::Error,
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::generation_statistics::GenerationStatistics;
+
+/// The minimal time between two progress reports, so that runs over millions of small chunks are not slowed down by reporting.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The state of a run after a chunk has been completed.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#progress>
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Progress<'a> {
+    /// Number of chunks that are done, including those done before this run.
+    pub(crate) chunks_completed: usize,
+    /// Number of chunks that were already done before this run, for example when extending a catalogue.
+    pub(crate) chunks_before_run: usize,
+    pub(crate) total_chunks: usize,
+    /// Distance of the center of the last completed chunk from the observer, in units of parsec.
+    pub(crate) distance_in_pc: f64,
+    pub(crate) statistics: &'a GenerationStatistics,
+}
+
+/// A function that is handed the progress after every chunk, and may abort the run by returning an error.
+pub(crate) type ProgressCallback<'a> = dyn FnMut(&Progress) -> Result<(), Box<dyn Error>> + 'a;
+
+/// How progress is reported on the standard error stream.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#progress>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ProgressFormat {
+    /// A single line for humans, updated in place.
+    Text,
+    /// One JSON object per line, for job schedulers.
+    Json,
+}
+
+/// A single line of the JSON progress stream.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum ProgressEvent {
+    Progress(ProgressLine),
+    Finished {
+        elapsed_in_s: f64,
+        statistics: GenerationStatistics,
+    },
+    Failed {
+        error: String,
+    },
+    Warning {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ProgressLine {
+    chunks_completed: usize,
+    total_chunks: usize,
+    distance_in_pc: f64,
+    stars_kept: u64,
+    stars_rejected: u64,
+    elapsed_in_s: f64,
+    /// Estimated time until the run is finished, once there is a chunk to extrapolate from.
+    eta_in_s: Option<f64>,
+}
+
+/// Writes progress reports at most once per interval, and always for the last chunk, on top of a final report once the output is complete.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#progress>
+pub(crate) struct ProgressReporter<W: Write> {
+    out: W,
+    format: ProgressFormat,
+    start: Instant,
+    last_report: Option<Instant>,
+}
+
+impl<W: Write> ProgressReporter<W> {
+    pub(crate) fn new(out: W, format: ProgressFormat) -> Self {
+        ProgressReporter {
+            out,
+            format,
+            start: Instant::now(),
+            last_report: None,
+        }
+    }
+
+    pub(crate) fn report(&mut self, progress: &Progress) -> Result<(), Box<dyn Error>> {
+        self.report_at(progress, Instant::now())
+    }
+
+    fn report_at(&mut self, progress: &Progress, now: Instant) -> Result<(), Box<dyn Error>> {
+        let last_chunk = progress.chunks_completed >= progress.total_chunks;
+        let due = self
+            .last_report
+            .is_none_or(|last| now.duration_since(last) >= REPORT_INTERVAL);
+        if !last_chunk && !due {
+            return Ok(());
+        }
+        self.last_report = Some(now);
+        let elapsed = now.duration_since(self.start).as_secs_f64();
+        let done_in_run = progress.chunks_completed - progress.chunks_before_run;
+        let eta_in_s = (done_in_run > 0).then(|| {
+            let remaining = progress.total_chunks - progress.chunks_completed;
+            elapsed / done_in_run as f64 * remaining as f64
+        });
+        let line = ProgressLine {
+            chunks_completed: progress.chunks_completed,
+            total_chunks: progress.total_chunks,
+            distance_in_pc: progress.distance_in_pc,
+            stars_kept: progress.statistics.stars_kept,
+            stars_rejected: progress.statistics.stars_culled_individually,
+            elapsed_in_s: elapsed,
+            eta_in_s,
+        };
+        match self.format {
+            ProgressFormat::Text => {
+                let percent = 100. * line.chunks_completed as f64 / line.total_chunks.max(1) as f64;
+                let eta = match line.eta_in_s {
+                    Some(eta) if !last_chunk => format!(", ETA {}", format_duration(eta)),
+                    _ => String::new(),
+                };
+                write!(
+                    self.out,
+                    "\r[{:5.1}%] chunk {}/{} at {:.0} pc, {} stars kept, {} rejected, {} elapsed{}",
+                    percent,
+                    line.chunks_completed,
+                    line.total_chunks,
+                    line.distance_in_pc,
+                    line.stars_kept,
+                    line.stars_rejected,
+                    format_duration(elapsed),
+                    eta
+                )?;
+            }
+            ProgressFormat::Json => {
+                serde_json::to_writer(&mut self.out, &ProgressEvent::Progress(line))?;
+                writeln!(self.out)?;
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Reports something the user should know about the output, before the first progress report.
+    pub(crate) fn warn(&mut self, message: &str) -> Result<(), Box<dyn Error>> {
+        match self.format {
+            ProgressFormat::Text => writeln!(self.out, "Warning: {}", message)?,
+            ProgressFormat::Json => {
+                let event = ProgressEvent::Warning {
+                    message: message.to_string(),
+                };
+                serde_json::to_writer(&mut self.out, &event)?;
+                writeln!(self.out)?;
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Reports that the run is complete, after everything has been written.
+    pub(crate) fn finish(
+        &mut self,
+        statistics: &GenerationStatistics,
+    ) -> Result<(), Box<dyn Error>> {
+        match self.format {
+            ProgressFormat::Text => {
+                if self.last_report.is_some() {
+                    writeln!(self.out)?;
+                }
+            }
+            ProgressFormat::Json => {
+                let event = ProgressEvent::Finished {
+                    elapsed_in_s: self.start.elapsed().as_secs_f64(),
+                    statistics: statistics.clone(),
+                };
+                serde_json::to_writer(&mut self.out, &event)?;
+                writeln!(self.out)?;
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Reports that the run was aborted by the given error.
+    pub(crate) fn fail(&mut self, error: &dyn Error) -> Result<(), Box<dyn Error>> {
+        match self.format {
+            ProgressFormat::Text => {
+                if self.last_report.is_some() {
+                    writeln!(self.out)?;
+                }
+            }
+            ProgressFormat::Json => {
+                let event = ProgressEvent::Failed {
+                    error: error.to_string(),
+                };
+                serde_json::to_writer(&mut self.out, &event)?;
+                writeln!(self.out)?;
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// A duration in seconds as hours, minutes and seconds.
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(chunks_completed: usize, statistics: &GenerationStatistics) -> Progress<'_> {
+        Progress {
+            chunks_completed,
+            chunks_before_run: 0,
+            total_chunks: 10,
+            distance_in_pc: 15. * chunks_completed as f64,
+            statistics,
+        }
+    }
+
+    #[test]
+    fn json_progress_is_throttled_but_always_reports_the_last_chunk() {
+        let statistics = GenerationStatistics {
+            stars_kept: 7,
+            stars_culled_individually: 93,
+            ..Default::default()
+        };
+        let mut reporter = ProgressReporter::new(Vec::new(), ProgressFormat::Json);
+        let start = reporter.start;
+        for chunk in 1..=10 {
+            let now = start + Duration::from_millis(300 * chunk as u64);
+            reporter
+                .report_at(&progress(chunk, &statistics), now)
+                .expect("Reporting should succeed");
+        }
+        reporter
+            .finish(&statistics)
+            .expect("Reporting should succeed");
+        let output = String::from_utf8(reporter.out).expect("The output should be UTF-8");
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("Every line should be JSON"))
+            .collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0]["event"], "progress");
+        assert_eq!(lines[0]["chunks_completed"], 1);
+        assert_eq!(lines[0]["stars_rejected"], 93);
+        assert_eq!(lines[1]["chunks_completed"], 5);
+        let eta = lines[1]["eta_in_s"]
+            .as_f64()
+            .expect("There should be an ETA");
+        assert!((eta - 1.5).abs() < 1e-6);
+        assert_eq!(lines[3]["event"], "progress");
+        assert_eq!(lines[3]["chunks_completed"], 10);
+        assert_eq!(lines[3]["eta_in_s"], 0.);
+        assert_eq!(lines[4]["event"], "finished");
+        assert_eq!(lines[4]["statistics"]["stars_kept"], 7);
+    }
+
+    #[test]
+    fn text_progress_is_updated_in_place() {
+        let statistics = GenerationStatistics::default();
+        let mut reporter = ProgressReporter::new(Vec::new(), ProgressFormat::Text);
+        let start = reporter.start;
+        reporter
+            .report_at(&progress(5, &statistics), start + Duration::from_secs(5))
+            .expect("Reporting should succeed");
+        reporter
+            .report_at(
+                &progress(10, &statistics),
+                start + Duration::from_secs(3725),
+            )
+            .expect("Reporting should succeed");
+        reporter
+            .finish(&statistics)
+            .expect("Reporting should succeed");
+        let output = String::from_utf8(reporter.out).expect("The output should be UTF-8");
+        assert_eq!(
+            output,
+            "\r[ 50.0%] chunk 5/10 at 75 pc, 0 stars kept, 0 rejected, 5s elapsed, ETA 5s\
+             \r[100.0%] chunk 10/10 at 150 pc, 0 stars kept, 0 rejected, 1h 02m 05s elapsed\n"
+        );
+    }
+
+    #[test]
+    fn failures_are_reported_as_json() {
+        let mut reporter = ProgressReporter::new(Vec::new(), ProgressFormat::Json);
+        reporter
+            .fail(&*Box::<dyn Error>::from("disk full"))
+            .expect("Reporting should succeed");
+        let output = String::from_utf8(reporter.out).expect("The output should be UTF-8");
+        assert_eq!(output, "{\"event\":\"failed\",\"error\":\"disk full\"}\n");
+    }
+
+    #[test]
+    fn warnings_are_reported_as_json() {
+        let mut reporter = ProgressReporter::new(Vec::new(), ProgressFormat::Json);
+        reporter
+            .warn("experimental")
+            .expect("Reporting should succeed");
+        let output = String::from_utf8(reporter.out).expect("The output should be UTF-8");
+        assert_eq!(
+            output,
+            "{\"event\":\"warning\",\"message\":\"experimental\"}\n"
+        );
+    }
+}
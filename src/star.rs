@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    colour::Colour,
+    coordinates::SkyCoordinates,
+    kinematics::Astrometry,
+    multiplicity::Orbit,
+    photometry::{Band, Magnitudes},
+    population::Population,
+    variability::Variability,
+};
+
+/// A single generated star.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#algorithm>
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Star {
+    /// Galactocentric position, in units of parsec.
+    pub position_in_pc: (f64, f64, f64),
+    /// Velocity in the galactocentric frame, in units of km/s.
+    #[serde(default)]
+    pub velocity_in_km_per_s: (f64, f64, f64),
+    /// Initial mass, in units of solar masses.
+    pub initial_mass_in_solar_masses: f64,
+    /// Present mass after mass loss, in units of solar masses.
+    pub mass_in_solar_masses: f64,
+    /// Age, in units of gigayears.
+    pub age_in_gyr: f64,
+    /// Metallicity [Fe/H], in units of dex.
+    pub metallicity_in_dex: f64,
+    /// Bolometric luminosity, in units of the solar luminosity.
+    pub luminosity_in_solar_units: f64,
+    /// Effective temperature, in units of Kelvin.
+    pub effective_temperature_in_k: f64,
+    /// The population the star was drawn from.
+    pub population: Population,
+    /// Time since the star became a white dwarf, in units of gigayears.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooling_age_in_gyr: Option<f64>,
+    /// Magnitudes as seen from a distance of 10 parsec, without extinction.
+    pub absolute_magnitudes: Magnitudes,
+    /// Magnitudes as seen by the observer, including extinction.
+    pub apparent_magnitudes: Magnitudes,
+    /// Combined apparent magnitudes of the star and all of its companions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_apparent_magnitudes: Option<Magnitudes>,
+    /// Visual extinction `A_V` between the observer and the star, in units of magnitudes.
+    #[serde(default)]
+    pub visual_extinction_in_mag: f64,
+    /// Position on the sky of the observer, in the requested frames.
+    #[serde(default, skip_serializing_if = "SkyCoordinates::is_empty")]
+    pub sky_coordinates: SkyCoordinates,
+    /// Proper motion and radial velocity relative to the observer.
+    #[serde(default)]
+    pub astrometry: Astrometry,
+    /// Display colour, only present if requested in the generation parameters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colour: Option<Colour>,
+    /// The orbit around the primary, only present for companions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orbit: Option<Orbit>,
+    /// Stars orbiting this one, only sampled if requested in the generation parameters.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub companions: Vec<Star>,
+    /// The light curve, only present for variable stars if requested in the generation parameters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variability: Option<Variability>,
+    /// The open cluster or OB association the star is a member of, only present if requested in the generation parameters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster_id: Option<u64>,
+}
+
+impl Star {
+    /// The apparent magnitude in the given band, or the bolometric one if no band is given, at the given time in days after the epoch of the catalogue.
+    ///
+    /// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#variable-stars>
+    pub fn apparent_magnitude_at(&self, band: Option<Band>, time_in_days: f64) -> f64 {
+        let offset = self
+            .variability
+            .map_or(0., |variability| variability.magnitude_offset(time_in_days));
+        self.apparent_magnitudes.get(band) + offset
+    }
+}
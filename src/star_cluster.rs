@@ -0,0 +1,338 @@
+use std::f64::consts::PI;
+
+use astro_units::time::gigayear;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    colour::Colour,
+    coordinates::SkyCoordinates,
+    kinematics::Astrometry,
+    multiplicity::sample_truncated_normal,
+    photometry::Magnitudes,
+    population::{AgeBin, Population, Subpopulation},
+};
+
+/// Fraction of the young thin disc stars that are members of open clusters or OB associations.
+pub(crate) const CLUSTERED_FRACTION: f64 = 0.1;
+/// Clusters are placed in the thin disc age bins that end before this age, in units of gigayears.
+const OLDEST_CLUSTER_AGE_IN_GYR: f64 = 1.;
+/// Clusters younger than this are unbound OB associations, in units of gigayears.
+const OB_ASSOCIATION_AGE_IN_GYR: f64 = 0.02;
+/// Range of the cluster mass function `dN/dM ∝ M^-2`, in units of solar masses.
+const CLUSTER_MASS_RANGE_IN_SOLAR_MASSES: (f64, f64) = (50., 5000.);
+/// Fraction of the spheroid stars that are members of globular clusters.
+pub(crate) const GLOBULAR_CLUSTER_FRACTION: f64 = 0.02;
+/// Mean and standard deviation of the decadic logarithm of the globular cluster mass in solar masses.
+const GLOBULAR_CLUSTER_LOG_MASS: (f64, f64) = (5.2, 0.5);
+/// Range of the sampled globular cluster masses, as the decadic logarithm of the mass in solar masses.
+const GLOBULAR_CLUSTER_LOG_MASS_RANGE: (f64, f64) = (4., 6.5);
+/// Number of steps used to integrate the mean globular cluster mass.
+const MEAN_MASS_INTEGRATION_STEPS: usize = 1000;
+/// Range of the Plummer scale radii of globular clusters, in units of parsec.
+const GLOBULAR_CLUSTER_SCALE_RADIUS_RANGE_IN_PC: (f64, f64) = (1., 4.);
+/// Range of the Plummer scale radii of open clusters, in units of parsec.
+const OPEN_CLUSTER_SCALE_RADIUS_RANGE_IN_PC: (f64, f64) = (0.5, 3.);
+/// Range of the Plummer scale radii of OB associations, in units of parsec.
+const OB_ASSOCIATION_SCALE_RADIUS_RANGE_IN_PC: (f64, f64) = (5., 20.);
+/// Velocity dispersion of the members of an OB association, in units of km/s.
+const OB_ASSOCIATION_VELOCITY_DISPERSION_IN_KM_PER_S: f64 = 3.;
+/// Members are placed within this many scale radii of the cluster center.
+const TRUNCATION_IN_SCALE_RADII: f64 = 5.;
+/// The half-mass radius of a Plummer sphere in units of its scale radius.
+const HALF_MASS_RADIUS_IN_SCALE_RADII: f64 = 1.305;
+/// Newton's gravitational constant, in units of parsec (km/s)^2 per solar mass.
+const GRAVITATIONAL_CONSTANT: f64 = 4.300_917e-3;
+
+/// The kind of a group of stars that formed together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClusterKind {
+    /// A gravitationally bound group of stars of the same age.
+    OpenCluster,
+    /// A young, unbound and expanding group of stars.
+    ObAssociation,
+    /// An old, massive and bound group of stars in the spheroid.
+    GlobularCluster,
+}
+
+/// A group of stars that formed together, sharing age, metallicity and bulk motion.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#open-clusters-and-ob-associations>
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StarCluster {
+    /// Identifies the cluster within the catalogue.
+    pub id: u64,
+    /// The kind of the cluster.
+    pub kind: ClusterKind,
+    /// Age of all members, in units of gigayears.
+    pub age_in_gyr: f64,
+    /// Metallicity [Fe/H] of all members, in units of dex.
+    pub metallicity_in_dex: f64,
+    /// Galactocentric position of the center, in units of parsec.
+    pub center_in_pc: (f64, f64, f64),
+    /// Velocity of the center in the galactocentric frame, in units of km/s.
+    pub velocity_in_km_per_s: (f64, f64, f64),
+    /// Total initial mass of the members, in units of solar masses.
+    pub mass_in_solar_masses: f64,
+    /// Scale radius of the Plummer sphere the members are placed in, in units of parsec.
+    pub scale_radius_in_pc: f64,
+}
+
+impl StarCluster {
+    /// Whether open clusters and OB associations are placed in the given age bin.
+    pub(crate) fn forms_in(age_bin: &AgeBin) -> bool {
+        age_bin.population == Population::ThinDisc(Subpopulation::Alive)
+            && age_bin.oldest.get::<gigayear>() <= OLDEST_CLUSTER_AGE_IN_GYR
+    }
+
+    /// The expectation value of the mass of the clusters of a population, in units of solar masses.
+    pub(crate) fn mean_mass(population: Population) -> f64 {
+        if population == Population::Spheroid {
+            return mean_globular_cluster_mass();
+        }
+        let (lower, upper) = CLUSTER_MASS_RANGE_IN_SOLAR_MASSES;
+        (upper / lower).ln() / (1. / lower - 1. / upper)
+    }
+
+    /// Samples the mass and size of a cluster of the given population with the given properties.
+    ///
+    /// Clusters of the spheroid are globular clusters, all others are open clusters or OB associations.
+    pub(crate) fn sample<R: Rng>(
+        id: u64,
+        population: Population,
+        age_in_gyr: f64,
+        metallicity_in_dex: f64,
+        center_in_pc: (f64, f64, f64),
+        velocity_in_km_per_s: (f64, f64, f64),
+        rng: &mut R,
+    ) -> Self {
+        let (kind, mass_in_solar_masses) = if population == Population::Spheroid {
+            let (mean, dispersion) = GLOBULAR_CLUSTER_LOG_MASS;
+            let log_mass =
+                sample_truncated_normal(mean, dispersion, GLOBULAR_CLUSTER_LOG_MASS_RANGE, rng);
+            (ClusterKind::GlobularCluster, 10_f64.powf(log_mass))
+        } else {
+            let (lower, upper) = CLUSTER_MASS_RANGE_IN_SOLAR_MASSES;
+            let u: f64 = rng.gen();
+            let mass = 1. / (1. / lower - u * (1. / lower - 1. / upper));
+            if age_in_gyr < OB_ASSOCIATION_AGE_IN_GYR {
+                (ClusterKind::ObAssociation, mass)
+            } else {
+                (ClusterKind::OpenCluster, mass)
+            }
+        };
+        let (smallest, largest) = match kind {
+            ClusterKind::OpenCluster => OPEN_CLUSTER_SCALE_RADIUS_RANGE_IN_PC,
+            ClusterKind::ObAssociation => OB_ASSOCIATION_SCALE_RADIUS_RANGE_IN_PC,
+            ClusterKind::GlobularCluster => GLOBULAR_CLUSTER_SCALE_RADIUS_RANGE_IN_PC,
+        };
+        Self {
+            id,
+            kind,
+            age_in_gyr,
+            metallicity_in_dex,
+            center_in_pc,
+            velocity_in_km_per_s,
+            mass_in_solar_masses,
+            scale_radius_in_pc: rng.gen_range(smallest..largest),
+        }
+    }
+
+    /// The distance from the center within which all members lie, in units of parsec.
+    pub(crate) fn extent_in_pc(&self) -> f64 {
+        TRUNCATION_IN_SCALE_RADII * self.scale_radius_in_pc
+    }
+
+    /// The radius within which half of the members lie, ignoring the truncation, in units of parsec.
+    pub(crate) fn half_mass_radius_in_pc(&self) -> f64 {
+        HALF_MASS_RADIUS_IN_SCALE_RADII * self.scale_radius_in_pc
+    }
+
+    /// The one-dimensional velocity dispersion of the members, in units of km/s.
+    ///
+    /// Open and globular clusters are in virial equilibrium, for which a Plummer sphere has `σ^2 = G M / (6 a)`.
+    pub(crate) fn velocity_dispersion_in_km_per_s(&self) -> f64 {
+        match self.kind {
+            ClusterKind::OpenCluster | ClusterKind::GlobularCluster => (GRAVITATIONAL_CONSTANT
+                * self.mass_in_solar_masses
+                / (6. * self.scale_radius_in_pc))
+                .sqrt(),
+            ClusterKind::ObAssociation => OB_ASSOCIATION_VELOCITY_DISPERSION_IN_KM_PER_S,
+        }
+    }
+
+    /// Samples the position of a member from a Plummer profile, in units of parsec.
+    pub(crate) fn sample_member_position<R: Rng>(&self, rng: &mut R) -> (f64, f64, f64) {
+        let a = self.scale_radius_in_pc;
+        let radius = loop {
+            // Inverse of the enclosed mass fraction r^3 / (r^2 + a^2)^(3/2).
+            let u: f64 = rng.gen();
+            let radius = a / (u.powf(-2. / 3.) - 1.).sqrt();
+            if radius <= self.extent_in_pc() {
+                break radius;
+            }
+        };
+        let (x, y, z) = isotropic_direction(rng);
+        let (cx, cy, cz) = self.center_in_pc;
+        (cx + radius * x, cy + radius * y, cz + radius * z)
+    }
+
+    /// Samples the velocity of a member, in units of km/s.
+    pub(crate) fn sample_member_velocity<R: Rng>(&self, rng: &mut R) -> (f64, f64, f64) {
+        let (vx, vy, vz) = self.velocity_in_km_per_s;
+        match Normal::new(0., self.velocity_dispersion_in_km_per_s()) {
+            Ok(normal) => (
+                vx + normal.sample(rng),
+                vy + normal.sample(rng),
+                vz + normal.sample(rng),
+            ),
+            Err(_) => self.velocity_in_km_per_s,
+        }
+    }
+}
+
+/// A cluster too distant to be resolved into individual stars, seen as a single object.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#globular-clusters>
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnresolvedCluster {
+    /// The cluster, with its center and velocity at the epoch of the catalogue.
+    pub cluster: StarCluster,
+    /// Integrated magnitudes of all members.
+    pub absolute_magnitudes: Magnitudes,
+    /// Integrated magnitudes as seen by the observer, including extinction.
+    pub apparent_magnitudes: Magnitudes,
+    /// Visual extinction `A_V` between the observer and the cluster center, in units of magnitudes.
+    pub visual_extinction_in_mag: f64,
+    /// The half-mass radius as seen by the observer, in units of arcseconds.
+    pub angular_radius_in_arcsec: f64,
+    /// Position of the center on the sky of the observer, in the requested frames.
+    #[serde(default, skip_serializing_if = "SkyCoordinates::is_empty")]
+    pub sky_coordinates: SkyCoordinates,
+    /// Proper motion and radial velocity of the center relative to the observer.
+    pub astrometry: Astrometry,
+    /// Display colour of the integrated light, only present if requested in the generation parameters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colour: Option<Colour>,
+}
+
+/// The expectation value of the truncated log-normal globular cluster mass function, in units of solar masses.
+fn mean_globular_cluster_mass() -> f64 {
+    let (mean, dispersion) = GLOBULAR_CLUSTER_LOG_MASS;
+    let (lower, upper) = GLOBULAR_CLUSTER_LOG_MASS_RANGE;
+    let step = (upper - lower) / MEAN_MASS_INTEGRATION_STEPS as f64;
+    let mut weight = 0.;
+    let mut weighted_mass = 0.;
+    for i in 0..MEAN_MASS_INTEGRATION_STEPS {
+        let log_mass = lower + (i as f64 + 0.5) * step;
+        let probability = (-0.5 * ((log_mass - mean) / dispersion).powi(2)).exp();
+        weight += probability;
+        weighted_mass += probability * 10_f64.powf(log_mass);
+    }
+    weighted_mass / weight
+}
+
+fn isotropic_direction<R: Rng>(rng: &mut R) -> (f64, f64, f64) {
+    let cos_theta: f64 = rng.gen_range(-1. ..1.);
+    let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+    let phi = rng.gen_range(0. ..2. * PI);
+    (sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{assert_ratio, generator::distance_in_pc};
+
+    use super::*;
+
+    fn cluster(age_in_gyr: f64, seed: u64) -> StarCluster {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let population = if age_in_gyr > OLDEST_CLUSTER_AGE_IN_GYR {
+            Population::Spheroid
+        } else {
+            Population::ThinDisc(Subpopulation::Alive)
+        };
+        StarCluster::sample(
+            0,
+            population,
+            age_in_gyr,
+            0.,
+            (0., 0., 0.),
+            (0., 0., 0.),
+            &mut rng,
+        )
+    }
+
+    #[test]
+    fn clusters_only_form_in_the_young_thin_disc() {
+        let forming: Vec<AgeBin> = AgeBin::all()
+            .into_iter()
+            .filter(StarCluster::forms_in)
+            .collect();
+        assert_eq!(2, forming.len());
+        assert!(forming
+            .iter()
+            .all(|bin| bin.population == Population::ThinDisc(Subpopulation::Alive)));
+    }
+
+    #[test]
+    fn sampled_cluster_masses_match_their_mean() {
+        const N: u64 = 20_000;
+        let mean = (0..N)
+            .map(|seed| cluster(0.5, seed).mass_in_solar_masses)
+            .sum::<f64>()
+            / N as f64;
+        assert_ratio!(
+            StarCluster::mean_mass(Population::ThinDisc(Subpopulation::Alive)),
+            mean,
+            0.05
+        );
+    }
+
+    #[test]
+    fn sampled_globular_cluster_masses_match_their_mean() {
+        const N: u64 = 20_000;
+        let mean = (0..N)
+            .map(|seed| cluster(14., seed).mass_in_solar_masses)
+            .sum::<f64>()
+            / N as f64;
+        assert_ratio!(StarCluster::mean_mass(Population::Spheroid), mean, 0.05);
+    }
+
+    #[test]
+    fn the_youngest_clusters_are_associations() {
+        assert_eq!(ClusterKind::ObAssociation, cluster(0.01, 0).kind);
+        assert_eq!(ClusterKind::OpenCluster, cluster(0.1, 0).kind);
+        assert_eq!(ClusterKind::GlobularCluster, cluster(14., 0).kind);
+    }
+
+    #[test]
+    fn half_of_the_members_lie_within_the_half_mass_radius() {
+        const N: usize = 20_000;
+        let cluster = cluster(0.5, 1);
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        let half_mass_radius = cluster.half_mass_radius_in_pc();
+        let inside = (0..N)
+            .map(|_| cluster.sample_member_position(&mut rng))
+            .filter(|&position| distance_in_pc(position, cluster.center_in_pc) < half_mass_radius)
+            .count();
+        // The truncation removes about 6% of the mass from the outskirts.
+        assert_ratio!(0.5 / 0.943, inside as f64 / N as f64, 0.03);
+    }
+
+    #[test]
+    fn open_clusters_have_dispersions_of_a_few_km_per_s() {
+        let dispersion = cluster(0.5, 3).velocity_dispersion_in_km_per_s();
+        assert!(dispersion > 0.01 && dispersion < 5.);
+    }
+
+    #[test]
+    fn globular_clusters_have_dispersions_of_several_km_per_s() {
+        let dispersion = cluster(14., 4).velocity_dispersion_in_km_per_s();
+        assert!(dispersion > 1. && dispersion < 30.);
+    }
+}
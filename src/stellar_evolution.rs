@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+
+/// The effective temperature of the sun, in units of Kelvin.
+pub(crate) const SOLAR_EFFECTIVE_TEMPERATURE_IN_K: f64 = 5772.;
+
+/// Main sequence lifetime of the sun, in units of gigayears.
+const SOLAR_MAIN_SEQUENCE_LIFETIME_IN_GYR: f64 = 10.;
+/// Lower bound for the main sequence lifetime of the most massive stars, in units of gigayears.
+const MIN_MAIN_SEQUENCE_LIFETIME_IN_GYR: f64 = 0.003;
+/// Duration of the giant phase as a fraction of the main sequence lifetime.
+const GIANT_PHASE_FRACTION: f64 = 0.1;
+/// Luminosity at the tip of the giant branch of low mass stars, in units of solar luminosities.
+const GIANT_TIP_LUMINOSITY: f64 = 2500.;
+/// Effective temperature of a star at the tip of the giant branch, in units of Kelvin.
+const GIANT_TIP_EFFECTIVE_TEMPERATURE_IN_K: f64 = 3500.;
+
+/// The phase of stellar evolution a star is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum EvolutionaryPhase {
+    MainSequence,
+    Giant,
+    Remnant,
+}
+
+/// The observable properties of a star at a given age.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct StellarState {
+    pub(crate) phase: EvolutionaryPhase,
+    pub(crate) mass_in_solar_masses: f64,
+    pub(crate) luminosity_in_solar_units: f64,
+    pub(crate) effective_temperature_in_k: f64,
+}
+
+/// The state of a star with the given initial mass at the given age.
+///
+/// This is an analytic approximation standing in for tabulated evolution tracks: Stars stay on the zero age main sequence for their main sequence lifetime, then brighten and cool along the giant branch and finally leave a dark remnant.
+pub(crate) fn evolve(initial_mass_in_solar_masses: f64, age_in_gyr: f64) -> StellarState {
+    let m = initial_mass_in_solar_masses;
+    let t_ms = main_sequence_lifetime_in_gyr(m);
+    let l_ms = main_sequence_luminosity(m);
+    let t_eff_ms = main_sequence_effective_temperature(m);
+    if age_in_gyr < t_ms {
+        StellarState {
+            phase: EvolutionaryPhase::MainSequence,
+            mass_in_solar_masses: m,
+            luminosity_in_solar_units: l_ms,
+            effective_temperature_in_k: t_eff_ms,
+        }
+    } else if age_in_gyr < t_ms * (1. + GIANT_PHASE_FRACTION) {
+        let x = giant_progress(m, age_in_gyr);
+        StellarState {
+            phase: EvolutionaryPhase::Giant,
+            mass_in_solar_masses: m,
+            luminosity_in_solar_units: l_ms * (tip_luminosity(m) / l_ms).powf(x),
+            effective_temperature_in_k: t_eff_ms
+                * (GIANT_TIP_EFFECTIVE_TEMPERATURE_IN_K / t_eff_ms).powf(x.sqrt()),
+        }
+    } else {
+        StellarState {
+            phase: EvolutionaryPhase::Remnant,
+            mass_in_solar_masses: m,
+            luminosity_in_solar_units: 0.,
+            effective_temperature_in_k: 0.,
+        }
+    }
+}
+
+/// The radius of a star with the given luminosity and effective temperature, from the Stefan-Boltzmann law, in units of solar radii.
+pub(crate) fn radius_in_solar_radii(
+    luminosity_in_solar_units: f64,
+    effective_temperature_in_k: f64,
+) -> f64 {
+    let temperature_ratio = SOLAR_EFFECTIVE_TEMPERATURE_IN_K / effective_temperature_in_k;
+    luminosity_in_solar_units.sqrt() * temperature_ratio * temperature_ratio
+}
+
+pub(crate) fn main_sequence_lifetime_in_gyr(initial_mass_in_solar_masses: f64) -> f64 {
+    SOLAR_MAIN_SEQUENCE_LIFETIME_IN_GYR * initial_mass_in_solar_masses.powf(-2.5)
+        + MIN_MAIN_SEQUENCE_LIFETIME_IN_GYR
+}
+
+/// The age at which a star of the given initial mass leaves a remnant, in units of gigayears.
+pub(crate) fn lifetime_in_gyr(initial_mass_in_solar_masses: f64) -> f64 {
+    main_sequence_lifetime_in_gyr(initial_mass_in_solar_masses) * (1. + GIANT_PHASE_FRACTION)
+}
+
+/// The initial mass of the heaviest star that has not yet become a remnant at the given age, in units of solar masses.
+pub(crate) fn heaviest_living_mass(age_in_gyr: f64) -> f64 {
+    let main_sequence_lifetime = age_in_gyr / (1. + GIANT_PHASE_FRACTION);
+    if main_sequence_lifetime <= MIN_MAIN_SEQUENCE_LIFETIME_IN_GYR {
+        return f64::INFINITY;
+    }
+    let scaled = (main_sequence_lifetime - MIN_MAIN_SEQUENCE_LIFETIME_IN_GYR)
+        / SOLAR_MAIN_SEQUENCE_LIFETIME_IN_GYR;
+    scaled.powf(-0.4)
+}
+
+/// The highest luminosity a star of the given initial mass reaches at any age within the given range, in units of solar luminosities.
+///
+/// The luminosity of a living star never decreases with age, so it is reached at the oldest age at which the star is still alive.
+pub(crate) fn max_luminosity(
+    initial_mass_in_solar_masses: f64,
+    youngest_in_gyr: f64,
+    oldest_in_gyr: f64,
+) -> f64 {
+    let m = initial_mass_in_solar_masses;
+    let lifetime = lifetime_in_gyr(m);
+    if youngest_in_gyr >= lifetime {
+        return 0.;
+    }
+    let age = oldest_in_gyr.min(lifetime * (1. - f64::EPSILON));
+    evolve(m, age).luminosity_in_solar_units
+}
+
+fn main_sequence_luminosity(m: f64) -> f64 {
+    if m < 0.43 {
+        0.23 * m.powf(2.3)
+    } else if m < 2. {
+        m.powi(4)
+    } else if m < 55. {
+        16. * (m / 2.).powf(3.5)
+    } else {
+        16. * (27.5_f64).powf(3.5) * m / 55.
+    }
+}
+
+fn main_sequence_radius(m: f64) -> f64 {
+    if m < 1. {
+        m.powf(0.8)
+    } else {
+        m.powf(0.57)
+    }
+}
+
+fn main_sequence_effective_temperature(m: f64) -> f64 {
+    let radius = main_sequence_radius(m);
+    SOLAR_EFFECTIVE_TEMPERATURE_IN_K * (main_sequence_luminosity(m) / (radius * radius)).powf(0.25)
+}
+
+fn tip_luminosity(m: f64) -> f64 {
+    GIANT_TIP_LUMINOSITY.max(2. * main_sequence_luminosity(m))
+}
+
+/// How far a star has progressed along the giant branch, between 0 and 1.
+fn giant_progress(m: f64, age_in_gyr: f64) -> f64 {
+    let t_ms = main_sequence_lifetime_in_gyr(m);
+    ((age_in_gyr - t_ms) / (GIANT_PHASE_FRACTION * t_ms)).clamp(0., 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_ratio;
+
+    use super::*;
+
+    #[test]
+    fn sun_is_a_main_sequence_star_with_solar_properties() {
+        let sun = evolve(1., 4.6);
+        assert_eq!(sun.phase, EvolutionaryPhase::MainSequence);
+        assert_ratio!(1., sun.luminosity_in_solar_units, 1e-3);
+        assert_ratio!(
+            SOLAR_EFFECTIVE_TEMPERATURE_IN_K,
+            sun.effective_temperature_in_k,
+            1e-3
+        );
+    }
+
+    #[test]
+    fn main_sequence_luminosity_increases_with_mass() {
+        let mut previous = 0.;
+        for i in 1..1000 {
+            let luminosity = main_sequence_luminosity(0.07 + i as f64 * 0.12);
+            assert!(luminosity > previous);
+            previous = luminosity;
+        }
+    }
+
+    #[test]
+    fn heaviest_living_mass_inverts_lifetime() {
+        for mass in [0.5, 1., 2., 10., 50.] {
+            let lifetime = lifetime_in_gyr(mass);
+            assert_ratio!(mass, heaviest_living_mass(lifetime), 1e-6);
+        }
+    }
+
+    #[test]
+    fn stars_end_as_dark_remnants() {
+        let state = evolve(2., 100.);
+        assert_eq!(state.phase, EvolutionaryPhase::Remnant);
+        assert_eq!(state.luminosity_in_solar_units, 0.);
+    }
+
+    #[test]
+    fn max_luminosity_bounds_all_ages_in_range() {
+        for mass in [0.3, 0.9, 1., 1.05, 3.] {
+            let bound = max_luminosity(mass, 9., 11.);
+            for i in 0..=100 {
+                let age = 9. + 2. * i as f64 / 100.;
+                assert!(evolve(mass, age).luminosity_in_solar_units <= bound);
+            }
+        }
+    }
+}
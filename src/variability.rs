@@ -0,0 +1,372 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    population::Population,
+    star::Star,
+    stellar_evolution::{radius_in_solar_radii, SOLAR_EFFECTIVE_TEMPERATURE_IN_K},
+};
+
+/// One astronomical unit, in units of solar radii.
+const AU_IN_SOLAR_RADII: f64 = 215.032;
+/// Fraction of the pulsation cycle during which Cepheids and RR Lyrae stars brighten.
+const RISING_BRANCH_FRACTION: f64 = 0.2;
+
+/// The kind of variability of a star.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VariabilityClass {
+    /// A pulsating supergiant in the instability strip.
+    ClassicalCepheid,
+    /// A pulsating horizontal branch star in the instability strip.
+    RrLyrae,
+    /// A pulsating main sequence or subgiant star in the instability strip.
+    DeltaScuti,
+    /// A long period pulsating star on the asymptotic giant branch.
+    Mira,
+    /// A binary system whose components eclipse each other.
+    EclipsingBinary,
+}
+
+/// The light curve of a variable star.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#variable-stars>
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Variability {
+    /// The kind of variability.
+    pub class: VariabilityClass,
+    /// Period of the light curve, in units of days.
+    pub period_in_days: f64,
+    /// Peak-to-peak amplitude, or the depth of the primary eclipse, in units of magnitudes.
+    pub amplitude_in_mag: f64,
+    /// The phase of the light curve at the epoch of the catalogue, between 0 and 1.
+    pub phase_at_epoch: f64,
+    /// The shape of the light curve, for eclipsing binaries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eclipse: Option<Eclipse>,
+}
+
+/// The shape of the light curve of an eclipsing binary.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Eclipse {
+    /// Depth of the eclipse half a period after the primary one, in units of magnitudes.
+    pub secondary_depth_in_mag: f64,
+    /// Duration of each eclipse as a fraction of the period.
+    pub duration_in_phase: f64,
+}
+
+impl Variability {
+    /// The difference to the catalogue magnitude at the given time, in units of magnitudes.
+    ///
+    /// For pulsating stars the catalogue magnitude is the mean magnitude, for eclipsing binaries it is the magnitude outside of eclipses.
+    pub fn magnitude_offset(&self, time_in_days: f64) -> f64 {
+        let phase = (self.phase_at_epoch + time_in_days / self.period_in_days).rem_euclid(1.);
+        let amplitude = self.amplitude_in_mag;
+        match self.class {
+            VariabilityClass::ClassicalCepheid | VariabilityClass::RrLyrae => {
+                // A sawtooth with a steep rise to maximum brightness and a slow decline.
+                if phase < RISING_BRANCH_FRACTION {
+                    amplitude * (0.5 - phase / RISING_BRANCH_FRACTION)
+                } else {
+                    amplitude
+                        * (-0.5 + (phase - RISING_BRANCH_FRACTION) / (1. - RISING_BRANCH_FRACTION))
+                }
+            }
+            VariabilityClass::DeltaScuti | VariabilityClass::Mira => {
+                -0.5 * amplitude * (2. * PI * phase).cos()
+            }
+            VariabilityClass::EclipsingBinary => match self.eclipse {
+                Some(eclipse) => {
+                    let dip = |distance: f64, depth: f64| {
+                        let x = 2. * distance / eclipse.duration_in_phase;
+                        if x < 1. {
+                            depth * (1. - x * x)
+                        } else {
+                            0.
+                        }
+                    };
+                    let primary_distance = phase.min(1. - phase);
+                    dip(primary_distance, amplitude)
+                        + dip((phase - 0.5).abs(), eclipse.secondary_depth_in_mag)
+                }
+                None => 0.,
+            },
+        }
+    }
+}
+
+/// Assigns a variability class to stars in one of the instability regions of the HR diagram, or to eclipsing binaries.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#variable-stars>
+pub(crate) fn classify<R: Rng>(star: &Star, rng: &mut R) -> Option<Variability> {
+    pulsation(star, rng).or_else(|| eclipse(star, rng))
+}
+
+fn pulsation<R: Rng>(star: &Star, rng: &mut R) -> Option<Variability> {
+    if star.cooling_age_in_gyr.is_some() || star.luminosity_in_solar_units <= 0. {
+        return None;
+    }
+    let luminosity = star.luminosity_in_solar_units;
+    let temperature = star.effective_temperature_in_k;
+    let is_old = matches!(
+        star.population,
+        Population::ThickDisc(_) | Population::Spheroid | Population::Bulge
+    );
+    let class = if (5000. ..=6500.).contains(&temperature) && luminosity >= 300. {
+        VariabilityClass::ClassicalCepheid
+    } else if (6300. ..=8600.).contains(&temperature) && (2. ..=100.).contains(&luminosity) {
+        VariabilityClass::DeltaScuti
+    } else if temperature <= 3600. && luminosity >= 1000. {
+        VariabilityClass::Mira
+    } else if is_old && is_giant(star) && (30. ..=80.).contains(&luminosity) {
+        VariabilityClass::RrLyrae
+    } else {
+        return None;
+    };
+    let (period_in_days, amplitude_in_mag) = match class {
+        VariabilityClass::ClassicalCepheid => {
+            (pulsation_period(star, 0.036), rng.gen_range(0.3..1.5))
+        }
+        VariabilityClass::DeltaScuti => (
+            pulsation_period(star, 0.033),
+            10_f64.powf(rng.gen_range(-2.5..-0.5)),
+        ),
+        VariabilityClass::Mira => (pulsation_period(star, 0.09), rng.gen_range(2.5..8.)),
+        VariabilityClass::RrLyrae => (rng.gen_range(0.4..0.8), rng.gen_range(0.3..1.2)),
+        VariabilityClass::EclipsingBinary => return None,
+    };
+    Some(Variability {
+        class,
+        period_in_days,
+        amplitude_in_mag,
+        phase_at_epoch: rng.gen(),
+        eclipse: None,
+    })
+}
+
+/// Whether the star has left the main sequence, judged by being cooler and brighter than a main sequence star of its mass.
+fn is_giant(star: &Star) -> bool {
+    star.effective_temperature_in_k < SOLAR_EFFECTIVE_TEMPERATURE_IN_K
+        && star.luminosity_in_solar_units > 10. * star.mass_in_solar_masses.powi(4)
+}
+
+/// The period from the period-mean density relation `P sqrt(ρ / ρ_sun) = Q`, in units of days.
+fn pulsation_period(star: &Star, pulsation_constant_in_days: f64) -> f64 {
+    let radius = radius_in_solar_radii(
+        star.luminosity_in_solar_units,
+        star.effective_temperature_in_k,
+    );
+    let relative_density = star.mass_in_solar_masses / radius.powi(3);
+    pulsation_constant_in_days / relative_density.sqrt()
+}
+
+/// Samples whether the orbit of the first companion is seen edge-on enough for the stars to eclipse each other.
+fn eclipse<R: Rng>(star: &Star, rng: &mut R) -> Option<Variability> {
+    let companion = star.companions.first()?;
+    let orbit = companion.orbit?;
+    let radius = |s: &Star| {
+        if s.luminosity_in_solar_units > 0. {
+            radius_in_solar_radii(s.luminosity_in_solar_units, s.effective_temperature_in_k)
+        } else {
+            0.
+        }
+    };
+    let (r1, r2) = (radius(star), radius(companion));
+    let separation = orbit.semi_major_axis_in_au * AU_IN_SOLAR_RADII;
+    // For isotropic orientations the cosine of the inclination is uniform.
+    let cos_inclination: f64 = rng.gen();
+    if r1 + r2 >= separation || cos_inclination >= (r1 + r2) / separation {
+        return None;
+    }
+    let (f1, f2) = (
+        star.luminosity_in_solar_units.max(0.),
+        companion.luminosity_in_solar_units.max(0.),
+    );
+    let total = f1 + f2;
+    if total <= 0. {
+        return None;
+    }
+    let covered = |front: f64, back: f64| (front / back).powi(2).min(1.);
+    let depth = |blocked: f64| -2.5 * (1. - blocked / total).max(1e-10).log10();
+    let primary_depth = depth(f1 * covered(r2, r1));
+    let secondary_depth = depth(f2 * covered(r1, r2));
+    Some(Variability {
+        class: VariabilityClass::EclipsingBinary,
+        period_in_days: orbit.period_in_days,
+        amplitude_in_mag: primary_depth.max(secondary_depth),
+        phase_at_epoch: rng.gen(),
+        eclipse: Some(Eclipse {
+            secondary_depth_in_mag: primary_depth.min(secondary_depth),
+            duration_in_phase: (r1 + r2) / (PI * separation),
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        assert_diff, assert_ratio, coordinates::SkyCoordinates, kinematics::Astrometry,
+        multiplicity::Orbit, photometry::Magnitudes, population::Subpopulation,
+        stellar_evolution::evolve,
+    };
+
+    use super::*;
+
+    fn star(initial_mass: f64, age_in_gyr: f64, population: Population) -> Star {
+        let state = evolve(initial_mass, age_in_gyr);
+        Star {
+            position_in_pc: (0., 0., 0.),
+            velocity_in_km_per_s: (0., 0., 0.),
+            initial_mass_in_solar_masses: initial_mass,
+            mass_in_solar_masses: state.mass_in_solar_masses,
+            age_in_gyr,
+            metallicity_in_dex: 0.,
+            luminosity_in_solar_units: state.luminosity_in_solar_units,
+            effective_temperature_in_k: state.effective_temperature_in_k,
+            population,
+            cooling_age_in_gyr: None,
+            absolute_magnitudes: Magnitudes::default(),
+            apparent_magnitudes: Magnitudes::default(),
+            system_apparent_magnitudes: None,
+            visual_extinction_in_mag: 0.,
+            sky_coordinates: SkyCoordinates::default(),
+            astrometry: Astrometry::default(),
+            colour: None,
+            orbit: None,
+            companions: Vec::new(),
+            variability: None,
+            cluster_id: None,
+        }
+    }
+
+    fn with_state(luminosity: f64, temperature: f64, mass: f64, population: Population) -> Star {
+        Star {
+            luminosity_in_solar_units: luminosity,
+            effective_temperature_in_k: temperature,
+            mass_in_solar_masses: mass,
+            ..star(mass, 0., population)
+        }
+    }
+
+    const THIN_DISC: Population = Population::ThinDisc(Subpopulation::Alive);
+
+    #[test]
+    fn sun_is_not_variable() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        assert!(classify(&star(1., 4.6, THIN_DISC), &mut rng).is_none());
+    }
+
+    #[test]
+    fn cepheid_periods_are_days_to_weeks() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let cepheid = with_state(3000., 5800., 6., THIN_DISC);
+        let variability = classify(&cepheid, &mut rng).expect("The star is in the strip");
+        assert_eq!(VariabilityClass::ClassicalCepheid, variability.class);
+        assert!(variability.period_in_days > 2. && variability.period_in_days < 60.);
+    }
+
+    #[test]
+    fn delta_scuti_periods_are_hours() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let delta_scuti = with_state(10., 7500., 1.8, THIN_DISC);
+        let variability = classify(&delta_scuti, &mut rng).expect("The star is in the strip");
+        assert_eq!(VariabilityClass::DeltaScuti, variability.class);
+        assert!(variability.period_in_days > 0.02 && variability.period_in_days < 0.3);
+    }
+
+    #[test]
+    fn miras_are_cool_luminous_giants() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mira = with_state(3000., 3000., 1.2, THIN_DISC);
+        let variability = classify(&mira, &mut rng).expect("The star is a Mira");
+        assert_eq!(VariabilityClass::Mira, variability.class);
+        assert!(variability.period_in_days > 80. && variability.period_in_days < 1000.);
+        assert!(variability.amplitude_in_mag >= 2.5);
+    }
+
+    #[test]
+    fn rr_lyrae_stars_only_belong_to_old_populations() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let old = with_state(50., 4000., 0.8, Population::Spheroid);
+        let variability = classify(&old, &mut rng).expect("The star is an RR Lyrae");
+        assert_eq!(VariabilityClass::RrLyrae, variability.class);
+        let young = with_state(50., 4000., 0.8, THIN_DISC);
+        assert!(classify(&young, &mut rng).is_none());
+    }
+
+    #[test]
+    fn pulsating_light_curves_average_to_the_catalogue_magnitude() {
+        for class in [
+            VariabilityClass::ClassicalCepheid,
+            VariabilityClass::DeltaScuti,
+        ] {
+            let variability = Variability {
+                class,
+                period_in_days: 5.,
+                amplitude_in_mag: 1.,
+                phase_at_epoch: 0.3,
+                eclipse: None,
+            };
+            const N: usize = 1000;
+            let offsets: Vec<f64> = (0..N)
+                .map(|i| variability.magnitude_offset(5. * i as f64 / N as f64))
+                .collect();
+            let mean = offsets.iter().sum::<f64>() / N as f64;
+            let min = offsets.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = offsets.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            assert_diff!(0., mean, 1e-2);
+            assert_diff!(1., max - min, 1e-2);
+            assert_diff!(
+                variability.magnitude_offset(1.),
+                variability.magnitude_offset(6.),
+                1e-10
+            );
+        }
+    }
+
+    #[test]
+    fn eclipses_dim_twin_stars_by_0_75_magnitudes() {
+        let mut primary = star(1., 1., THIN_DISC);
+        let mut twin = primary.clone();
+        twin.orbit = Some(Orbit {
+            mass_ratio: 1.,
+            period_in_days: 1.,
+            eccentricity: 0.,
+            semi_major_axis_in_au: 0.02,
+            angular_separation_in_arcsec: 0.,
+            resolved: false,
+        });
+        primary.companions.push(twin);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut eclipsing = 0;
+        for _ in 0..1000 {
+            if let Some(variability) = classify(&primary, &mut rng) {
+                eclipsing += 1;
+                assert_eq!(VariabilityClass::EclipsingBinary, variability.class);
+                assert_diff!(2.5 * 2_f64.log10(), variability.amplitude_in_mag, 1e-10);
+                let eclipse = variability
+                    .eclipse
+                    .expect("Eclipsing binaries have eclipses");
+                let at_primary_eclipse = -variability.phase_at_epoch * variability.period_in_days;
+                assert_diff!(
+                    variability.amplitude_in_mag,
+                    variability.magnitude_offset(at_primary_eclipse),
+                    1e-10
+                );
+                let between = at_primary_eclipse + 0.25 * variability.period_in_days;
+                assert_eq!(0., variability.magnitude_offset(between));
+                assert!(eclipse.duration_in_phase < 0.5);
+            }
+        }
+        // Two solar radii at 0.02 AU are seen to eclipse with a probability of about 47%.
+        assert_ratio!(
+            2. / (0.02 * AU_IN_SOLAR_RADII),
+            eclipsing as f64 / 1000.,
+            0.1
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::{error::Error, fs, path::Path};
+
+use rand::Rng;
+
+use crate::{
+    initial_mass_function::InitialMassFunction,
+    interpolation::{bracket, grid_axis},
+    stellar_evolution::{heaviest_living_mass, lifetime_in_gyr},
+};
+
+/// The cooling tracks shipped with the crate.
+const DEFAULT_COOLING_TABLE: &str = include_str!("../data/white_dwarf_cooling.csv");
+
+/// The names of the columns of a cooling table, in order.
+const COLUMNS: [&str; 4] = [
+    "mass_in_solar_masses",
+    "cooling_age_in_gyr",
+    "log_luminosity_in_solar_units",
+    "effective_temperature_in_k",
+];
+
+/// The heaviest progenitor that still leaves a white dwarf, in units of solar masses.
+const MAX_PROGENITOR_MASS: f64 = 8.;
+/// Number of attempts to draw a progenitor before giving up.
+const MAX_PROGENITOR_ATTEMPTS: usize = 1000;
+/// Number of steps per axis used to integrate the mean white dwarf mass.
+const MEAN_MASS_INTEGRATION_STEPS: usize = 200;
+
+/// Luminosity and effective temperature of white dwarfs as a function of mass and cooling age.
+///
+/// Compare <https://github.com/TheComamba/UrsaLumi/blob/main/Documentation/Generation_Algorithm.md#white-dwarfs>
+#[derive(Debug, Clone)]
+pub(crate) struct CoolingTable {
+    masses: Vec<f64>,
+    log_cooling_ages: Vec<f64>,
+    /// `log10(L/L_sun)`, indexed by mass first and cooling age second.
+    log_luminosities: Vec<Vec<f64>>,
+    /// `log10(T_eff/K)`, indexed by mass first and cooling age second.
+    log_temperatures: Vec<Vec<f64>>,
+}
+
+impl CoolingTable {
+    /// Loads the cooling table from the given file, or the one shipped with the crate if none is given.
+    pub(crate) fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        match path {
+            Some(path) => Self::parse(&fs::read_to_string(path)?),
+            None => Self::parse(DEFAULT_COOLING_TABLE),
+        }
+    }
+
+    /// Parses a comma separated table with the columns mass, cooling age, log luminosity and effective temperature.
+    ///
+    /// Lines starting with `#` and the header line are ignored.
+    fn parse(content: &str) -> Result<Self, Box<dyn Error>> {
+        let mut rows = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("mass") {
+                continue;
+            }
+            let values = line
+                .split(',')
+                .map(|value| value.trim().parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()?;
+            if values.len() != COLUMNS.len() {
+                return Err(format!("Expected 4 columns in cooling table line '{}'", line).into());
+            }
+            if let Some((_, column)) = values.iter().zip(COLUMNS).find(|(v, _)| !v.is_finite()) {
+                return Err(format!(
+                    "The cooling table line '{}' has an invalid value in column {}",
+                    line, column
+                )
+                .into());
+            }
+            rows.push(values);
+        }
+
+        let masses = grid_axis(&rows, 0);
+        let ages = grid_axis(&rows, 1);
+        if masses.len() < 2 || ages.len() < 2 || ages[0] <= 0. {
+            return Err("The cooling table needs at least two masses and two positive ages".into());
+        }
+        if rows.len() != masses.len() * ages.len() {
+            return Err("The cooling table must contain every combination of mass and age".into());
+        }
+
+        let mut log_luminosities = vec![vec![f64::NAN; ages.len()]; masses.len()];
+        let mut log_temperatures = vec![vec![f64::NAN; ages.len()]; masses.len()];
+        for row in rows.iter() {
+            let position = |axis: &[f64], column: usize| {
+                axis.iter().position(|&v| v == row[column]).ok_or_else(|| {
+                    format!(
+                        "The value {} in column {} of the cooling table is not on its grid",
+                        row[column], COLUMNS[column]
+                    )
+                })
+            };
+            let i = position(&masses, 0)?;
+            let j = position(&ages, 1)?;
+            log_luminosities[i][j] = row[2];
+            log_temperatures[i][j] = row[3].log10();
+        }
+        if log_luminosities.iter().flatten().any(|v| v.is_nan()) {
+            return Err("The cooling table contains duplicate entries".into());
+        }
+
+        Ok(Self {
+            masses,
+            log_cooling_ages: ages.iter().map(|t| t.log10()).collect(),
+            log_luminosities,
+            log_temperatures,
+        })
+    }
+
+    /// Luminosity in solar units and effective temperature in Kelvin of a white dwarf.
+    ///
+    /// Values are interpolated linearly in mass and in the logarithm of the cooling age, and clamped at the edges of the table.
+    pub(crate) fn interpolate(
+        &self,
+        mass_in_solar_masses: f64,
+        cooling_age_in_gyr: f64,
+    ) -> (f64, f64) {
+        let (i, u) = bracket(&self.masses, mass_in_solar_masses);
+        let (j, v) = bracket(&self.log_cooling_ages, cooling_age_in_gyr.log10());
+        let bilinear = |grid: &Vec<Vec<f64>>| {
+            (1. - u) * ((1. - v) * grid[i][j] + v * grid[i][j + 1])
+                + u * ((1. - v) * grid[i + 1][j] + v * grid[i + 1][j + 1])
+        };
+        (
+            10_f64.powf(bilinear(&self.log_luminosities)),
+            10_f64.powf(bilinear(&self.log_temperatures)),
+        )
+    }
+
+    /// The highest luminosity found anywhere in the table, in units of solar luminosities.
+    pub(crate) fn max_luminosity(&self) -> f64 {
+        let max_log = self
+            .log_luminosities
+            .iter()
+            .flatten()
+            .fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+        10_f64.powf(max_log)
+    }
+}
+
+/// The mass of the white dwarf left behind by a progenitor of the given initial mass, in units of solar masses.
+///
+/// This is the linear initial-final mass relation of Kalirai et al. (2008).
+pub(crate) fn white_dwarf_mass(progenitor_mass_in_solar_masses: f64) -> f64 {
+    0.109 * progenitor_mass_in_solar_masses + 0.394
+}
+
+/// Draws the total age and the progenitor mass of a white dwarf.
+///
+/// Stars are assumed to have formed at a constant rate within the age range. Only progenitors that have died by the sampled age and are light enough to leave a white dwarf are accepted.
+pub(crate) fn sample_progenitor<R: Rng>(
+    imf: &InitialMassFunction,
+    youngest_in_gyr: f64,
+    oldest_in_gyr: f64,
+    rng: &mut R,
+) -> Option<(f64, f64)> {
+    let lightest = lightest_dead_mass(oldest_in_gyr, imf);
+    for _ in 0..MAX_PROGENITOR_ATTEMPTS {
+        let age = if oldest_in_gyr > youngest_in_gyr {
+            rng.gen_range(youngest_in_gyr..oldest_in_gyr)
+        } else {
+            youngest_in_gyr
+        };
+        let mass = imf.sample_above(lightest, rng);
+        if mass <= MAX_PROGENITOR_MASS && lifetime_in_gyr(mass) < age {
+            return Some((age, mass));
+        }
+    }
+    None
+}
+
+/// The expectation value of the white dwarf mass for the progenitor distribution of [`sample_progenitor`], in units of solar masses.
+pub(crate) fn mean_white_dwarf_mass(
+    imf: &InitialMassFunction,
+    youngest_in_gyr: f64,
+    oldest_in_gyr: f64,
+) -> f64 {
+    let mut weight = 0.;
+    let mut weighted_mass = 0.;
+    for step in 0..MEAN_MASS_INTEGRATION_STEPS {
+        let fraction = (step as f64 + 0.5) / MEAN_MASS_INTEGRATION_STEPS as f64;
+        let age = youngest_in_gyr + fraction * (oldest_in_gyr - youngest_in_gyr);
+        let lightest = lightest_dead_mass(age, imf);
+        if lightest >= MAX_PROGENITOR_MASS {
+            continue;
+        }
+        let ratio = MAX_PROGENITOR_MASS / lightest;
+        for mass_step in 0..MEAN_MASS_INTEGRATION_STEPS {
+            let lower =
+                lightest * ratio.powf(mass_step as f64 / MEAN_MASS_INTEGRATION_STEPS as f64);
+            let upper =
+                lightest * ratio.powf((mass_step + 1) as f64 / MEAN_MASS_INTEGRATION_STEPS as f64);
+            let probability = imf.fraction_above(lower) - imf.fraction_above(upper);
+            weight += probability;
+            weighted_mass += probability * white_dwarf_mass((lower + upper) / 2.);
+        }
+    }
+    if weight > 0. {
+        weighted_mass / weight
+    } else {
+        white_dwarf_mass(MAX_PROGENITOR_MASS)
+    }
+}
+
+/// The luminosities of white dwarfs on a grid over the progenitor distribution of [`sample_progenitor`], in units of solar luminosities, each with the probability of its grid cell.
+pub(crate) fn luminosity_grid(
+    imf: &InitialMassFunction,
+    youngest_in_gyr: f64,
+    oldest_in_gyr: f64,
+    cooling_table: &CoolingTable,
+) -> Vec<(f64, f64)> {
+    let mut grid = Vec::new();
+    for step in 0..MEAN_MASS_INTEGRATION_STEPS {
+        let fraction = (step as f64 + 0.5) / MEAN_MASS_INTEGRATION_STEPS as f64;
+        let age = youngest_in_gyr + fraction * (oldest_in_gyr - youngest_in_gyr);
+        let lightest = lightest_dead_mass(age, imf);
+        if lightest >= MAX_PROGENITOR_MASS {
+            continue;
+        }
+        let ratio = MAX_PROGENITOR_MASS / lightest;
+        for mass_step in 0..MEAN_MASS_INTEGRATION_STEPS {
+            let lower =
+                lightest * ratio.powf(mass_step as f64 / MEAN_MASS_INTEGRATION_STEPS as f64);
+            let upper =
+                lightest * ratio.powf((mass_step + 1) as f64 / MEAN_MASS_INTEGRATION_STEPS as f64);
+            let probability = imf.fraction_above(lower) - imf.fraction_above(upper);
+            let progenitor_mass = (lower + upper) / 2.;
+            let cooling_age_in_gyr = (age - lifetime_in_gyr(progenitor_mass)).max(0.);
+            let (luminosity, _) =
+                cooling_table.interpolate(white_dwarf_mass(progenitor_mass), cooling_age_in_gyr);
+            grid.push((luminosity, probability));
+        }
+    }
+    grid
+}
+
+/// The initial mass above which stars have died by the given age, in units of solar masses.
+fn lightest_dead_mass(age_in_gyr: f64, imf: &InitialMassFunction) -> f64 {
+    heaviest_living_mass(age_in_gyr).max(imf.min_mass())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        assert_ratio,
+        population::{Population, Subpopulation},
+    };
+
+    use super::*;
+
+    fn default_table() -> CoolingTable {
+        CoolingTable::load(None).expect("The shipped cooling table should be valid")
+    }
+
+    #[test]
+    fn white_dwarfs_fade_with_cooling_age() {
+        let table = default_table();
+        let (young, _) = table.interpolate(0.6, 0.1);
+        let (old, _) = table.interpolate(0.6, 10.);
+        assert!(old < young);
+    }
+
+    #[test]
+    fn interpolation_reproduces_grid_points() {
+        let table = default_table();
+        let (luminosity, temperature) = table.interpolate(0.6, 1.);
+        assert_ratio!(1e-3, luminosity, 0.05);
+        assert!(temperature > 5000. && temperature < 15000.);
+    }
+
+    #[test]
+    fn incomplete_tables_are_rejected() {
+        let content = "mass_in_solar_masses,cooling_age_in_gyr,log_luminosity_in_solar_units,effective_temperature_in_k
+0.6,0.1,-1,20000
+0.6,1,-3,9000
+0.8,0.1,-1,20000";
+        assert!(CoolingTable::parse(content).is_err());
+    }
+
+    #[test]
+    fn invalid_grid_values_are_rejected_with_their_column() {
+        let content = "mass_in_solar_masses,cooling_age_in_gyr,log_luminosity_in_solar_units,effective_temperature_in_k
+0.6,0.1,-1,20000
+0.6,NaN,-3,9000
+0.8,0.1,-1,20000
+0.8,1,-3,9000";
+        let Err(error) = CoolingTable::parse(content) else {
+            panic!("The table should be rejected");
+        };
+        assert!(error.to_string().contains("cooling_age_in_gyr"));
+    }
+
+    #[test]
+    fn mean_white_dwarf_mass_matches_sampling() {
+        const N: usize = 20_000;
+        let imf = InitialMassFunction::new(Population::ThinDisc(Subpopulation::WhiteDwarf));
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut total = 0.;
+        for _ in 0..N {
+            let (_, progenitor) =
+                sample_progenitor(&imf, 0., 10., &mut rng).expect("A progenitor should be found");
+            total += white_dwarf_mass(progenitor);
+        }
+        assert_ratio!(mean_white_dwarf_mass(&imf, 0., 10.), total / N as f64, 0.02);
+    }
+}